@@ -17,12 +17,14 @@ async fn main() -> Result<()> {
     println!("listening on http://{addr}");
 
     let custom_cors = cors::Config::new()
+        .allow_origins(["http://localhost:8080"])
         .allow_methods([Method::GET, Method::POST])
-        .credentials(true);
+        .credentials(true)
+        .build()?;
 
     let app = Router::new()
         .route("/", get(index).options(options))
-        // .with(cors::Config::default()); // Default CORS config
+        // .with(cors::Config::new().allow_origins(["*"]).build()?); // Any origin
         .with(custom_cors); // Our custom CORS config
 
     if let Err(e) = serve(listener, app).await {
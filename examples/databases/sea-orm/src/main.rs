@@ -16,7 +16,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let dir = env::var("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap();
 
     let app = Router::new()
-        .get("/", serve::File::new(dir.join("public/index.html")))
+        .get("/", serve::File::new(dir.join("public/index.html"))?)
         .get("/todos", api::list)
         .post("/todos", api::create)
         .put("/todos/:id", api::update)
@@ -16,10 +16,10 @@ async fn main() -> Result<()> {
 
     let app = Router::new()
         .get("/", index)
-        .get("/cargo.toml", serve::File::new(dir.join("Cargo.toml")))
+        .get("/cargo.toml", serve::File::new(dir.join("Cargo.toml"))?)
         .get(
             "/examples/*",
-            serve::Dir::new(dir.join("../../../examples")).listing(),
+            serve::Dir::new(dir.join("../../../examples"))?.listing(),
         )
         .any("/*", |_| async { Ok(Response::text("Welcome!")) });
 
@@ -1,13 +1,19 @@
 //! Built-in Middleware.
 
+#[cfg(feature = "auth")]
+pub mod auth;
 #[cfg(feature = "cookie")]
 pub mod cookie;
 #[cfg(feature = "cors")]
 pub mod cors;
 #[cfg(feature = "csrf")]
 pub mod csrf;
+#[cfg(feature = "forwarded")]
+pub mod forwarded;
 #[cfg(feature = "limits")]
 pub mod limits;
+#[cfg(feature = "secure-headers")]
+pub mod secure_headers;
 #[cfg(feature = "session")]
 pub mod session;
 
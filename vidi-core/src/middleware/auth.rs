@@ -0,0 +1,457 @@
+//! Authentication Middleware.
+//!
+//! The [`AuthMiddleware`] authenticates the request with an [`Authenticator`] and stores the
+//! [`Identity`] in the request, the built-in schemes are [`Basic`], [`Bearer`] and [`ApiKey`].
+//!
+//! ```
+//! use vidi_core::{
+//!     Result,
+//!     middleware::auth::{self, Bearer, Identity},
+//! };
+//!
+//! #[derive(Clone)]
+//! struct User(String);
+//!
+//! let config = auth::Config::new(Bearer::new("vidi", |token: String| async move {
+//!     (token == "secret").then(|| User("vidi".to_string()))
+//! }));
+//!
+//! async fn me(Identity(user): Identity<User>) -> Result<String> {
+//!     Ok(user.0)
+//! }
+//! ```
+
+use std::{fmt, sync::Arc};
+
+use base64::Engine as _;
+
+use crate::{
+    Error, FromRequest, Future, Handler, IntoResponse, Request, RequestExt, Response, Result,
+    StatusCode, ThisError, Transform,
+    header::{AUTHORIZATION, HeaderName, HeaderValue, WWW_AUTHENTICATE},
+};
+
+/// An interface for authenticating the requests.
+pub trait Authenticator: Send + Sync + 'static {
+    /// The identity of the authenticated request.
+    type Identity: Clone + Send + Sync + 'static;
+
+    /// Authenticates the request.
+    ///
+    /// Returns `Ok(None)` if the request has no credentials of this scheme.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`AuthError::Invalid`] if the credentials are invalid.
+    fn authenticate(
+        &self,
+        req: &mut Request,
+    ) -> impl Future<Output = Result<Option<Self::Identity>, AuthError>> + Send;
+
+    /// The `WWW-Authenticate` challenge when the request has no credentials.
+    fn challenge(&self) -> Option<HeaderValue>;
+}
+
+/// Tries the first authenticator, then the second if the request has no credentials of the
+/// first one.
+impl<A, B> Authenticator for (A, B)
+where
+    A: Authenticator,
+    B: Authenticator<Identity = A::Identity>,
+{
+    type Identity = A::Identity;
+
+    async fn authenticate(&self, req: &mut Request) -> Result<Option<Self::Identity>, AuthError> {
+        match self.0.authenticate(req).await? {
+            Some(identity) => Ok(Some(identity)),
+            None => self.1.authenticate(req).await,
+        }
+    }
+
+    fn challenge(&self) -> Option<HeaderValue> {
+        match (self.0.challenge(), self.1.challenge()) {
+            (Some(a), Some(b)) => {
+                let mut value = a.as_bytes().to_vec();
+                value.extend_from_slice(b", ");
+                value.extend_from_slice(b.as_bytes());
+                HeaderValue::from_bytes(&value).ok()
+            }
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Authentication Error
+#[derive(Debug, ThisError)]
+pub enum AuthError {
+    /// 401, with the challenge.
+    #[error("missing credentials")]
+    Missing(Option<HeaderValue>),
+    /// 401, with the challenge.
+    #[error("invalid credentials")]
+    Invalid(Option<HeaderValue>),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let challenge = match &self {
+            Self::Missing(challenge) | Self::Invalid(challenge) => challenge.clone(),
+        };
+        let mut res = (StatusCode::UNAUTHORIZED, self.to_string()).into_response();
+        if let Some(challenge) = challenge {
+            res.headers_mut().insert(WWW_AUTHENTICATE, challenge);
+        }
+        res
+    }
+}
+
+impl From<AuthError> for Error {
+    fn from(e: AuthError) -> Self {
+        e.into_error()
+    }
+}
+
+/// The identity of the authenticated request.
+///
+/// Extracting it fails with `401 Unauthorized` if the request is not authenticated, use
+/// `Option<Identity<T>>` for the public but personalized routes.
+#[derive(Clone, Debug)]
+pub struct Identity<T>(pub T);
+
+impl<T> Identity<T> {
+    /// Consumes the extractor and returns the inner identity.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> FromRequest for Identity<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Error = AuthError;
+
+    async fn extract(req: &mut Request) -> Result<Self, Self::Error> {
+        let extensions = req.extensions();
+        extensions.get::<Self>().cloned().ok_or_else(|| {
+            AuthError::Missing(extensions.get::<Challenge>().and_then(|c| c.0.clone()))
+        })
+    }
+}
+
+/// The challenge of the unauthenticated request on the optional routes.
+#[derive(Clone)]
+struct Challenge(Option<HeaderValue>);
+
+/// A configuration for [`AuthMiddleware`].
+pub struct Config<A> {
+    authenticator: Arc<A>,
+    optional: bool,
+}
+
+impl<A> Config<A> {
+    /// Creates a new config with the [`Authenticator`].
+    #[must_use]
+    pub fn new(authenticator: A) -> Self {
+        Self {
+            authenticator: Arc::new(authenticator),
+            optional: false,
+        }
+    }
+
+    /// Whether to let the requests without credentials through, `false` by default.
+    ///
+    /// The requests with invalid credentials are still rejected.
+    #[must_use]
+    pub const fn optional(mut self, optional: bool) -> Self {
+        self.optional = optional;
+        self
+    }
+}
+
+impl<A> Clone for Config<A> {
+    fn clone(&self) -> Self {
+        Self {
+            authenticator: self.authenticator.clone(),
+            optional: self.optional,
+        }
+    }
+}
+
+impl<A> fmt::Debug for Config<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("optional", &self.optional)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<H, A> Transform<H> for Config<A> {
+    type Output = AuthMiddleware<H, A>;
+
+    fn transform(&self, h: H) -> Self::Output {
+        AuthMiddleware {
+            h,
+            config: self.clone(),
+        }
+    }
+}
+
+/// Authentication middleware.
+#[derive(Debug)]
+pub struct AuthMiddleware<H, A> {
+    h: H,
+    config: Config<A>,
+}
+
+impl<H, A> Clone for AuthMiddleware<H, A>
+where
+    H: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            h: self.h.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[crate::async_trait]
+impl<H, O, A> Handler<Request> for AuthMiddleware<H, A>
+where
+    H: Handler<Request, Output = Result<O>>,
+    O: IntoResponse,
+    A: Authenticator,
+{
+    type Output = Result<Response>;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let authenticator = self.config.authenticator.as_ref();
+
+        if let Some(identity) = authenticator.authenticate(&mut req).await? {
+            req.extensions_mut().insert(Identity(identity));
+        } else {
+            let challenge = authenticator.challenge();
+            if !self.config.optional {
+                return Err(AuthError::Missing(challenge).into());
+            }
+            req.extensions_mut().insert(Challenge(challenge));
+        }
+
+        self.h.call(req).await.map(IntoResponse::into_response)
+    }
+}
+
+/// Gets the credentials of the scheme from the `Authorization` header.
+fn credentials<'a>(req: &'a Request, scheme: &str) -> Option<&'a str> {
+    let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (name, credentials) = value.split_once(' ')?;
+    name.eq_ignore_ascii_case(scheme)
+        .then(|| credentials.trim())
+}
+
+fn challenge(value: String) -> Option<HeaderValue> {
+    HeaderValue::try_from(value).ok()
+}
+
+/// The [Basic][RFC7617] authentication scheme.
+///
+/// [RFC7617]: https://datatracker.ietf.org/doc/html/rfc7617
+pub struct Basic<F> {
+    realm: String,
+    verify: F,
+}
+
+impl<F> Basic<F> {
+    /// Creates a new scheme with the realm and the function which verifies the username and
+    /// the password.
+    pub fn new(realm: impl Into<String>, verify: F) -> Self {
+        Self {
+            realm: realm.into(),
+            verify,
+        }
+    }
+}
+
+impl<F> fmt::Debug for Basic<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Basic")
+            .field("realm", &self.realm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, Fut, T> Authenticator for Basic<F>
+where
+    F: Fn(String, String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<T>> + Send,
+    T: Clone + Send + Sync + 'static,
+{
+    type Identity = T;
+
+    async fn authenticate(&self, req: &mut Request) -> Result<Option<Self::Identity>, AuthError> {
+        let Some(credentials) = credentials(req, "Basic") else {
+            return Ok(None);
+        };
+
+        let (username, password) = base64::engine::general_purpose::STANDARD
+            .decode(credentials)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .and_then(|s| {
+                s.split_once(':')
+                    .map(|(u, p)| (u.to_string(), p.to_string()))
+            })
+            .ok_or_else(|| AuthError::Invalid(self.challenge()))?;
+
+        (self.verify)(username, password)
+            .await
+            .map(Some)
+            .ok_or_else(|| AuthError::Invalid(self.challenge()))
+    }
+
+    fn challenge(&self) -> Option<HeaderValue> {
+        challenge(format!(r#"Basic realm="{}", charset="UTF-8""#, self.realm))
+    }
+}
+
+/// The [Bearer][RFC6750] authentication scheme.
+///
+/// [RFC6750]: https://datatracker.ietf.org/doc/html/rfc6750
+pub struct Bearer<F> {
+    realm: String,
+    verify: F,
+}
+
+impl<F> Bearer<F> {
+    /// Creates a new scheme with the realm and the function which verifies the token.
+    pub fn new(realm: impl Into<String>, verify: F) -> Self {
+        Self {
+            realm: realm.into(),
+            verify,
+        }
+    }
+}
+
+impl<F> fmt::Debug for Bearer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bearer")
+            .field("realm", &self.realm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, Fut, T> Authenticator for Bearer<F>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<T>> + Send,
+    T: Clone + Send + Sync + 'static,
+{
+    type Identity = T;
+
+    async fn authenticate(&self, req: &mut Request) -> Result<Option<Self::Identity>, AuthError> {
+        let Some(token) = credentials(req, "Bearer").map(ToString::to_string) else {
+            return Ok(None);
+        };
+
+        (self.verify)(token).await.map(Some).ok_or_else(|| {
+            AuthError::Invalid(challenge(format!(
+                r#"Bearer realm="{}", error="invalid_token""#,
+                self.realm
+            )))
+        })
+    }
+
+    fn challenge(&self) -> Option<HeaderValue> {
+        challenge(format!(r#"Bearer realm="{}""#, self.realm))
+    }
+}
+
+/// The location of the API key.
+#[derive(Clone, Debug)]
+pub enum KeyLocation {
+    /// Via Header.
+    Header(HeaderName),
+    /// Via Query.
+    Query(String),
+    /// Via Cookie.
+    #[cfg(feature = "cookie")]
+    Cookie(String),
+}
+
+/// The API key authentication scheme, the key is read from a header, a query parameter or a
+/// cookie.
+pub struct ApiKey<F> {
+    location: KeyLocation,
+    verify: F,
+}
+
+impl<F> ApiKey<F> {
+    /// Creates a new scheme with the location and the function which verifies the key.
+    pub const fn new(location: KeyLocation, verify: F) -> Self {
+        Self { location, verify }
+    }
+
+    /// Reads the key from the header, e.g. `x-api-key`.
+    pub const fn header(name: HeaderName, verify: F) -> Self {
+        Self::new(KeyLocation::Header(name), verify)
+    }
+
+    /// Reads the key from the query parameter, e.g. `api_key`.
+    pub fn query(name: impl Into<String>, verify: F) -> Self {
+        Self::new(KeyLocation::Query(name.into()), verify)
+    }
+
+    /// Reads the key from the cookie, which requires the cookie middleware.
+    #[cfg(feature = "cookie")]
+    pub fn cookie(name: impl Into<String>, verify: F) -> Self {
+        Self::new(KeyLocation::Cookie(name.into()), verify)
+    }
+
+    fn key(&self, req: &Request) -> Option<String> {
+        match &self.location {
+            KeyLocation::Header(name) => req.header(name),
+            KeyLocation::Query(name) => {
+                serde_urlencoded::from_str::<Vec<(String, String)>>(req.query_string()?)
+                    .ok()?
+                    .into_iter()
+                    .find_map(|(k, v)| (&k == name).then_some(v))
+            }
+            #[cfg(feature = "cookie")]
+            KeyLocation::Cookie(name) => req.cookie(name).map(|c| c.value().to_string()),
+        }
+        .filter(|key| !key.is_empty())
+    }
+}
+
+impl<F> fmt::Debug for ApiKey<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKey")
+            .field("location", &self.location)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, Fut, T> Authenticator for ApiKey<F>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<T>> + Send,
+    T: Clone + Send + Sync + 'static,
+{
+    type Identity = T;
+
+    async fn authenticate(&self, req: &mut Request) -> Result<Option<Self::Identity>, AuthError> {
+        let Some(key) = self.key(req) else {
+            return Ok(None);
+        };
+
+        (self.verify)(key)
+            .await
+            .map(Some)
+            .ok_or(AuthError::Invalid(None))
+    }
+
+    fn challenge(&self) -> Option<HeaderValue> {
+        None
+    }
+}
@@ -9,12 +9,12 @@ use crate::{
 };
 
 #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-use crate::types::CookieKey;
+use crate::types::{CookieKey, CookieKeyring};
 
 /// A configure for [`CookieMiddleware`].
 pub struct Config {
     #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-    key: std::sync::Arc<CookieKey>,
+    keyring: std::sync::Arc<CookieKeyring>,
 }
 
 impl Config {
@@ -22,8 +22,18 @@ impl Config {
     #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
     #[must_use]
     pub fn with_key(key: CookieKey) -> Self {
+        Self::with_keyring(CookieKeyring::new(key))
+    }
+
+    /// Creates a new config with the [`CookieKeyring`].
+    ///
+    /// The incoming cookies which were issued with a retired key can still be read, they are
+    /// re-issued with the primary key by the `private` and `signed` getters of [`Cookies`].
+    #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
+    #[must_use]
+    pub fn with_keyring(keyring: CookieKeyring) -> Self {
         Self {
-            key: std::sync::Arc::new(key),
+            keyring: std::sync::Arc::new(keyring),
         }
     }
 }
@@ -32,7 +42,7 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-            key: std::sync::Arc::new(CookieKey::generate()),
+            keyring: std::sync::Arc::new(CookieKeyring::new(CookieKey::generate())),
         }
     }
 }
@@ -42,7 +52,7 @@ impl fmt::Debug for Config {
         let mut d = f.debug_struct("CookieConfig");
 
         #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-        d.field("key", &[..]);
+        d.field("keyring", &self.keyring);
 
         d.finish()
     }
@@ -58,7 +68,7 @@ where
         CookieMiddleware {
             h,
             #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-            key: self.key.clone(),
+            keyring: self.keyring.clone(),
         }
     }
 }
@@ -68,7 +78,7 @@ where
 pub struct CookieMiddleware<H> {
     h: H,
     #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-    key: std::sync::Arc<CookieKey>,
+    keyring: std::sync::Arc<CookieKeyring>,
 }
 
 impl<H> fmt::Debug for CookieMiddleware<H> {
@@ -76,9 +86,9 @@ impl<H> fmt::Debug for CookieMiddleware<H> {
         let mut d = f.debug_struct("CookieMiddleware");
 
         #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-        d.field("key", &[..]);
+        d.field("keyring", &self.keyring);
 
-        d.finish()
+        d.finish_non_exhaustive()
     }
 }
 
@@ -101,7 +111,7 @@ where
 
         let cookies = Cookies::new(jar);
         #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-        let cookies = cookies.with_key(self.key.clone());
+        let cookies = cookies.with_keyring(self.keyring.clone());
 
         req.extensions_mut().insert::<Cookies>(cookies.clone());
 
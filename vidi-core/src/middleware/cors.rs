@@ -1,13 +1,16 @@
 //! CORS Middleware.
 
+#[cfg(feature = "params")]
+use std::collections::HashMap;
 use std::{collections::HashSet, fmt, sync::Arc};
 
 use crate::{
-    Handler, IntoResponse, Method, Request, RequestExt, Response, Result, StatusCode, Transform,
+    Error, Handler, IntoResponse, Method, Request, RequestExt, Response, Result, StatusCode,
+    ThisError, Transform,
     header::{
         ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
-        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD,
-        HeaderMap, HeaderName, HeaderValue, ORIGIN, VARY,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+        ACCESS_CONTROL_REQUEST_METHOD, HeaderMap, HeaderName, HeaderValue, ORIGIN, VARY,
     },
     headers::{
         AccessControlAllowHeaders, AccessControlAllowMethods, AccessControlExposeHeaders,
@@ -15,15 +18,30 @@ use crate::{
     },
 };
 
-/// A configuration for [`CorsMiddleware`].
+#[cfg(feature = "params")]
+use crate::types::RouteInfo;
+
+/// The `Access-Control-Request-Private-Network` header.
+pub const ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-request-private-network");
+
+/// The `Access-Control-Allow-Private-Network` header.
+pub const ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK: HeaderName =
+    HeaderName::from_static("access-control-allow-private-network");
+
+/// A configuration for [`CorsMiddleware`], the [`Policies`] are built by
+/// [`build`][Self::build].
 pub struct Config {
     max_age: usize,
     credentials: bool,
+    private_network: bool,
     allow_methods: HashSet<Method>,
     allow_headers: HashSet<HeaderName>,
     allow_origins: HashSet<HeaderValue>,
     expose_headers: HashSet<HeaderName>,
     origin_verify: Option<Arc<dyn Fn(&HeaderValue) -> bool + Send + Sync>>,
+    #[cfg(feature = "params")]
+    routes: Vec<(String, Arc<dyn Fn(Self) -> Self + Send + Sync>)>,
 }
 
 impl Config {
@@ -44,6 +62,9 @@ impl Config {
 
     /// Whether to allow credentials. [MDN]
     ///
+    /// The credentials can only be allowed with the explicit origins or patterns, not with the
+    /// `*` origin, see [`validate`][Self::validate].
+    ///
     /// [MDN]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Access-Control-Allow-Credentials
     #[must_use]
     pub const fn credentials(mut self, credentials: bool) -> Self {
@@ -87,6 +108,11 @@ impl Config {
 
     /// Allowed origins. [MDN]
     ///
+    /// An origin can be `*` for any origin, or a pattern which `*` stands for one or more
+    /// characters of the host, e.g. `https://*.example.com`.
+    ///
+    /// Default is no origin, the cross-origin requests are rejected.
+    ///
     /// [MDN]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Access-Control-Allow-Origin
     #[must_use]
     pub fn allow_origins<H>(mut self, allow_origins: H) -> Self
@@ -128,6 +154,100 @@ impl Config {
         self.origin_verify = origin_verify;
         self
     }
+
+    /// Whether to allow the requests from public websites to the private network. [WICG]
+    ///
+    /// [WICG]: https://wicg.github.io/private-network-access/
+    #[must_use]
+    pub const fn private_network(mut self, private_network: bool) -> Self {
+        self.private_network = private_network;
+        self
+    }
+
+    /// Overrides the policy for the route with the specified pattern.
+    ///
+    /// The route policy is built from this configuration, so only the differences are needed,
+    /// e.g. `.route("/public/*", |c| c.allow_origins(["*"]).credentials(false))`.
+    #[cfg(feature = "params")]
+    #[must_use]
+    pub fn route<F>(mut self, pattern: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(Self) -> Self + Send + Sync + 'static,
+    {
+        self.routes.push((pattern.into(), Arc::new(f)));
+        self
+    }
+
+    /// Validates the configuration and the route policies.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`ConfigError::WildcardWithCredentials`] if the `*` origin is allowed
+    /// together with credentials, or [`ConfigError::CredentialsWithoutOrigins`] if the
+    /// credentials are allowed without any explicit origin.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.credentials {
+            if self.allow_origins.iter().any(|origin| origin == "*") {
+                return Err(ConfigError::WildcardWithCredentials);
+            }
+            if self.allow_origins.is_empty() {
+                return Err(ConfigError::CredentialsWithoutOrigins);
+            }
+        }
+
+        #[cfg(feature = "params")]
+        for (_, f) in &self.routes {
+            f(self.without_routes()).validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// Validates the configuration and builds the [`Policies`] which can be used as a
+    /// middleware.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`ConfigError`] if the configuration or a route policy is invalid, see
+    /// [`validate`][Self::validate].
+    pub fn build(&self) -> Result<Policies, ConfigError> {
+        self.validate()?;
+
+        Ok(Policies {
+            policy: Arc::new(Policy::new(self.clone())),
+            #[cfg(feature = "params")]
+            routes: Arc::new(
+                self.routes
+                    .iter()
+                    .map(|(pattern, f)| (pattern.clone(), Policy::new(f(self.without_routes()))))
+                    .collect(),
+            ),
+        })
+    }
+
+    #[cfg(feature = "params")]
+    fn without_routes(&self) -> Self {
+        let mut config = self.clone();
+        config.routes.clear();
+        config
+    }
+}
+
+/// Rejects with an error when the CORS configuration is invalid.
+#[derive(Debug, ThisError)]
+pub enum ConfigError {
+    /// The `*` origin is not allowed with credentials.
+    #[error("the `*` origin cannot be used with credentials")]
+    WildcardWithCredentials,
+    /// The credentials are not allowed without an explicit origin.
+    #[error("the credentials cannot be used without an explicit origin")]
+    CredentialsWithoutOrigins,
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Self::boxed(e)
+    }
 }
 
 impl Default for Config {
@@ -135,6 +255,7 @@ impl Default for Config {
         Self {
             max_age: 86400,
             credentials: false,
+            private_network: false,
             allow_methods: HashSet::from([
                 Method::GET,
                 Method::POST,
@@ -143,10 +264,12 @@ impl Default for Config {
                 Method::DELETE,
                 Method::PATCH,
             ]),
-            allow_origins: HashSet::from([HeaderValue::from_static("*")]),
+            allow_origins: HashSet::new(),
             allow_headers: HashSet::new(),
             expose_headers: HashSet::new(),
             origin_verify: None,
+            #[cfg(feature = "params")]
+            routes: Vec::new(),
         }
     }
 }
@@ -156,52 +279,197 @@ impl Clone for Config {
         Self {
             max_age: self.max_age,
             credentials: self.credentials,
+            private_network: self.private_network,
             allow_methods: self.allow_methods.clone(),
             allow_headers: self.allow_headers.clone(),
             allow_origins: self.allow_origins.clone(),
             expose_headers: self.expose_headers.clone(),
             origin_verify: self.origin_verify.clone(),
+            #[cfg(feature = "params")]
+            routes: self.routes.clone(),
         }
     }
 }
 
 impl fmt::Debug for Config {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("CorsConfig")
-            .field("max_age", &self.max_age)
+        let mut d = f.debug_struct("CorsConfig");
+
+        d.field("max_age", &self.max_age)
             .field("credentials", &self.credentials)
+            .field("private_network", &self.private_network)
             .field("allow_methods", &self.allow_methods)
             .field("allow_headers", &self.allow_headers)
             .field("allow_origins", &self.allow_origins)
-            .field("expose_headers", &self.expose_headers)
-            .finish_non_exhaustive()
+            .field("expose_headers", &self.expose_headers);
+
+        #[cfg(feature = "params")]
+        d.field(
+            "routes",
+            &self.routes.iter().map(|(p, _)| p).collect::<Vec<_>>(),
+        );
+
+        d.finish_non_exhaustive()
     }
 }
 
-impl<H> Transform<H> for Config {
+/// The validated CORS policies, built by [`Config::build`].
+#[derive(Clone, Debug)]
+pub struct Policies {
+    policy: Arc<Policy>,
+    #[cfg(feature = "params")]
+    routes: Arc<HashMap<String, Policy>>,
+}
+
+impl Policies {
+    #[cfg_attr(not(feature = "params"), allow(unused_variables))]
+    fn get(&self, req: &Request) -> &Policy {
+        #[cfg(feature = "params")]
+        if let Some(policy) = req
+            .extensions()
+            .get::<Arc<RouteInfo>>()
+            .and_then(|route| self.routes.get(&route.pattern))
+        {
+            return policy;
+        }
+
+        &self.policy
+    }
+}
+
+impl<H> Transform<H> for Policies {
     type Output = CorsMiddleware<H>;
 
     fn transform(&self, h: H) -> Self::Output {
         CorsMiddleware {
             h,
-            acam: self.allow_methods.clone().into_iter().collect(),
-            acah: self.allow_headers.clone().into_iter().collect(),
-            aceh: self.expose_headers.clone().into_iter().collect(),
-            config: self.clone(),
+            policies: self.clone(),
         }
     }
 }
 
-/// CORS middleware.
-#[derive(Clone, Debug)]
-pub struct CorsMiddleware<H> {
-    h: H,
+/// A resolved CORS policy.
+#[derive(Debug)]
+struct Policy {
     config: Config,
+    origins: Origins,
     acam: AccessControlAllowMethods,
     acah: AccessControlAllowHeaders,
     aceh: AccessControlExposeHeaders,
 }
 
+impl Policy {
+    fn new(config: Config) -> Self {
+        Self {
+            origins: Origins::new(&config.allow_origins),
+            acam: config.allow_methods.clone().into_iter().collect(),
+            acah: config.allow_headers.clone().into_iter().collect(),
+            aceh: config.expose_headers.clone().into_iter().collect(),
+            config,
+        }
+    }
+
+    fn allows(&self, origin: &HeaderValue) -> bool {
+        self.origins.allows(origin)
+            && self
+                .config
+                .origin_verify
+                .as_ref()
+                .is_none_or(|f| (f)(origin))
+    }
+}
+
+/// The allowed origins: any, exact values and patterns.
+#[derive(Debug)]
+struct Origins {
+    any: bool,
+    exact: HashSet<HeaderValue>,
+    patterns: Vec<Pattern>,
+}
+
+impl Origins {
+    fn new(allow_origins: &HashSet<HeaderValue>) -> Self {
+        let mut origins = Self {
+            any: false,
+            exact: HashSet::new(),
+            patterns: Vec::new(),
+        };
+
+        for origin in allow_origins {
+            match origin.to_str() {
+                Ok("*") => origins.any = true,
+                Ok(pattern) if pattern.contains('*') => {
+                    origins.patterns.push(Pattern::new(pattern));
+                }
+                _ => {
+                    origins.exact.insert(origin.clone());
+                }
+            }
+        }
+
+        origins
+    }
+
+    fn allows(&self, origin: &HeaderValue) -> bool {
+        self.any
+            || self.exact.contains(origin)
+            || origin
+                .to_str()
+                .is_ok_and(|origin| self.patterns.iter().any(|p| p.matches(origin)))
+    }
+}
+
+/// An origin pattern, the `*` matches one or more characters of the host.
+#[derive(Debug)]
+struct Pattern(Vec<String>);
+
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        Self(pattern.split('*').map(ToString::to_string).collect())
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        let Some((first, parts)) = self.0.split_first() else {
+            return false;
+        };
+        let Some(mut rest) = origin.strip_prefix(first.as_str()) else {
+            return false;
+        };
+
+        for (i, part) in parts.iter().enumerate() {
+            if i + 1 == parts.len() {
+                return rest
+                    .strip_suffix(part.as_str())
+                    .is_some_and(is_host_segment);
+            }
+
+            // finds the leftmost occurrence after at least one character
+            let Some(pos) = rest
+                .get(1..)
+                .and_then(|r| r.find(part.as_str()))
+                .map(|pos| pos + 1)
+            else {
+                return false;
+            };
+
+            if !is_host_segment(&rest[..pos]) {
+                return false;
+            }
+
+            rest = &rest[pos + part.len()..];
+        }
+
+        rest.is_empty()
+    }
+}
+
+/// CORS middleware.
+#[derive(Clone, Debug)]
+pub struct CorsMiddleware<H> {
+    h: H,
+    policies: Policies,
+}
+
 #[crate::async_trait]
 impl<H, O> Handler<Request> for CorsMiddleware<H>
 where
@@ -211,17 +479,21 @@ where
     type Output = Result<Response>;
 
     async fn call(&self, req: Request) -> Self::Output {
+        let policy = self.policies.get(&req);
+
         let Some(origin) = req.header(ORIGIN).filter(is_not_empty) else {
-            return self.h.call(req).await.map(IntoResponse::into_response);
+            return self
+                .h
+                .call(req)
+                .await
+                .map(IntoResponse::into_response)
+                .map(|mut resp| {
+                    vary(resp.headers_mut(), &[ORIGIN]);
+                    resp
+                });
         };
 
-        if !self.config.allow_origins.contains(&origin)
-            || !self
-                .config
-                .origin_verify
-                .as_ref()
-                .map_or(true, |f| (f)(&origin))
-        {
+        if !policy.allows(&origin) {
             return Err(StatusCode::FORBIDDEN.into_error());
         }
 
@@ -231,11 +503,11 @@ where
             if req
                 .header(ACCESS_CONTROL_REQUEST_METHOD)
                 .is_some_and(|method| {
-                    self.config.allow_methods.is_empty()
-                        || self.config.allow_methods.contains(&method)
+                    policy.config.allow_methods.is_empty()
+                        || policy.config.allow_methods.contains(&method)
                 })
             {
-                headers.typed_insert(self.acam.clone());
+                headers.typed_insert(policy.acam.clone());
             } else {
                 return Err((StatusCode::FORBIDDEN, "Invalid Preflight Request").into_error());
             }
@@ -249,7 +521,7 @@ where
                                 .map(str::as_bytes)
                                 .map(HeaderName::from_bytes)
                                 .filter_map(Result::ok)
-                                .any(|header| self.config.allow_headers.contains(&header))
+                                .any(|header| policy.config.allow_headers.contains(&header))
                         }),
                         Some(hs),
                     )
@@ -259,31 +531,51 @@ where
                 return Err((StatusCode::FORBIDDEN, "Invalid Preflight Request").into_error());
             }
 
-            if self.config.allow_headers.is_empty() {
+            if policy.config.allow_headers.is_empty() {
                 headers.insert(
                     ACCESS_CONTROL_ALLOW_HEADERS,
                     request_headers.unwrap_or(HeaderValue::from_static("*")),
                 );
             } else {
-                headers.typed_insert(self.acah.clone());
+                headers.typed_insert(policy.acah.clone());
+            }
+
+            headers.insert(ACCESS_CONTROL_MAX_AGE, policy.config.max_age.into());
+
+            if policy.config.private_network
+                && req
+                    .header(ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK)
+                    .is_some_and(|v: HeaderValue| v == "true")
+            {
+                headers.insert(
+                    ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK,
+                    HeaderValue::from_static("true"),
+                );
             }
 
             // 204 - no content
-            StatusCode::NO_CONTENT.into_response()
+            let mut resp = StatusCode::NO_CONTENT.into_response();
+            vary(
+                resp.headers_mut(),
+                &[
+                    ACCESS_CONTROL_REQUEST_METHOD,
+                    ACCESS_CONTROL_REQUEST_HEADERS,
+                    ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK,
+                ],
+            );
+            resp
         } else {
             // Simple Request
-            if !self.config.expose_headers.is_empty() {
-                headers.typed_insert(self.aceh.clone());
+            if !policy.config.expose_headers.is_empty() {
+                headers.typed_insert(policy.aceh.clone());
             }
 
             self.h.call(req).await.map(IntoResponse::into_response)?
         };
 
-        // https://github.com/rs/cors/issues/10
-        headers.insert(VARY, ORIGIN.into());
         headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
 
-        if self.config.credentials {
+        if policy.config.credentials {
             headers.insert(
                 ACCESS_CONTROL_ALLOW_CREDENTIALS,
                 HeaderValue::from_static("true"),
@@ -292,6 +584,9 @@ where
 
         resp.headers_mut().extend(headers);
 
+        // https://github.com/rs/cors/issues/10
+        vary(resp.headers_mut(), &[ORIGIN]);
+
         Ok(resp)
     }
 }
@@ -299,3 +594,27 @@ where
 fn is_not_empty(h: &HeaderValue) -> bool {
     !h.is_empty()
 }
+
+fn is_host_segment(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+}
+
+/// Appends the names to the `Vary` header, keeps the existing values.
+fn vary(headers: &mut HeaderMap, names: &[HeaderName]) {
+    for name in names {
+        let exists = headers
+            .get_all(VARY)
+            .iter()
+            .map(HeaderValue::to_str)
+            .filter_map(Result::ok)
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .any(|v| v == "*" || v.eq_ignore_ascii_case(name.as_str()));
+
+        if !exists {
+            headers.append(VARY, name.clone().into());
+        }
+    }
+}
@@ -3,35 +3,62 @@
 use std::{collections::HashSet, fmt, sync::Arc};
 
 use base64::Engine as _;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
 
 use crate::{
-    Error, FromRequest, Handler, IntoResponse, Method, Request, RequestExt, Response, Result,
-    StatusCode, Transform,
-    header::{HeaderName, HeaderValue, VARY},
+    Body, BodyState, Bytes, Error, FromRequest, Handler, IntoResponse, Method, Request, RequestExt,
+    Response, Result, StatusCode, Transform,
+    header::{HOST, HeaderName, HeaderValue, ORIGIN, REFERER, VARY},
     middleware::helper::{CookieOptions, Cookieable},
+    types::PayloadError,
 };
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Inner<S, G, V> {
     store: Store,
+    mode: Mode,
     ignored_methods: HashSet<Method>,
+    trusted_origins: HashSet<HeaderValue>,
     cookie_options: CookieOptions,
     header: HeaderName,
+    field: Option<&'static str>,
     secret: S,
     generate: G,
     verify: V,
 }
 
 /// The CSRF token source that is cookie or session.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Store {
     /// Via Cookie.
     Cookie,
+    /// Via Signed Cookie, the stateless double-submit cookie pattern.
+    #[cfg(feature = "cookie-signed")]
+    SignedCookie,
     #[cfg(feature = "session")]
     /// Via Session.
     Session,
 }
 
+/// The CSRF protection mode.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Mode {
+    /// Verifies the token which is issued via the [`Store`], by default.
+    #[default]
+    Token,
+    /// Verifies the `Origin` header, or the `Referer` header if it is missing, against the
+    /// scheme and the `Host` header of the request and the trusted origins.
+    Origin,
+    /// Verifies the `Sec-Fetch-Site` header, falls back to [`Mode::Origin`] if it is missing.
+    ///
+    /// The `same-origin` and `none` requests are always allowed, the `cross-site` requests are
+    /// always rejected.
+    FetchMetadata {
+        /// Whether to allow the `same-site` requests.
+        same_site: bool,
+    },
+}
+
 /// Extracts CSRF token via cookie or session.
 #[derive(Clone, Debug)]
 pub struct CsrfToken(pub String);
@@ -59,6 +86,9 @@ where
     /// The name of CSRF header.
     pub const CSRF_TOKEN: &'static str = "x-csrf-token";
 
+    /// The maximum size of the body that is buffered when reading the token from a field.
+    pub const FIELD_BODY_LIMIT: usize = 1024 * 1024 * 2;
+
     /// Creates a new configuration.
     pub fn new(
         store: Store,
@@ -75,7 +105,10 @@ where
             secret,
             generate,
             verify,
+            mode: Mode::Token,
+            trusted_origins: HashSet::new(),
             header: HeaderName::from_static(Self::CSRF_TOKEN),
+            field: None,
         }))
     }
 
@@ -86,24 +119,14 @@ where
     pub fn get(&self, req: &Request) -> Result<Option<Vec<u8>>> {
         let inner = self.as_ref();
         match inner.store {
-            Store::Cookie => self
-                .get_cookie(&req.cookies()?)
-                .map(|c| c.value().to_string())
-                .map_or_else(
-                    || Ok(None),
-                    |raw_token| {
-                        base64::engine::general_purpose::URL_SAFE_NO_PAD
-                            .decode(raw_token)
-                            .ok()
-                            .filter(|b| b.len() == 64)
-                            .map(unmask::<32>)
-                            .map(Option::Some)
-                            .ok_or_else(|| {
-                                (StatusCode::INTERNAL_SERVER_ERROR, "Invalid csrf token")
-                                    .into_error()
-                            })
-                    },
-                ),
+            Store::Cookie => decode(self.get_cookie(&req.cookies()?)),
+            #[cfg(feature = "cookie-signed")]
+            Store::SignedCookie => {
+                let options = self.options();
+                decode(req.cookies()?.signed_get_with(options.name, |cookie| {
+                    options.into_cookie(cookie.value()).into_owned()
+                }))
+            }
             #[cfg(feature = "session")]
             Store::Session => req.session().get(inner.cookie_options.name),
         }
@@ -121,12 +144,62 @@ where
                 self.set_cookie(&req.cookies()?, token);
                 Ok(())
             }
+            #[cfg(feature = "cookie-signed")]
+            Store::SignedCookie => {
+                req.cookies()?.signed_add(self.options().into_cookie(token));
+                Ok(())
+            }
             #[cfg(feature = "session")]
             Store::Session => req.session().set(inner.cookie_options.name, secret),
         }
     }
 }
 
+impl<S, G, V> Config<S, G, V>
+where
+    S: Clone,
+    G: Clone,
+    V: Clone,
+{
+    /// Sets the protection [`Mode`], [`Mode::Token`] by default.
+    #[must_use]
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.inner_mut().mode = mode;
+        self
+    }
+
+    /// Sets the origins which are trusted by [`Mode::Origin`] besides the request's host,
+    /// e.g. `https://vidi.rs`.
+    #[must_use]
+    pub fn trusted_origins<H>(mut self, trusted_origins: H) -> Self
+    where
+        H: IntoIterator,
+        H::Item: TryInto<HeaderValue>,
+    {
+        self.inner_mut().trusted_origins = trusted_origins
+            .into_iter()
+            .map(TryInto::try_into)
+            .filter_map(Result::ok)
+            .collect();
+        self
+    }
+
+    /// Reads the token from the field of an `application/x-www-form-urlencoded` or a
+    /// `multipart/form-data` body when the CSRF header is missing, e.g. `_csrf`.
+    ///
+    /// The body is buffered up to [`FIELD_BODY_LIMIT`][Self::FIELD_BODY_LIMIT] and restored
+    /// for the handler.
+    #[must_use]
+    pub fn field(mut self, name: &'static str) -> Self {
+        self.inner_mut().field.replace(name);
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner<S, G, V> {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
 impl<S, G, V> Clone for Config<S, G, V> {
     fn clone(&self) -> Self {
         Self(self.0.clone())
@@ -148,7 +221,11 @@ impl<S, G, V> AsRef<Inner<S, G, V>> for Config<S, G, V> {
 impl<S, G, V> fmt::Debug for Config<S, G, V> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("CsrfConfig")
+            .field("store", &self.as_ref().store)
+            .field("mode", &self.as_ref().mode)
             .field("header", &self.as_ref().header)
+            .field("field", &self.as_ref().field)
+            .field("trusted_origins", &self.as_ref().trusted_origins)
             .field("cookie_options", &self.as_ref().cookie_options)
             .field("ignored_methods", &self.as_ref().ignored_methods)
             .finish()
@@ -197,14 +274,48 @@ where
     type Output = Result<Response>;
 
     async fn call(&self, mut req: Request) -> Self::Output {
-        let mut secret = self.config.get(&req)?;
-
         let config = self.config.as_ref();
+        let safe = config.ignored_methods.contains(req.method());
+
+        match config.mode {
+            Mode::Token => {}
+            Mode::Origin => {
+                if !safe && !verify_origin(&req, &config.trusted_origins) {
+                    return Err(forbidden());
+                }
+                return self.h.call(req).await.map(IntoResponse::into_response);
+            }
+            Mode::FetchMetadata { same_site } => {
+                if !safe {
+                    let allowed = match req.header::<_, String>(SEC_FETCH_SITE).as_deref() {
+                        Some("same-origin" | "none") => true,
+                        Some("same-site") => same_site,
+                        Some(_) => false,
+                        None => verify_origin(&req, &config.trusted_origins),
+                    };
+                    if !allowed {
+                        return Err(forbidden());
+                    }
+                }
+                return self.h.call(req).await.map(IntoResponse::into_response);
+            }
+        }
 
-        if !config.ignored_methods.contains(req.method()) {
+        let mut secret = self.config.get(&req)?;
+
+        if !safe {
             let mut forbidden = true;
             if let Some(secret) = secret.take() {
-                if let Some(raw_token) = req.header(&config.header) {
+                let raw_token = match req.header(&config.header) {
+                    Some(raw_token) => Some(raw_token),
+                    None => match config.field {
+                        Some(name) => {
+                            read_field(&mut req, name, Config::<S, G, V>::FIELD_BODY_LIMIT).await?
+                        }
+                        None => None,
+                    },
+                };
+                if let Some(raw_token) = raw_token {
                     forbidden = !(config.verify)(&secret, raw_token);
                 }
             }
@@ -226,12 +337,135 @@ where
             .map(IntoResponse::into_response)
             .map(|mut res| {
                 res.headers_mut()
-                    .insert(VARY, HeaderValue::from_static("Cookie"));
+                    .append(VARY, HeaderValue::from_static("Cookie"));
                 res
             })
     }
 }
 
+/// The `Sec-Fetch-Site` header.
+const SEC_FETCH_SITE: HeaderName = HeaderName::from_static("sec-fetch-site");
+
+fn forbidden() -> Error {
+    (StatusCode::FORBIDDEN, "Cross-site request forbidden").into_error()
+}
+
+/// Verifies the `Origin` header, or the origin of the `Referer` header, against the scheme and
+/// the `Host` header of the request and the trusted origins.
+///
+/// The scheme is taken from the URI, e.g. set by the `forwarded` middleware, or the TLS
+/// connection, otherwise it is `http`.
+fn verify_origin(req: &Request, trusted_origins: &HashSet<HeaderValue>) -> bool {
+    let Some(origin) = req
+        .header::<_, String>(ORIGIN)
+        .filter(|origin| origin != "null")
+        .or_else(|| {
+            req.header::<_, http::Uri>(REFERER)
+                .and_then(|uri| Some(format!("{}://{}", uri.scheme_str()?, uri.authority()?)))
+        })
+    else {
+        return false;
+    };
+
+    if trusted_origins
+        .iter()
+        .any(|trusted| trusted == origin.as_str())
+    {
+        return true;
+    }
+
+    let host = req
+        .header::<_, String>(HOST)
+        .or_else(|| req.uri().authority().map(ToString::to_string));
+    let scheme = req.schema().map_or("http", http::uri::Scheme::as_str);
+
+    origin.split_once("://").is_some_and(|(s, authority)| {
+        s.eq_ignore_ascii_case(scheme)
+            && host.is_some_and(|host| host.eq_ignore_ascii_case(authority))
+    })
+}
+
+/// Reads the token from the field of the form body, the body is restored for the handler.
+async fn read_field(req: &mut Request, name: &str, limit: usize) -> Result<Option<String>> {
+    let Some(m) = req.content_type() else {
+        return Ok(None);
+    };
+
+    let urlencoded = m == mime::APPLICATION_WWW_FORM_URLENCODED;
+    let multipart = m.type_() == mime::MULTIPART && m.subtype() == mime::FORM_DATA;
+
+    if !urlencoded && !multipart {
+        return Ok(None);
+    }
+
+    let bytes = Limited::new(req.incoming()?, limit)
+        .collect()
+        .await
+        .map_err(|err| {
+            if err.is::<LengthLimitError>() {
+                PayloadError::TooLarge
+            } else {
+                PayloadError::Read
+            }
+        })?
+        .to_bytes();
+
+    *req.body_mut() = Body::Full(Full::new(bytes.clone()));
+    req.extensions_mut().remove::<BodyState>();
+
+    if urlencoded {
+        return Ok(form_urlencoded_field(&bytes, name));
+    }
+
+    #[cfg(feature = "multipart")]
+    {
+        use futures_util::TryStreamExt;
+
+        let boundary = m
+            .get_param(mime::BOUNDARY)
+            .ok_or(PayloadError::MissingBoundary)?;
+        let mut multipart =
+            crate::types::Multipart::new(Body::Full(Full::new(bytes)), boundary.as_str());
+
+        while let Some(mut field) = multipart.try_next().await? {
+            if field.name == name && field.filename.is_none() {
+                let value = field.bytes().await?;
+                return Ok(String::from_utf8(value.to_vec()).ok());
+            }
+            field.ignore().await?;
+        }
+    }
+
+    Ok(None)
+}
+
+/// Finds the value of the field, the token is URL-safe so it is not percent-decoded.
+fn form_urlencoded_field(bytes: &Bytes, name: &str) -> Option<String> {
+    std::str::from_utf8(bytes)
+        .ok()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(key, value)| (key == name).then(|| value.to_string()))
+}
+
+/// Decodes the secret from the token of the cookie.
+fn decode(cookie: Option<crate::types::Cookie<'_>>) -> Result<Option<Vec<u8>>> {
+    cookie.map(|c| c.value().to_string()).map_or_else(
+        || Ok(None),
+        |raw_token| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(raw_token)
+                .ok()
+                .filter(|b| b.len() == 64)
+                .map(unmask::<32>)
+                .map(Option::Some)
+                .ok_or_else(|| {
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Invalid csrf token").into_error()
+                })
+        },
+    )
+}
+
 /// Gets random secret
 ///
 /// # Errors
@@ -257,8 +491,7 @@ pub fn verify(secret: &[u8], raw_token: String) -> bool {
         .ok()
         .filter(|b| b.len() == 64)
         .map(unmask::<32>)
-        .filter(|t| t == secret)
-        .is_some()
+        .is_some_and(|t| t == secret)
 }
 
 /// Returns masked token
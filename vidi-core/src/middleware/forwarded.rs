@@ -0,0 +1,114 @@
+//! Forwarded Middleware.
+//!
+//! Reconstructs the client's scheme and host from the headers of the trusted proxies, and
+//! makes [`RealIp`][crate::types::RealIp] read the forwarded headers of the proxies.
+//!
+//! ```
+//! use vidi_core::{Request, RequestExt, Result, middleware::forwarded, types::TrustedProxies};
+//!
+//! let config =
+//!     forwarded::Config::new(TrustedProxies::new().cidrs(["10.0.0.0/8".parse().unwrap()]));
+//!
+//! async fn index(req: Request) -> Result<String> {
+//!     Ok(format!("{:?} {:?}", req.schema(), req.realip()))
+//! }
+//! ```
+
+use http::uri::{Authority, PathAndQuery, Scheme, Uri};
+
+use crate::{
+    Handler, IntoResponse, Request, Response, Result, Transform,
+    header::{HOST, HeaderValue},
+    types::TrustedProxies,
+};
+
+/// A configuration for [`ForwardedMiddleware`].
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    proxies: TrustedProxies,
+}
+
+impl Config {
+    /// Creates a new Config with the trusted proxies.
+    #[must_use]
+    pub const fn new(proxies: TrustedProxies) -> Self {
+        Self { proxies }
+    }
+}
+
+impl<H> Transform<H> for Config
+where
+    H: Clone,
+{
+    type Output = ForwardedMiddleware<H>;
+
+    fn transform(&self, h: H) -> Self::Output {
+        ForwardedMiddleware {
+            h,
+            config: self.clone(),
+        }
+    }
+}
+
+/// Forwarded middleware.
+#[derive(Clone, Debug)]
+pub struct ForwardedMiddleware<H> {
+    h: H,
+    config: Config,
+}
+
+#[crate::async_trait]
+impl<H, O> Handler<Request> for ForwardedMiddleware<H>
+where
+    H: Handler<Request, Output = Result<O>>,
+    O: IntoResponse,
+{
+    type Output = Result<Response>;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let forwarded = self.config.proxies.resolve(&req);
+
+        let host = forwarded
+            .host
+            .as_deref()
+            .and_then(|host| Authority::try_from(host).ok());
+        let scheme = forwarded
+            .proto
+            .as_deref()
+            .and_then(|proto| Scheme::try_from(proto).ok());
+
+        if let Some(host) = &host {
+            if let Ok(value) = HeaderValue::from_str(host.as_str()) {
+                req.headers_mut().insert(HOST, value);
+            }
+        }
+
+        let authority = host.or_else(|| {
+            req.uri().authority().cloned().or_else(|| {
+                req.headers()
+                    .get(HOST)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| Authority::try_from(v).ok())
+            })
+        });
+
+        // an absolute URI requires both the scheme and the authority
+        if let (Some(scheme), Some(authority)) =
+            (scheme.or_else(|| req.uri().scheme().cloned()), authority)
+        {
+            let mut parts = req.uri().clone().into_parts();
+            parts.scheme = Some(scheme);
+            parts.authority = Some(authority);
+            if parts.path_and_query.is_none() {
+                parts.path_and_query = Some(PathAndQuery::from_static("/"));
+            }
+            if let Ok(uri) = Uri::from_parts(parts) {
+                *req.uri_mut() = uri;
+            }
+        }
+
+        req.extensions_mut().insert(self.config.proxies.clone());
+
+        self.h.call(req).await.map(IntoResponse::into_response)
+    }
+}
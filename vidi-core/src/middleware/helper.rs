@@ -5,7 +5,7 @@ use std::time::Duration;
 use crate::types::{Cookie, Cookies, SameSite};
 
 /// Cookie's Options
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CookieOptions {
     /// Cookie's `name`, `viz.sid` by defaults
     pub name: &'static str,
@@ -152,8 +152,13 @@ pub trait Cookieable {
     fn options(&self) -> &CookieOptions;
 
     /// Gets a cookie from the cookies.
+    ///
+    /// A cookie encrypted with a retired key is re-issued with the primary key.
     fn get_cookie<'a>(&'a self, cookies: &'a Cookies) -> Option<Cookie<'a>> {
-        cookies.private_get(self.options().name)
+        let options = self.options();
+        cookies.private_get_with(options.name, |cookie| {
+            options.into_cookie(cookie.value()).into_owned()
+        })
     }
 
     /// Deletes a cookie from the cookies.
@@ -0,0 +1,843 @@
+//! Secure Headers Middleware.
+//!
+//! Sets the security related response headers, which are not set by the handler.
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use base64::Engine as _;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use serde::Deserialize;
+
+use crate::{
+    Error, FromRequest, Handler, IntoResponse, Request, RequestExt, Response, Result, StatusCode,
+    Transform,
+    header::{
+        CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY, HeaderMap, HeaderName,
+        HeaderValue, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+        X_FRAME_OPTIONS,
+    },
+    types::PayloadError,
+};
+
+/// The `Permissions-Policy` header.
+pub const PERMISSIONS_POLICY: HeaderName = HeaderName::from_static("permissions-policy");
+
+/// The `Cross-Origin-Opener-Policy` header.
+pub const CROSS_ORIGIN_OPENER_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-opener-policy");
+
+/// The `Cross-Origin-Embedder-Policy` header.
+pub const CROSS_ORIGIN_EMBEDDER_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-embedder-policy");
+
+/// The `Cross-Origin-Resource-Policy` header.
+pub const CROSS_ORIGIN_RESOURCE_POLICY: HeaderName =
+    HeaderName::from_static("cross-origin-resource-policy");
+
+/// A per-request nonce of the Content Security Policy, for the inline scripts and styles.
+///
+/// It is available when the policy contains [`Source::Nonce`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CspNonce(pub String);
+
+impl FromRequest for CspNonce {
+    type Error = Error;
+
+    async fn extract(req: &mut Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get()
+            .cloned()
+            .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "Missing csp nonce").into_error())
+    }
+}
+
+/// The [Strict-Transport-Security][MDN] policy.
+///
+/// [MDN]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Strict-Transport-Security
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hsts {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Hsts {
+    /// Creates a new policy with the `max-age`.
+    #[must_use]
+    pub const fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// Whether to apply to all subdomains.
+    #[must_use]
+    pub const fn include_subdomains(mut self, include_subdomains: bool) -> Self {
+        self.include_subdomains = include_subdomains;
+        self
+    }
+
+    /// Whether to request the inclusion in the browsers' preload lists.
+    #[must_use]
+    pub const fn preload(mut self, preload: bool) -> Self {
+        self.preload = preload;
+        self
+    }
+
+    fn to_header_value(&self) -> HeaderValue {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        HeaderValue::try_from(value).expect("hsts should be a valid header value")
+    }
+}
+
+impl Default for Hsts {
+    /// One year, including subdomains.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(31_536_000)).include_subdomains(true)
+    }
+}
+
+/// The [X-Frame-Options][MDN] policy.
+///
+/// [MDN]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-Frame-Options
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameOptions {
+    /// The page cannot be displayed in a frame.
+    #[default]
+    Deny,
+    /// The page can only be displayed in a frame on the same origin.
+    SameOrigin,
+}
+
+impl FrameOptions {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Deny => "DENY",
+            Self::SameOrigin => "SAMEORIGIN",
+        }
+    }
+}
+
+/// The directives of the Content Security Policy. [MDN]
+///
+/// [MDN]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy#directives
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Directive {
+    DefaultSrc,
+    ScriptSrc,
+    ScriptSrcElem,
+    ScriptSrcAttr,
+    StyleSrc,
+    StyleSrcElem,
+    StyleSrcAttr,
+    ImgSrc,
+    FontSrc,
+    ConnectSrc,
+    MediaSrc,
+    ObjectSrc,
+    FrameSrc,
+    ChildSrc,
+    WorkerSrc,
+    ManifestSrc,
+    BaseUri,
+    FormAction,
+    FrameAncestors,
+}
+
+impl Directive {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::DefaultSrc => "default-src",
+            Self::ScriptSrc => "script-src",
+            Self::ScriptSrcElem => "script-src-elem",
+            Self::ScriptSrcAttr => "script-src-attr",
+            Self::StyleSrc => "style-src",
+            Self::StyleSrcElem => "style-src-elem",
+            Self::StyleSrcAttr => "style-src-attr",
+            Self::ImgSrc => "img-src",
+            Self::FontSrc => "font-src",
+            Self::ConnectSrc => "connect-src",
+            Self::MediaSrc => "media-src",
+            Self::ObjectSrc => "object-src",
+            Self::FrameSrc => "frame-src",
+            Self::ChildSrc => "child-src",
+            Self::WorkerSrc => "worker-src",
+            Self::ManifestSrc => "manifest-src",
+            Self::BaseUri => "base-uri",
+            Self::FormAction => "form-action",
+            Self::FrameAncestors => "frame-ancestors",
+        }
+    }
+}
+
+/// The sources of the Content Security Policy directives. [MDN]
+///
+/// [MDN]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/Sources
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// `'none'`
+    None,
+    /// `'self'`
+    SelfOrigin,
+    /// `'unsafe-inline'`
+    UnsafeInline,
+    /// `'unsafe-eval'`
+    UnsafeEval,
+    /// `'strict-dynamic'`
+    StrictDynamic,
+    /// `'nonce-<base64>'`, generated for each request, see [`CspNonce`].
+    Nonce,
+    /// A scheme, e.g. `https:` or `data:`.
+    Scheme(&'static str),
+    /// A host, e.g. `cdn.example.com` or `https://*.example.com`.
+    Host(String),
+    /// A hash, e.g. `'sha256-<base64>'`.
+    Hash(String),
+}
+
+impl Source {
+    /// Whether the source is a single token, which can not inject the other sources or
+    /// directives.
+    fn is_valid(&self) -> bool {
+        match self {
+            Self::Scheme(scheme) => is_token(scheme),
+            Self::Host(host) => is_token(host),
+            Self::Hash(hash) => is_token(hash.trim_matches('\'')),
+            _ => true,
+        }
+    }
+
+    fn write(&self, value: &mut String, nonce: Option<&str>) {
+        match self {
+            Self::None => value.push_str("'none'"),
+            Self::SelfOrigin => value.push_str("'self'"),
+            Self::UnsafeInline => value.push_str("'unsafe-inline'"),
+            Self::UnsafeEval => value.push_str("'unsafe-eval'"),
+            Self::StrictDynamic => value.push_str("'strict-dynamic'"),
+            Self::Nonce => {
+                value.push_str("'nonce-");
+                value.push_str(nonce.unwrap_or_default());
+                value.push('\'');
+            }
+            Self::Scheme(scheme) => {
+                value.push_str(scheme.trim_end_matches(':'));
+                value.push(':');
+            }
+            Self::Host(host) => value.push_str(host),
+            Self::Hash(hash) => {
+                value.push('\'');
+                value.push_str(hash.trim_matches('\''));
+                value.push('\'');
+            }
+        }
+    }
+}
+
+/// A typed [Content-Security-Policy][MDN] builder.
+///
+/// [MDN]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Csp {
+    directives: Vec<(Directive, Vec<Source>)>,
+    upgrade_insecure_requests: bool,
+    report_uri: Option<String>,
+    report_to: Option<String>,
+}
+
+impl Csp {
+    /// Creates an empty policy.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the sources of a directive, replacing the previous ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a source is empty or contains `;`, `,`, `'` or the whitespaces.
+    #[must_use]
+    pub fn directive<I>(mut self, directive: Directive, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Source>,
+    {
+        let sources: Vec<_> = sources.into_iter().collect();
+        if let Some(source) = sources.iter().find(|source| !source.is_valid()) {
+            panic!("invalid content security policy source: {source:?}");
+        }
+        match self.directives.iter_mut().find(|(d, _)| *d == directive) {
+            Some((_, s)) => *s = sources,
+            None => self.directives.push((directive, sources)),
+        }
+        self
+    }
+
+    /// Sets the `default-src` directive.
+    #[must_use]
+    pub fn default_src<I>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Source>,
+    {
+        self.directive(Directive::DefaultSrc, sources)
+    }
+
+    /// Sets the `script-src` directive.
+    #[must_use]
+    pub fn script_src<I>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Source>,
+    {
+        self.directive(Directive::ScriptSrc, sources)
+    }
+
+    /// Sets the `style-src` directive.
+    #[must_use]
+    pub fn style_src<I>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Source>,
+    {
+        self.directive(Directive::StyleSrc, sources)
+    }
+
+    /// Sets the `img-src` directive.
+    #[must_use]
+    pub fn img_src<I>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Source>,
+    {
+        self.directive(Directive::ImgSrc, sources)
+    }
+
+    /// Sets the `connect-src` directive.
+    #[must_use]
+    pub fn connect_src<I>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Source>,
+    {
+        self.directive(Directive::ConnectSrc, sources)
+    }
+
+    /// Sets the `frame-ancestors` directive.
+    #[must_use]
+    pub fn frame_ancestors<I>(self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Source>,
+    {
+        self.directive(Directive::FrameAncestors, sources)
+    }
+
+    /// Adds the `upgrade-insecure-requests` directive.
+    #[must_use]
+    pub const fn upgrade_insecure_requests(mut self, upgrade: bool) -> Self {
+        self.upgrade_insecure_requests = upgrade;
+        self
+    }
+
+    /// Sets the `report-uri` directive, the endpoint can be served by [`ReportHandler`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the URI is empty or contains `;`, `,`, `'` or the whitespaces.
+    #[must_use]
+    pub fn report_uri(mut self, uri: impl Into<String>) -> Self {
+        let uri = uri.into();
+        assert!(
+            is_token(&uri),
+            "invalid content security policy report uri: {uri:?}"
+        );
+        self.report_uri.replace(uri);
+        self
+    }
+
+    /// Sets the `report-to` directive, the group of the `Reporting-Endpoints` header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the group is empty or contains `;`, `,`, `'` or the whitespaces.
+    #[must_use]
+    pub fn report_to(mut self, group: impl Into<String>) -> Self {
+        let group = group.into();
+        assert!(
+            is_token(&group),
+            "invalid content security policy report group: {group:?}"
+        );
+        self.report_to.replace(group);
+        self
+    }
+
+    /// Whether the policy requires a nonce.
+    fn has_nonce(&self) -> bool {
+        self.directives
+            .iter()
+            .any(|(_, sources)| sources.contains(&Source::Nonce))
+    }
+
+    fn to_header_value(&self, nonce: Option<&str>) -> HeaderValue {
+        HeaderValue::try_from(self.render(nonce)).expect("csp should be a valid header value")
+    }
+
+    /// Renders the policy with the nonce.
+    #[must_use]
+    pub fn render(&self, nonce: Option<&str>) -> String {
+        let mut value = String::new();
+        let push = |value: &mut String, directive: &str| {
+            if !value.is_empty() {
+                value.push_str("; ");
+            }
+            value.push_str(directive);
+        };
+
+        for (directive, sources) in &self.directives {
+            push(&mut value, directive.as_str());
+            for source in sources {
+                value.push(' ');
+                source.write(&mut value, nonce);
+            }
+        }
+        if self.upgrade_insecure_requests {
+            push(&mut value, "upgrade-insecure-requests");
+        }
+        if let Some(uri) = &self.report_uri {
+            push(&mut value, "report-uri ");
+            value.push_str(uri);
+        }
+        if let Some(group) = &self.report_to {
+            push(&mut value, "report-to ");
+            value.push_str(group);
+        }
+        value
+    }
+}
+
+/// Checks the value is a non-empty token of the visible ASCII characters, without the
+/// separators of the sources and the directives.
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !matches!(b, b';' | b',' | b'\''))
+}
+
+/// A configuration for [`SecureHeadersMiddleware`].
+///
+/// By default, it sets:
+///
+/// * `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// * `X-Content-Type-Options: nosniff`
+/// * `X-Frame-Options: DENY`
+/// * `Referrer-Policy: strict-origin-when-cross-origin`
+/// * `Cross-Origin-Opener-Policy: same-origin`
+#[derive(Clone, Debug)]
+pub struct Config {
+    hsts: Option<Hsts>,
+    csp: Option<Csp>,
+    csp_report_only: Option<Csp>,
+    nosniff: bool,
+    frame_options: Option<FrameOptions>,
+    referrer_policy: Option<HeaderValue>,
+    permissions_policy: Option<HeaderValue>,
+    coop: Option<HeaderValue>,
+    coep: Option<HeaderValue>,
+    corp: Option<HeaderValue>,
+}
+
+impl Config {
+    /// Creates a new [`Config`] with the default values.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new [`Config`] without any header.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self {
+            hsts: None,
+            csp: None,
+            csp_report_only: None,
+            nosniff: false,
+            frame_options: None,
+            referrer_policy: None,
+            permissions_policy: None,
+            coop: None,
+            coep: None,
+            corp: None,
+        }
+    }
+
+    /// Sets the `Strict-Transport-Security` header, `None` to disable.
+    #[must_use]
+    pub fn hsts(mut self, hsts: impl Into<Option<Hsts>>) -> Self {
+        self.hsts = hsts.into();
+        self
+    }
+
+    /// Sets the `Content-Security-Policy` header, `None` to disable.
+    #[must_use]
+    pub fn csp(mut self, csp: impl Into<Option<Csp>>) -> Self {
+        self.csp = csp.into();
+        self
+    }
+
+    /// Sets the `Content-Security-Policy-Report-Only` header, `None` to disable.
+    ///
+    /// The violations are reported but not enforced, it can be used along with
+    /// [`csp`][Self::csp] to try a new policy.
+    #[must_use]
+    pub fn csp_report_only(mut self, csp: impl Into<Option<Csp>>) -> Self {
+        self.csp_report_only = csp.into();
+        self
+    }
+
+    /// Whether to set `X-Content-Type-Options: nosniff`.
+    #[must_use]
+    pub const fn nosniff(mut self, nosniff: bool) -> Self {
+        self.nosniff = nosniff;
+        self
+    }
+
+    /// Sets the `X-Frame-Options` header, `None` to disable.
+    #[must_use]
+    pub const fn frame_options(mut self, frame_options: Option<FrameOptions>) -> Self {
+        self.frame_options = frame_options;
+        self
+    }
+
+    /// Sets the `Referrer-Policy` header, e.g. `no-referrer`.
+    #[must_use]
+    pub fn referrer_policy(mut self, policy: &'static str) -> Self {
+        self.referrer_policy = Some(HeaderValue::from_static(policy));
+        self
+    }
+
+    /// Sets the `Permissions-Policy` header, e.g. `camera=(), geolocation=(self)`.
+    #[must_use]
+    pub fn permissions_policy(mut self, policy: &'static str) -> Self {
+        self.permissions_policy = Some(HeaderValue::from_static(policy));
+        self
+    }
+
+    /// Sets the `Cross-Origin-Opener-Policy` header, e.g. `same-origin`.
+    #[must_use]
+    pub fn cross_origin_opener_policy(mut self, policy: &'static str) -> Self {
+        self.coop = Some(HeaderValue::from_static(policy));
+        self
+    }
+
+    /// Sets the `Cross-Origin-Embedder-Policy` header, e.g. `require-corp`.
+    #[must_use]
+    pub fn cross_origin_embedder_policy(mut self, policy: &'static str) -> Self {
+        self.coep = Some(HeaderValue::from_static(policy));
+        self
+    }
+
+    /// Sets the `Cross-Origin-Resource-Policy` header, e.g. `same-site`.
+    #[must_use]
+    pub fn cross_origin_resource_policy(mut self, policy: &'static str) -> Self {
+        self.corp = Some(HeaderValue::from_static(policy));
+        self
+    }
+
+    fn has_nonce(&self) -> bool {
+        self.csp.as_ref().is_some_and(Csp::has_nonce)
+            || self.csp_report_only.as_ref().is_some_and(Csp::has_nonce)
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, nonce: Option<&str>) {
+        let mut set = |name: HeaderName, value: Option<HeaderValue>| {
+            if let Some(value) = value {
+                headers.entry(name).or_insert(value);
+            }
+        };
+
+        set(
+            STRICT_TRANSPORT_SECURITY,
+            self.hsts.as_ref().map(Hsts::to_header_value),
+        );
+        set(
+            CONTENT_SECURITY_POLICY,
+            self.csp.as_ref().map(|csp| csp.to_header_value(nonce)),
+        );
+        set(
+            CONTENT_SECURITY_POLICY_REPORT_ONLY,
+            self.csp_report_only
+                .as_ref()
+                .map(|csp| csp.to_header_value(nonce)),
+        );
+        set(
+            X_CONTENT_TYPE_OPTIONS,
+            self.nosniff.then(|| HeaderValue::from_static("nosniff")),
+        );
+        set(
+            X_FRAME_OPTIONS,
+            self.frame_options
+                .map(|f| HeaderValue::from_static(f.as_str())),
+        );
+        set(REFERRER_POLICY, self.referrer_policy.clone());
+        set(PERMISSIONS_POLICY, self.permissions_policy.clone());
+        set(CROSS_ORIGIN_OPENER_POLICY, self.coop.clone());
+        set(CROSS_ORIGIN_EMBEDDER_POLICY, self.coep.clone());
+        set(CROSS_ORIGIN_RESOURCE_POLICY, self.corp.clone());
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hsts: Some(Hsts::default()),
+            nosniff: true,
+            frame_options: Some(FrameOptions::Deny),
+            referrer_policy: Some(HeaderValue::from_static("strict-origin-when-cross-origin")),
+            coop: Some(HeaderValue::from_static("same-origin")),
+            ..Self::empty()
+        }
+    }
+}
+
+impl<H> Transform<H> for Config {
+    type Output = SecureHeadersMiddleware<H>;
+
+    fn transform(&self, h: H) -> Self::Output {
+        SecureHeadersMiddleware {
+            h,
+            config: Arc::new(self.clone()),
+        }
+    }
+}
+
+/// Secure headers middleware.
+#[derive(Debug)]
+pub struct SecureHeadersMiddleware<H> {
+    h: H,
+    config: Arc<Config>,
+}
+
+impl<H> Clone for SecureHeadersMiddleware<H>
+where
+    H: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            h: self.h.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[crate::async_trait]
+impl<H, O> Handler<Request> for SecureHeadersMiddleware<H>
+where
+    H: Handler<Request, Output = Result<O>>,
+    O: IntoResponse,
+{
+    type Output = Result<Response>;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let nonce = if self.config.has_nonce() {
+            let nonce = nonce()?;
+            req.extensions_mut().insert(CspNonce(nonce.clone()));
+            Some(nonce)
+        } else {
+            None
+        };
+
+        let nonce = nonce.as_deref();
+
+        match self.h.call(req).await {
+            Ok(res) => {
+                let mut res = res.into_response();
+                self.config.apply(res.headers_mut(), nonce);
+                Ok(res)
+            }
+            Err(Error::Responder(mut res)) => {
+                self.config.apply(res.headers_mut(), nonce);
+                Err(Error::Responder(res))
+            }
+            Err(Error::Report(err, mut res)) => {
+                self.config.apply(res.headers_mut(), nonce);
+                Err(Error::Report(err, res))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Generates a random nonce.
+fn nonce() -> Result<String> {
+    let mut buf = [0u8; 16];
+    getrandom::fill(&mut buf)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_error())?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(buf))
+}
+
+/// A violation report of the Content Security Policy.
+///
+/// Both the `application/csp-report` format of `report-uri` and the
+/// `application/reports+json` format of `report-to` are accepted.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CspReport {
+    /// The URL of the document in which the violation occurred.
+    #[serde(rename = "documentURL", alias = "document-uri")]
+    pub document_url: String,
+    /// The referrer of the document.
+    #[serde(alias = "referrer")]
+    pub referrer: Option<String>,
+    /// The URL of the resource which was blocked.
+    #[serde(rename = "blockedURL", alias = "blocked-uri")]
+    pub blocked_url: Option<String>,
+    /// The directive whose enforcement caused the violation.
+    #[serde(
+        rename = "effectiveDirective",
+        alias = "effective-directive",
+        alias = "violated-directive"
+    )]
+    pub effective_directive: String,
+    /// The original policy.
+    #[serde(rename = "originalPolicy", alias = "original-policy")]
+    pub original_policy: String,
+    /// `enforce` or `report`.
+    #[serde(alias = "disposition")]
+    pub disposition: Option<String>,
+    /// The URL of the source file which caused the violation.
+    #[serde(rename = "sourceFile", alias = "source-file")]
+    pub source_file: Option<String>,
+    /// The line number of the source file.
+    #[serde(rename = "lineNumber", alias = "line-number")]
+    pub line_number: Option<u64>,
+    /// The column number of the source file.
+    #[serde(rename = "columnNumber", alias = "column-number")]
+    pub column_number: Option<u64>,
+    /// The sample of the violation.
+    #[serde(alias = "script-sample")]
+    pub sample: Option<String>,
+    /// The HTTP status code of the document.
+    #[serde(rename = "statusCode", alias = "status-code")]
+    pub status_code: Option<u16>,
+}
+
+#[derive(Deserialize)]
+struct LegacyReport {
+    #[serde(rename = "csp-report")]
+    csp_report: CspReport,
+}
+
+#[derive(Deserialize)]
+struct Report {
+    #[serde(rename = "type")]
+    kind: String,
+    body: CspReport,
+}
+
+/// A handler which collects the violation reports of the Content Security Policy.
+///
+/// ```
+/// use vidi_core::middleware::secure_headers::{CspReport, ReportHandler};
+///
+/// let handler = ReportHandler::new(|report: CspReport| {
+///     println!(
+///         "{} blocked by {}",
+///         report.document_url, report.effective_directive
+///     );
+/// });
+/// ```
+pub struct ReportHandler<F> {
+    f: Arc<F>,
+    limit: usize,
+}
+
+impl<F> ReportHandler<F> {
+    /// The default maximum size of a report body.
+    pub const LIMIT: usize = 64 * 1024;
+
+    /// Creates a new handler with the callback of the reports.
+    pub fn new(f: F) -> Self {
+        Self {
+            f: Arc::new(f),
+            limit: Self::LIMIT,
+        }
+    }
+
+    /// Sets the maximum size of a report body.
+    #[must_use]
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<F> Clone for ReportHandler<F> {
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f.clone(),
+            limit: self.limit,
+        }
+    }
+}
+
+impl<F> fmt::Debug for ReportHandler<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReportHandler")
+            .field("limit", &self.limit)
+            .finish_non_exhaustive()
+    }
+}
+
+#[crate::async_trait]
+impl<F> Handler<Request> for ReportHandler<F>
+where
+    F: Fn(CspReport) + Send + Sync + 'static,
+{
+    type Output = Result<Response>;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let reports = req.content_type().map(|m| m.essence_str().to_string());
+
+        let bytes = Limited::new(req.incoming()?, self.limit)
+            .collect()
+            .await
+            .map_err(|err| {
+                if err.is::<LengthLimitError>() {
+                    PayloadError::TooLarge
+                } else {
+                    PayloadError::Read
+                }
+            })?
+            .to_bytes();
+
+        let reports =
+            match reports.as_deref() {
+                Some("application/csp-report") => serde_json::from_slice::<LegacyReport>(&bytes)
+                    .map(|report| vec![report.csp_report]),
+                Some("application/reports+json") => serde_json::from_slice::<Vec<Report>>(&bytes)
+                    .map(|reports| {
+                        reports
+                            .into_iter()
+                            .filter(|report| report.kind == "csp-violation")
+                            .map(|report| report.body)
+                            .collect()
+                    }),
+                _ => Err(PayloadError::UnsupportedMediaType(
+                    "application/reports+json"
+                        .parse()
+                        .expect("should be a valid mime"),
+                ))?,
+            }
+            .map_err(PayloadError::Json)?;
+
+        for report in reports {
+            (self.f)(report);
+        }
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
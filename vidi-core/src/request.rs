@@ -36,7 +36,7 @@ use crate::types::{ParamsError, PathDeserializer, RouteInfo};
 
 /// The [`Request`] Extension.
 pub trait RequestExt: private::Sealed + Sized {
-    /// Get URL's schema of this request.
+    /// Get URL's schema of this request, or the schema of the connection, e.g. `https` over TLS.
     fn schema(&self) -> Option<&http::uri::Scheme>;
 
     /// Get URL's path of this request.
@@ -184,7 +184,9 @@ pub trait RequestExt: private::Sealed + Sized {
 impl RequestExt for Request {
     #[inline]
     fn schema(&self) -> Option<&http::uri::Scheme> {
-        self.uri().scheme()
+        self.uri()
+            .scheme()
+            .or_else(|| self.extensions().get::<http::uri::Scheme>())
     }
 
     #[inline]
@@ -373,7 +375,9 @@ impl RequestExt for Request {
 
     #[inline]
     fn remote_addr(&self) -> Option<&std::net::SocketAddr> {
-        self.extensions().get()
+        self.extensions()
+            .get::<Option<std::sync::Arc<std::net::SocketAddr>>>()
+            .and_then(Option::as_deref)
     }
 
     #[cfg(feature = "params")]
@@ -2,10 +2,10 @@
 
 #[cfg(feature = "cookie")]
 mod cookie;
-#[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-pub use self::cookie::CookieKey;
 #[cfg(feature = "cookie")]
 pub use self::cookie::{Cookie, CookieJar, Cookies, CookiesError, SameSite};
+#[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
+pub use self::cookie::{CookieKey, CookieKeyring};
 
 #[cfg(feature = "state")]
 mod state;
@@ -29,6 +29,10 @@ pub use limits::Limits;
 
 #[cfg(feature = "multipart")]
 mod multipart;
+#[cfg(feature = "multipart-form")]
+pub use multipart::{
+    FromMultipart, MultipartFieldSpec, MultipartFields, MultipartForm, MultipartFormError, TempFile,
+};
 #[cfg(feature = "multipart")]
 pub use multipart::{Multipart, MultipartError, MultipartLimits};
 
@@ -70,5 +74,8 @@ pub use header::{Header, HeaderError};
 mod payload;
 pub use payload::{Payload, PayloadError};
 
+mod cidr;
+pub use cidr::{Cidr, CidrError};
+
 mod realip;
-pub use realip::RealIp;
+pub use realip::{Forwarded, RealIp, TrustedProxies};
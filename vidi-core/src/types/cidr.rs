@@ -0,0 +1,107 @@
+//! Represents an IP network in the CIDR notation.
+
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use crate::ThisError;
+
+/// An IP network in the CIDR notation, e.g. `10.0.0.0/8` or `::1/128`.
+///
+/// A single address is parsed as a network with the full prefix, the IPv4-mapped IPv6 addresses
+/// are matched as IPv4.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Creates a network with an address and a prefix length, the host bits are cleared.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the prefix is longer than the address.
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, CidrError> {
+        let addr = addr.to_canonical();
+        if prefix > max_prefix(addr) {
+            return Err(CidrError);
+        }
+        Ok(Self {
+            addr: mask(addr, prefix),
+            prefix,
+        })
+    }
+
+    /// Gets the network address.
+    #[must_use]
+    pub const fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Gets the prefix length.
+    #[must_use]
+    pub const fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Checks if the address is in this network.
+    #[must_use]
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        let addr = addr.to_canonical();
+        addr.is_ipv4() == self.addr.is_ipv4() && mask(addr, self.prefix) == self.addr
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    fn from(addr: IpAddr) -> Self {
+        let addr = addr.to_canonical();
+        Self {
+            addr,
+            prefix: max_prefix(addr),
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((addr, prefix)) => Self::new(
+                addr.parse().map_err(|_| CidrError)?,
+                prefix.parse().map_err(|_| CidrError)?,
+            ),
+            None => s.parse::<IpAddr>().map(Self::from).map_err(|_| CidrError),
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+/// Rejects an invalid CIDR.
+#[derive(Debug, ThisError)]
+#[error("invalid CIDR")]
+pub struct CidrError;
+
+const fn max_prefix(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn mask(addr: IpAddr, prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(addr) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V4((u32::from(addr) & mask).into())
+        }
+        IpAddr::V6(addr) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V6((u128::from(addr) & mask).into())
+        }
+    }
+}
@@ -15,11 +15,70 @@ pub use ::cookie::{Cookie, CookieJar, SameSite};
 #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
 pub type CookieKey = ::cookie::Key;
 
+/// A set of cryptographic keys for rotating the `Signed` and/or `Private` cookies.
+///
+/// The primary key signs and encrypts the outgoing cookies, the retired keys are only used to
+/// read the incoming cookies which were issued before the rotation.
+#[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
+#[derive(Clone)]
+pub struct CookieKeyring {
+    primary: CookieKey,
+    retired: Vec<CookieKey>,
+}
+
+#[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
+impl CookieKeyring {
+    /// Creates a new keyring with the primary [`Key`][CookieKey].
+    #[must_use]
+    pub const fn new(primary: CookieKey) -> Self {
+        Self {
+            primary,
+            retired: Vec::new(),
+        }
+    }
+
+    /// Adds a retired [`Key`][CookieKey] which is only used for reading.
+    #[must_use]
+    pub fn retired(mut self, key: CookieKey) -> Self {
+        self.retired.push(key);
+        self
+    }
+
+    /// Returns the primary [`Key`][CookieKey].
+    #[must_use]
+    pub const fn primary(&self) -> &CookieKey {
+        &self.primary
+    }
+
+    /// Returns the retired keys in the order they were added.
+    #[must_use]
+    pub fn retired_keys(&self) -> &[CookieKey] {
+        &self.retired
+    }
+}
+
+#[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
+impl From<CookieKey> for CookieKeyring {
+    fn from(primary: CookieKey) -> Self {
+        Self::new(primary)
+    }
+}
+
+#[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
+impl fmt::Debug for CookieKeyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieKeyring")
+            .field("primary", &[..])
+            .field("retired", &self.retired.len())
+            .finish()
+    }
+}
+
 /// Extracts the cookies from the request.
 pub struct Cookies {
     inner: Arc<Mutex<CookieJar>>,
     #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-    key: Option<Arc<CookieKey>>,
+    keyring: Option<Arc<CookieKeyring>>,
 }
 
 impl Clone for Cookies {
@@ -27,7 +86,7 @@ impl Clone for Cookies {
         Self {
             inner: self.inner.clone(),
             #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-            key: self.key.clone(),
+            keyring: self.keyring.clone(),
         }
     }
 }
@@ -39,7 +98,7 @@ impl Cookies {
         Self {
             inner: Arc::new(Mutex::new(cookie_jar)),
             #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-            key: None,
+            keyring: None,
         }
     }
 
@@ -90,8 +149,14 @@ impl Cookies {
 impl Cookies {
     /// A cryptographic master key for use with `Signed` and/or `Private` jars.
     #[must_use]
-    pub fn with_key(mut self, key: Arc<CookieKey>) -> Self {
-        self.key.replace(key);
+    pub fn with_key(self, key: Arc<CookieKey>) -> Self {
+        self.with_keyring(Arc::new(CookieKeyring::new(Arc::unwrap_or_clone(key))))
+    }
+
+    /// A set of cryptographic keys for use with `Signed` and/or `Private` jars.
+    #[must_use]
+    pub fn with_keyring(mut self, keyring: Arc<CookieKeyring>) -> Self {
+        self.keyring.replace(keyring);
         self
     }
 
@@ -102,18 +167,55 @@ impl Cookies {
     /// Will panic if missing a key
     #[must_use]
     pub fn key(&self) -> &CookieKey {
-        self.key.as_ref().expect("the `CookieKey` is required")
+        self.keyring().primary()
+    }
+
+    /// Returns the [`CookieKeyring`].
+    ///
+    /// # Panics
+    ///
+    /// Will panic if missing a key
+    #[must_use]
+    pub fn keyring(&self) -> &CookieKeyring {
+        self.keyring.as_ref().expect("the `CookieKey` is required")
     }
 }
 
 #[cfg(feature = "cookie-private")]
 impl Cookies {
     /// Returns a reference to the `Cookie` inside this jar with the specified name.
+    ///
+    /// A cookie encrypted with a retired key is re-encrypted with the primary key, keeping the
+    /// attributes of the cookie, see [`private_get_with`][Self::private_get_with].
     pub fn private_get(&self, name: impl AsRef<str>) -> Option<Cookie<'_>> {
-        self.jar()
-            .lock()
-            .ok()
-            .and_then(|c| c.private(self.key()).get(name.as_ref()))
+        self.private_get_with(name, |cookie| cookie)
+    }
+
+    /// Returns a reference to the `Cookie` inside this jar with the specified name.
+    ///
+    /// A cookie encrypted with a retired key is re-encrypted with the primary key, `reissue`
+    /// builds the outgoing cookie with its attributes.
+    pub fn private_get_with<F>(&self, name: impl AsRef<str>, reissue: F) -> Option<Cookie<'_>>
+    where
+        F: FnOnce(Cookie<'static>) -> Cookie<'static>,
+    {
+        let keyring = self.keyring();
+        let mut c = self.jar().lock().ok()?;
+
+        if let Some(cookie) = c.private(keyring.primary()).get(name.as_ref()) {
+            return Some(cookie);
+        }
+
+        let original = c.get(name.as_ref())?.clone();
+        let cookie = keyring
+            .retired_keys()
+            .iter()
+            .find_map(|key| c.private(key).decrypt(original.clone()))?;
+
+        c.private_mut(keyring.primary())
+            .add(reissue(cookie.clone()));
+
+        Some(cookie)
     }
 
     /// Adds `cookie` to the parent jar.
@@ -138,25 +240,54 @@ impl Cookies {
         }
     }
 
-    /// Authenticates and decrypts `cookie` and returning the plain `cookie`.
+    /// Authenticates and decrypts `cookie` with the primary key or any retired key and
+    /// returning the plain `cookie`.
     #[must_use]
     pub fn private_decrypt(&self, cookie: Cookie<'_>) -> Option<Cookie<'_>> {
-        self.jar()
-            .lock()
-            .ok()?
-            .private(self.key())
-            .decrypt(cookie.into_owned())
+        let keyring = self.keyring();
+        let c = self.jar().lock().ok()?;
+        let cookie = cookie.into_owned();
+
+        std::iter::once(keyring.primary())
+            .chain(keyring.retired_keys())
+            .find_map(|key| c.private(key).decrypt(cookie.clone()))
     }
 }
 
 #[cfg(feature = "cookie-signed")]
 impl Cookies {
     /// Returns a reference to the `Cookie` inside this jar with the specified name.
+    ///
+    /// A cookie signed with a retired key is re-signed with the primary key, keeping the
+    /// attributes of the cookie, see [`signed_get_with`][Self::signed_get_with].
     pub fn signed_get(&self, name: impl AsRef<str>) -> Option<Cookie<'_>> {
-        self.jar()
-            .lock()
-            .ok()
-            .and_then(|c| c.signed(self.key()).get(name.as_ref()))
+        self.signed_get_with(name, |cookie| cookie)
+    }
+
+    /// Returns a reference to the `Cookie` inside this jar with the specified name.
+    ///
+    /// A cookie signed with a retired key is re-signed with the primary key, `reissue` builds
+    /// the outgoing cookie with its attributes.
+    pub fn signed_get_with<F>(&self, name: impl AsRef<str>, reissue: F) -> Option<Cookie<'_>>
+    where
+        F: FnOnce(Cookie<'static>) -> Cookie<'static>,
+    {
+        let keyring = self.keyring();
+        let mut c = self.jar().lock().ok()?;
+
+        if let Some(cookie) = c.signed(keyring.primary()).get(name.as_ref()) {
+            return Some(cookie);
+        }
+
+        let original = c.get(name.as_ref())?.clone();
+        let cookie = keyring
+            .retired_keys()
+            .iter()
+            .find_map(|key| c.signed(key).verify(original.clone()))?;
+
+        c.signed_mut(keyring.primary()).add(reissue(cookie.clone()));
+
+        Some(cookie)
     }
 
     /// Adds `cookie` to the parent jar.
@@ -181,14 +312,17 @@ impl Cookies {
         }
     }
 
-    /// Verifies the authenticity and integrity of `cookie` and returning the plain `cookie`.
+    /// Verifies the authenticity and integrity of `cookie` with the primary key or any retired
+    /// key and returning the plain `cookie`.
     #[must_use]
     pub fn signed_verify(&self, cookie: Cookie<'_>) -> Option<Cookie<'_>> {
-        self.jar()
-            .lock()
-            .ok()?
-            .signed(self.key())
-            .verify(cookie.into_owned())
+        let keyring = self.keyring();
+        let c = self.jar().lock().ok()?;
+        let cookie = cookie.into_owned();
+
+        std::iter::once(keyring.primary())
+            .chain(keyring.retired_keys())
+            .find_map(|key| c.signed(key).verify(cookie.clone()))
     }
 }
 
@@ -207,7 +341,7 @@ impl fmt::Debug for Cookies {
         d.field("jar", self.inner.as_ref());
 
         #[cfg(any(feature = "cookie-signed", feature = "cookie-private"))]
-        d.field("key", &self.key.is_some());
+        d.field("key", &self.keyring.is_some());
 
         d.finish()
     }
@@ -8,6 +8,13 @@ use super::{Payload, PayloadError};
 
 pub use form_data::{Error as MultipartError, Limits as MultipartLimits};
 
+#[cfg(feature = "multipart-form")]
+mod form;
+#[cfg(feature = "multipart-form")]
+pub use form::{
+    FromMultipart, MultipartFieldSpec, MultipartFields, MultipartForm, MultipartFormError, TempFile,
+};
+
 /// Extracts the data from the multipart body of a request.
 pub type Multipart<T = Body> = FormData<T>;
 
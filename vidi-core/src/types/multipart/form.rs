@@ -0,0 +1,622 @@
+//! Typed Multipart Form Extractor
+
+use std::{
+    collections::{HashMap, hash_map::RandomState},
+    fmt,
+    hash::{BuildHasher, Hasher},
+    io,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use form_data::Field;
+use futures_util::TryStreamExt;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    Body, FromRequest, IntoResponse, Request, RequestExt, Response, StatusCode, ThisError,
+};
+
+use super::super::{Payload, PayloadError};
+use super::{Multipart, MultipartError, MultipartLimits};
+
+/// Extracts a [`FromMultipart`] type from the `multipart/form-data` body of a request.
+///
+/// The text fields are parsed by [`FromStr`], the file fields are streamed to the [`TempFile`]s.
+/// The sizes are limited by the [`MultipartLimits`] of the `limits` middleware, or by the `limit`
+/// of a field.
+///
+/// ```
+/// use vidi_core::types::{
+///     FromMultipart, MultipartFieldSpec, MultipartFields, MultipartForm, MultipartFormError,
+///     TempFile,
+/// };
+///
+/// struct Upload {
+///     title: String,
+///     avatar: Option<TempFile>,
+/// }
+///
+/// // `#[derive(MultipartForm)]` of the `vidi-macros` generates it.
+/// impl FromMultipart for Upload {
+///     const FIELDS: &'static [MultipartFieldSpec] = &[
+///         MultipartFieldSpec::text("title"),
+///         MultipartFieldSpec::file("avatar")
+///             .limit(1024 * 1024)
+///             .content_types(&["image/*"]),
+///     ];
+///
+///     fn from_fields(fields: &mut MultipartFields) -> Result<Self, MultipartFormError> {
+///         Ok(Self {
+///             title: fields.text("title")?,
+///             avatar: fields.file_opt("avatar")?,
+///         })
+///     }
+/// }
+///
+/// async fn upload(MultipartForm(upload): MultipartForm<Upload>) {
+///     if let Some(avatar) = upload.avatar {
+///         println!("{} {}", upload.title, avatar.sha256_hex());
+///     }
+/// }
+/// ```
+pub struct MultipartForm<T>(pub T);
+
+impl<T> MultipartForm<T> {
+    /// Create new `MultipartForm` instance.
+    #[inline]
+    pub const fn new(data: T) -> Self {
+        Self(data)
+    }
+
+    /// Consumes the `MultipartForm`, returning the wrapped value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> AsRef<T> for MultipartForm<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Deref for MultipartForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for MultipartForm<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for MultipartForm<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+impl<T> FromRequest for MultipartForm<T>
+where
+    T: FromMultipart + Send,
+{
+    type Error = PayloadError;
+
+    async fn extract(req: &mut Request) -> Result<Self, Self::Error> {
+        let m = <Multipart as Payload>::check_type(req.content_type())?;
+        let boundary = m
+            .get_param(mime::BOUNDARY)
+            .ok_or(PayloadError::MissingBoundary)?
+            .as_str();
+        let limits = req
+            .extensions()
+            .get::<Arc<MultipartLimits>>()
+            .map(AsRef::as_ref)
+            .cloned()
+            .unwrap_or_default();
+        let multipart = Multipart::with_limits(req.incoming()?, boundary, limits.clone());
+
+        let mut fields = MultipartFields::read(multipart, T::FIELDS, &limits).await?;
+        Ok(Self(T::from_fields(&mut fields)?))
+    }
+}
+
+/// Converts the fields of a `multipart/form-data` body to a type.
+///
+/// It can be derived by `#[derive(MultipartForm)]` of the `vidi-macros`.
+pub trait FromMultipart: Sized {
+    /// The declared fields, the others are ignored.
+    const FIELDS: &'static [MultipartFieldSpec];
+
+    /// Creates the value by the received fields.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`MultipartFormError`] if a field is missing or invalid.
+    fn from_fields(fields: &mut MultipartFields) -> Result<Self, MultipartFormError>;
+}
+
+/// The declaration of a field.
+#[derive(Clone, Copy, Debug)]
+pub struct MultipartFieldSpec {
+    /// The name of the field.
+    pub name: &'static str,
+    /// Whether the field is streamed to a [`TempFile`].
+    pub file: bool,
+    /// The maximum size of the field, it can not exceed the [`MultipartLimits`].
+    pub limit: Option<u64>,
+    /// The allowed media types of the file, e.g. `image/png` and `image/*`, any if it is empty.
+    pub content_types: &'static [&'static str],
+}
+
+impl MultipartFieldSpec {
+    /// Declares a text field.
+    #[must_use]
+    pub const fn text(name: &'static str) -> Self {
+        Self {
+            name,
+            file: false,
+            limit: None,
+            content_types: &[],
+        }
+    }
+
+    /// Declares a file field.
+    #[must_use]
+    pub const fn file(name: &'static str) -> Self {
+        Self {
+            name,
+            file: true,
+            limit: None,
+            content_types: &[],
+        }
+    }
+
+    /// Sets the maximum size of the field.
+    #[must_use]
+    pub const fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the allowed media types of the file.
+    #[must_use]
+    pub const fn content_types(mut self, content_types: &'static [&'static str]) -> Self {
+        self.content_types = content_types;
+        self
+    }
+
+    /// Checks the media type is allowed.
+    fn allows(&self, content_type: Option<&mime::Mime>) -> bool {
+        if self.content_types.is_empty() {
+            return true;
+        }
+        let Some(m) = content_type else {
+            return false;
+        };
+        self.content_types.iter().any(|allowed| {
+            let (type_, subtype) = allowed.split_once('/').unwrap_or((allowed, "*"));
+            (type_ == "*" || m.type_().as_str().eq_ignore_ascii_case(type_))
+                && (subtype == "*" || m.subtype().as_str().eq_ignore_ascii_case(subtype))
+        })
+    }
+}
+
+/// The received fields of a `multipart/form-data` body.
+#[derive(Debug, Default)]
+pub struct MultipartFields {
+    texts: HashMap<String, Vec<String>>,
+    files: HashMap<String, Vec<TempFile>>,
+}
+
+impl MultipartFields {
+    /// Reads the declared fields, the files are streamed to the temporary directory.
+    async fn read(
+        mut multipart: Multipart,
+        specs: &[MultipartFieldSpec],
+        limits: &MultipartLimits,
+    ) -> Result<Self, MultipartFormError> {
+        let mut fields = Self::default();
+
+        while let Some(mut field) = multipart.try_next().await? {
+            let Some(spec) = specs.iter().find(|spec| spec.name == field.name) else {
+                field.ignore().await?;
+                continue;
+            };
+
+            if spec.file {
+                // an empty file input of the browsers
+                if field.filename.as_deref() == Some("") {
+                    field.ignore().await?;
+                    continue;
+                }
+                if !spec.allows(field.content_type.as_ref()) {
+                    Err(MultipartFormError::UnsupportedMediaType(
+                        spec.name.to_string(),
+                    ))?;
+                }
+                let limit = spec.limit.or(limits.file_size.map(|max| max as u64));
+                let file = TempFile::receive(&mut field, spec.name, limit).await?;
+                fields
+                    .files
+                    .entry(spec.name.to_string())
+                    .or_default()
+                    .push(file);
+            } else {
+                let limit = spec.limit.or(limits.field_size.map(|max| max as u64));
+                let mut bytes = Vec::new();
+                while let Some(chunk) = field
+                    .try_next()
+                    .await
+                    .map_err(|e| MultipartFormError::from_field(e, spec.name))?
+                {
+                    bytes.extend_from_slice(&chunk);
+                    check_limit(bytes.len() as u64, limit, spec.name)?;
+                }
+                let text = String::from_utf8(bytes).map_err(|e| MultipartFormError::Invalid {
+                    name: spec.name.to_string(),
+                    message: e.to_string(),
+                })?;
+                fields
+                    .texts
+                    .entry(spec.name.to_string())
+                    .or_default()
+                    .push(text);
+            }
+        }
+
+        Ok(fields)
+    }
+
+    fn take<V>(
+        map: &mut HashMap<String, Vec<V>>,
+        name: &str,
+    ) -> Result<Option<V>, MultipartFormError> {
+        match map.remove(name) {
+            None => Ok(None),
+            Some(mut values) if values.len() == 1 => Ok(values.pop()),
+            Some(_) => Err(MultipartFormError::Duplicate(name.to_string())),
+        }
+    }
+
+    /// Takes the optional text field.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`MultipartFormError`] if the field is duplicated or could not be parsed.
+    pub fn text_opt<T>(&mut self, name: &str) -> Result<Option<T>, MultipartFormError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        Self::take(&mut self.texts, name)?
+            .map(|text| parse(&text, name))
+            .transpose()
+    }
+
+    /// Takes the required text field.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`MultipartFormError`] if the field is missing, duplicated or could not be
+    /// parsed.
+    pub fn text<T>(&mut self, name: &str) -> Result<T, MultipartFormError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.text_opt(name)?
+            .ok_or_else(|| MultipartFormError::Missing(name.to_string()))
+    }
+
+    /// Takes all the values of the text field.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`MultipartFormError`] if a value could not be parsed.
+    pub fn texts<T>(&mut self, name: &str) -> Result<Vec<T>, MultipartFormError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.texts
+            .remove(name)
+            .unwrap_or_default()
+            .iter()
+            .map(|text| parse(text, name))
+            .collect()
+    }
+
+    /// Takes the optional file field.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`MultipartFormError`] if the field is duplicated.
+    pub fn file_opt(&mut self, name: &str) -> Result<Option<TempFile>, MultipartFormError> {
+        Self::take(&mut self.files, name)
+    }
+
+    /// Takes the required file field.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`MultipartFormError`] if the field is missing or duplicated.
+    pub fn file(&mut self, name: &str) -> Result<TempFile, MultipartFormError> {
+        self.file_opt(name)?
+            .ok_or_else(|| MultipartFormError::Missing(name.to_string()))
+    }
+
+    /// Takes all the files of the field.
+    #[must_use]
+    pub fn files(&mut self, name: &str) -> Vec<TempFile> {
+        self.files.remove(name).unwrap_or_default()
+    }
+}
+
+fn parse<T>(text: &str, name: &str) -> Result<T, MultipartFormError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    text.parse()
+        .map_err(|e: T::Err| MultipartFormError::Invalid {
+            name: name.to_string(),
+            message: e.to_string(),
+        })
+}
+
+fn check_limit(size: u64, limit: Option<u64>, name: &str) -> Result<(), MultipartFormError> {
+    match limit {
+        Some(limit) if size > limit => Err(MultipartFormError::TooLarge {
+            name: name.to_string(),
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// A file field in the temporary directory, which is deleted on drop.
+pub struct TempFile {
+    path: PathBuf,
+    file_name: Option<String>,
+    content_type: Option<mime::Mime>,
+    size: u64,
+    sha256: [u8; 32],
+}
+
+impl TempFile {
+    /// Creates an empty file in the temporary directory.
+    async fn create(
+        file_name: Option<String>,
+        content_type: Option<mime::Mime>,
+    ) -> io::Result<(Self, tokio::fs::File)> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(format!("vidi-{:016x}", hasher.finish()));
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // the temp directory is shared, only the owner can read the upload
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file = options.open(&path).await?;
+
+        Ok((
+            Self {
+                path,
+                file_name,
+                content_type,
+                size: 0,
+                sha256: [0; 32],
+            },
+            file,
+        ))
+    }
+
+    /// Streams the field to a new file, computes the size and the digest.
+    async fn receive(
+        field: &mut Field<Body>,
+        name: &str,
+        limit: Option<u64>,
+    ) -> Result<Self, MultipartFormError> {
+        let (mut this, mut file) =
+            Self::create(field.filename.clone(), field.content_type.clone()).await?;
+        let mut hasher = Sha256::new();
+
+        while let Some(chunk) = field
+            .try_next()
+            .await
+            .map_err(|e| MultipartFormError::from_field(e, name))?
+        {
+            this.size += chunk.len() as u64;
+            check_limit(this.size, limit, name)?;
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        this.sha256 = hasher.finalize().into();
+        Ok(this)
+    }
+
+    /// The path of the file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The file name of the client, it should not be trusted as a path.
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// The media type of the client.
+    #[must_use]
+    pub const fn content_type(&self) -> Option<&mime::Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// The size of the file.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The SHA-256 digest of the file.
+    #[must_use]
+    pub const fn sha256(&self) -> &[u8; 32] {
+        &self.sha256
+    }
+
+    /// The SHA-256 digest of the file in lowercase hexadecimal.
+    #[must_use]
+    pub fn sha256_hex(&self) -> String {
+        self.sha256
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, b| {
+                use std::fmt::Write;
+                let _ = write!(hex, "{b:02x}");
+                hex
+            })
+    }
+
+    /// Moves the file to the path, it is not deleted anymore.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file could not be moved.
+    pub async fn persist(mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if tokio::fs::rename(&self.path, path).await.is_err() {
+            // e.g. across the file systems
+            tokio::fs::copy(&self.path, path).await?;
+            tokio::fs::remove_file(&self.path).await?;
+        }
+        self.path = PathBuf::new();
+        Ok(())
+    }
+
+    /// Keeps the file, returns the path of the file.
+    #[must_use]
+    pub fn keep(mut self) -> PathBuf {
+        std::mem::take(&mut self.path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl fmt::Debug for TempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TempFile")
+            .field("path", &self.path)
+            .field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .field("size", &self.size)
+            .field("sha256", &self.sha256_hex())
+            .finish()
+    }
+}
+
+/// Rejects with an error when the fields of a [`MultipartForm`] are invalid.
+#[derive(Debug, ThisError)]
+pub enum MultipartFormError {
+    /// 400 or 413
+    #[error(transparent)]
+    Multipart(#[from] MultipartError),
+
+    /// 400
+    #[error("field `{0}` is missing")]
+    Missing(String),
+
+    /// 400
+    #[error("field `{0}` is duplicated")]
+    Duplicate(String),
+
+    /// 400
+    #[error("field `{name}` is invalid, {message}")]
+    Invalid {
+        /// The name of the field.
+        name: String,
+        /// The reason.
+        message: String,
+    },
+
+    /// 413
+    #[error("field `{name}` is too large, the limit is {limit} bytes")]
+    TooLarge {
+        /// The name of the field.
+        name: String,
+        /// The maximum size.
+        limit: u64,
+    },
+
+    /// 415
+    #[error("field `{0}` has an unsupported media type")]
+    UnsupportedMediaType(String),
+
+    /// 500
+    #[error("failed to write temporary file, {0}")]
+    Io(#[from] io::Error),
+}
+
+impl MultipartFormError {
+    /// Converts the size errors of the [`MultipartLimits`] to [`Self::TooLarge`].
+    fn from_field(e: MultipartError, name: &str) -> Self {
+        match e {
+            MultipartError::FileTooLarge(limit) | MultipartError::FieldTooLarge(limit) => {
+                Self::TooLarge {
+                    name: name.to_string(),
+                    limit: limit as u64,
+                }
+            }
+            e => Self::Multipart(e),
+        }
+    }
+
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            Self::Multipart(MultipartError::PayloadTooLarge(_)) | Self::TooLarge { .. } => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            Self::Multipart(
+                MultipartError::Stream(_)
+                | MultipartError::BoxError(_)
+                | MultipartError::TryLockError(_),
+            )
+            | Self::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Multipart(_) | Self::Missing(_) | Self::Duplicate(_) | Self::Invalid { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        }
+    }
+}
+
+impl IntoResponse for MultipartFormError {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
@@ -39,6 +39,11 @@ pub enum PayloadError {
     #[error("url decode failed, {0}")]
     UrlDecode(#[from] serde_urlencoded::de::Error),
 
+    /// 400, 413, 415 or 500
+    #[cfg(feature = "multipart-form")]
+    #[error(transparent)]
+    MultipartForm(#[from] super::MultipartFormError),
+
     /// 411
     #[error("content-length is required")]
     LengthRequired,
@@ -70,6 +75,8 @@ impl IntoResponse for PayloadError {
                 Self::Json(_) => StatusCode::BAD_REQUEST,
                 #[cfg(any(feature = "form", feature = "query"))]
                 Self::UrlDecode(_) => StatusCode::BAD_REQUEST,
+                #[cfg(feature = "multipart-form")]
+                Self::MultipartForm(ref e) => e.status(),
                 Self::LengthRequired => StatusCode::LENGTH_REQUIRED,
                 Self::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
                 Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
@@ -1,16 +1,13 @@
 use std::{
     net::{IpAddr, SocketAddr},
-    str,
+    sync::Arc,
 };
 
-use rfc7239::{NodeIdentifier, NodeName};
+use rfc7239::NodeIdentifier;
 
-use crate::{
-    Request, RequestExt, Result,
-    header::{FORWARDED, HeaderValue},
-};
+use crate::{Request, RequestExt, header::FORWARDED, types::Cidr};
 
-/// Gets real ip remote addr from request headers.
+/// Gets the real IP address of the client, see [`parse`][Self::parse].
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct RealIp(pub IpAddr);
 
@@ -21,47 +18,196 @@ impl RealIp {
     /// X-Forwarded-For header.
     pub const X_FORWARDED_FOR: &'static str = "x-forwarded-for";
 
+    /// X-Forwarded-Proto header.
+    pub const X_FORWARDED_PROTO: &'static str = "x-forwarded-proto";
+
+    /// X-Forwarded-Host header.
+    pub const X_FORWARDED_HOST: &'static str = "x-forwarded-host";
+
     /// Parse the headers.
+    ///
+    /// With the [`TrustedProxies`] in the extensions of the request, e.g. added by the
+    /// `forwarded` middleware, it is [`parse_with`][Self::parse_with]. Otherwise no proxy is
+    /// trusted, the headers are ignored and the remote address is used.
     pub fn parse(req: &Request) -> Option<Self> {
-        req.headers()
-            .get(Self::X_REAL_IP)
-            .map(HeaderValue::to_str)
-            .and_then(Result::ok)
-            .map(str::parse)
-            .and_then(Result::ok)
+        match req.extensions().get::<TrustedProxies>() {
+            Some(proxies) => Self::parse_with(req, proxies),
+            None => req.remote_addr().map(SocketAddr::ip).map(RealIp),
+        }
+    }
+
+    /// Parse the headers which are added by the trusted proxies.
+    ///
+    /// Returns `None` if the client is hidden by the proxy, e.g. `Forwarded: for=unknown`.
+    pub fn parse_with(req: &Request, proxies: &TrustedProxies) -> Option<Self> {
+        proxies.resolve(req).client.map(RealIp)
+    }
+}
+
+/// The proxies which are trusted to forward the client's address, scheme and host.
+///
+/// The forwarded chain, `Forwarded`, `X-Forwarded-For` or `X-Real-IP`, is walked from the
+/// right with the remote address, the first untrusted address is the client. A proxy is
+/// trusted if its address is in the CIDRs or it is one of the nearest [`hops`][Self::hops].
+///
+/// Without the remote address, e.g. on a Unix socket, the peer is only trusted by the
+/// [`hops`][Self::hops].
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    cidrs: Arc<[Cidr]>,
+    hops: usize,
+}
+
+/// The client's information which are resolved from the forwarded chain.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Forwarded {
+    /// The address of the client.
+    pub client: Option<IpAddr>,
+    /// The scheme which is requested by the client, e.g. `https`.
+    pub proto: Option<String>,
+    /// The host which is requested by the client.
+    pub host: Option<String>,
+}
+
+struct Hop<'a> {
+    ip: Option<IpAddr>,
+    proto: Option<&'a str>,
+    host: Option<&'a str>,
+}
+
+impl TrustedProxies {
+    /// Creates a new config which trusts nothing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts the proxies in the CIDRs, e.g. `10.0.0.0/8`.
+    #[must_use]
+    pub fn cidrs<I>(mut self, cidrs: I) -> Self
+    where
+        I: IntoIterator<Item = Cidr>,
+    {
+        self.cidrs = self.cidrs.iter().copied().chain(cidrs).collect();
+        self
+    }
+
+    /// Trusts the nearest proxies by the count, including the remote address.
+    ///
+    /// E.g. `1` for a load balancer in front of the server.
+    #[must_use]
+    pub const fn hops(mut self, hops: usize) -> Self {
+        self.hops = hops;
+        self
+    }
+
+    fn is_trusted(&self, position: usize, ip: Option<IpAddr>) -> bool {
+        position < self.hops || ip.is_some_and(|ip| self.cidrs.iter().any(|c| c.contains(&ip)))
+    }
+
+    /// Resolves the client's information from the forwarded chain.
+    #[must_use]
+    pub fn resolve(&self, req: &Request) -> Forwarded {
+        let remote = req.remote_addr().map(SocketAddr::ip);
+
+        if !self.is_trusted(0, remote) {
+            return Forwarded {
+                client: remote,
+                ..Forwarded::default()
+            };
+        }
+
+        let headers = req.headers();
+        let values = |name| {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>()
+        };
+        let protos = values(RealIp::X_FORWARDED_PROTO);
+        let hosts = values(RealIp::X_FORWARDED_HOST);
+
+        let hops = forwarded(req)
             .or_else(|| {
-                req.headers()
-                    .get(FORWARDED)
-                    .map(HeaderValue::to_str)
-                    .and_then(Result::ok)
-                    .map(rfc7239::parse)
-                    .map(Iterator::collect)
-                    .and_then(Result::ok)
-                    .map(Vec::into_iter)
-                    .and_then(|mut value| {
-                        value.find_map(|item| match item.forwarded_for {
-                            Some(NodeIdentifier {
-                                name: NodeName::Ip(ip_addr),
-                                ..
-                            }) => Some(ip_addr),
-                            _ => None,
+                let ips = values(RealIp::X_FORWARDED_FOR);
+                (!ips.is_empty()).then(|| {
+                    ips.iter()
+                        .enumerate()
+                        .map(|(i, ip)| Hop {
+                            ip: ip.parse().ok(),
+                            proto: pick(&protos, ips.len(), i),
+                            host: pick(&hosts, ips.len(), i),
                         })
-                    })
+                        .collect()
+                })
             })
             .or_else(|| {
-                req.headers()
-                    .get(Self::X_FORWARDED_FOR)
-                    .map(HeaderValue::to_str)
-                    .and_then(Result::ok)
-                    .and_then(|value| {
-                        value
-                            .split(',')
-                            .map(str::trim)
-                            .map(str::parse)
-                            .find_map(Result::ok)
+                headers
+                    .get(RealIp::X_REAL_IP)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|ip| {
+                        vec![Hop {
+                            ip: ip.trim().parse().ok(),
+                            proto: protos.last().copied(),
+                            host: hosts.last().copied(),
+                        }]
                     })
             })
-            .map(RealIp)
-            .or_else(|| req.remote_addr().map(SocketAddr::ip).map(RealIp))
+            .unwrap_or_default();
+
+        let client = hops
+            .iter()
+            .rev()
+            .enumerate()
+            .find(|(i, hop)| !self.is_trusted(i + 1, hop.ip))
+            .map(|(_, hop)| hop)
+            .or_else(|| hops.first());
+
+        match client {
+            Some(hop) => Forwarded {
+                client: hop.ip,
+                proto: hop.proto.map(str::to_ascii_lowercase),
+                host: hop.host.map(ToString::to_string),
+            },
+            None => Forwarded {
+                client: remote,
+                proto: protos.last().map(|v| v.to_ascii_lowercase()),
+                host: hosts.last().map(ToString::to_string),
+            },
+        }
+    }
+}
+
+/// Picks the value of the hop if the list is aligned with the `X-Forwarded-For`, otherwise the
+/// value of the nearest proxy.
+fn pick<'a>(values: &[&'a str], len: usize, i: usize) -> Option<&'a str> {
+    if values.len() == len {
+        values.get(i).copied()
+    } else {
+        values.last().copied()
+    }
+}
+
+/// Parses the `Forwarded` headers, `None` if missing or invalid.
+fn forwarded(req: &Request) -> Option<Vec<Hop<'_>>> {
+    let mut hops = Vec::new();
+    for value in req.headers().get_all(FORWARDED) {
+        for item in rfc7239::parse(value.to_str().ok()?) {
+            let item = item.ok()?;
+            hops.push(Hop {
+                ip: item
+                    .forwarded_for
+                    .as_ref()
+                    .and_then(NodeIdentifier::ip)
+                    .copied(),
+                proto: item.protocol,
+                host: item.host,
+            });
+        }
     }
+    (!hops.is_empty()).then_some(hops)
 }
@@ -0,0 +1,130 @@
+//! Cookie middleware test cases
+
+#![cfg(feature = "cookie-private")]
+
+use ::cookie::time::Duration;
+use vidi_core::{
+    Handler, HandlerExt, IntoResponse, Request, RequestExt, Result, Transform,
+    header::{COOKIE, HeaderValue, SET_COOKIE},
+    middleware::cookie,
+    types::{Cookie, CookieJar, CookieKey, CookieKeyring, SameSite},
+};
+
+fn encrypt(key: &CookieKey, name: &'static str, value: &'static str) -> String {
+    let mut jar = CookieJar::new();
+    jar.private_mut(key).add(Cookie::new(name, value));
+    jar.delta().next().unwrap().encoded().to_string()
+}
+
+#[tokio::test]
+async fn cookie_key_rotation() -> Result<()> {
+    let old = CookieKey::generate();
+    let new = CookieKey::generate();
+
+    let handler = (|req: Request| async move {
+        let cookies = req.cookies()?;
+        if req.uri().path() == "/read" {
+            return Ok(cookies
+                .private_get("vidi")
+                .map(|c| c.value().to_string())
+                .unwrap_or_default());
+        }
+        Ok(cookies
+            .private_get_with("vidi", |cookie| {
+                Cookie::build(cookie)
+                    .path("/app")
+                    .domain("vidi.rs")
+                    .http_only(true)
+                    .secure(true)
+                    .same_site(SameSite::Strict)
+                    .max_age(Duration::hours(1))
+                    .build()
+            })
+            .map(|c| c.value().to_string())
+            .unwrap_or_default())
+    })
+    .map_into_response();
+
+    let handler =
+        cookie::Config::with_keyring(CookieKeyring::new(new.clone()).retired(old.clone()))
+            .transform(handler);
+
+    // issued with the retired key, re-issued with the primary key
+    let mut req = Request::default();
+    req.headers_mut().insert(
+        COOKIE,
+        HeaderValue::from_str(&encrypt(&old, "vidi", "rotated")).unwrap(),
+    );
+    let res = handler.call(req).await?;
+    let set_cookie = res
+        .headers()
+        .get(SET_COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .map(Cookie::parse_encoded)
+        .and_then(Result::ok)
+        .map(Cookie::into_owned)
+        .unwrap();
+    assert_eq!(set_cookie.path(), Some("/app"));
+    assert_eq!(set_cookie.domain(), Some("vidi.rs"));
+    assert_eq!(set_cookie.http_only(), Some(true));
+    assert_eq!(set_cookie.secure(), Some(true));
+    assert_eq!(set_cookie.same_site(), Some(SameSite::Strict));
+    assert_eq!(set_cookie.max_age(), Some(Duration::hours(1)));
+
+    let mut jar = CookieJar::new();
+    jar.add_original(set_cookie.clone());
+    assert!(jar.private(&old).get("vidi").is_none());
+    assert_eq!(jar.private(&new).get("vidi").unwrap().value(), "rotated");
+
+    let body = res.into_body();
+    let bytes = http_body_util::BodyExt::collect(body).await?.to_bytes();
+    assert_eq!(bytes, "rotated");
+
+    // read by the plain getter, re-issued with the attributes of the cookie
+    let mut req = Request::default();
+    *req.uri_mut() = "/read".parse().unwrap();
+    req.headers_mut().insert(
+        COOKIE,
+        HeaderValue::from_str(&encrypt(&old, "vidi", "rotated")).unwrap(),
+    );
+    let res = handler.call(req).await?;
+    let set_cookie = res
+        .headers()
+        .get(SET_COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .map(Cookie::parse_encoded)
+        .and_then(Result::ok)
+        .map(Cookie::into_owned)
+        .unwrap();
+    assert_eq!(set_cookie.path(), None);
+    assert_eq!(set_cookie.max_age(), None);
+
+    let mut jar = CookieJar::new();
+    jar.add_original(set_cookie);
+    assert_eq!(jar.private(&new).get("vidi").unwrap().value(), "rotated");
+
+    let bytes = http_body_util::BodyExt::collect(res.into_body())
+        .await?
+        .to_bytes();
+    assert_eq!(bytes, "rotated");
+
+    // issued with the primary key, left untouched
+    let mut req = Request::default();
+    req.headers_mut().insert(
+        COOKIE,
+        HeaderValue::from_str(&encrypt(&new, "vidi", "current")).unwrap(),
+    );
+    let res = handler.call(req).await?;
+    assert!(res.headers().get(SET_COOKIE).is_none());
+
+    // issued with an unknown key
+    let mut req = Request::default();
+    req.headers_mut().insert(
+        COOKIE,
+        HeaderValue::from_str(&encrypt(&CookieKey::generate(), "vidi", "unknown")).unwrap(),
+    );
+    let res = handler.call(req).await?.into_response();
+    assert!(res.headers().get(SET_COOKIE).is_none());
+
+    Ok(())
+}
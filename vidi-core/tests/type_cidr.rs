@@ -0,0 +1,38 @@
+//! `Cidr` type test cases
+
+use std::net::IpAddr;
+
+use vidi_core::types::Cidr;
+
+#[test]
+fn cidr() {
+    let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+    let cidr = "10.1.2.3/8".parse::<Cidr>().unwrap();
+    assert_eq!(cidr.to_string(), "10.0.0.0/8");
+    assert!(cidr.contains(&ip("10.255.0.1")));
+    assert!(!cidr.contains(&ip("11.0.0.1")));
+    // IPv4-mapped IPv6
+    assert!(cidr.contains(&ip("::ffff:10.0.0.1")));
+
+    let cidr = "fd00::/8".parse::<Cidr>().unwrap();
+    assert!(cidr.contains(&ip("fd12::1")));
+    assert!(!cidr.contains(&ip("fe80::1")));
+    assert!(!cidr.contains(&ip("10.0.0.1")));
+
+    let cidr = "127.0.0.1".parse::<Cidr>().unwrap();
+    assert_eq!(cidr.prefix(), 32);
+    assert!(cidr.contains(&ip("127.0.0.1")));
+    assert!(!cidr.contains(&ip("127.0.0.2")));
+
+    assert!(
+        "0.0.0.0/0"
+            .parse::<Cidr>()
+            .unwrap()
+            .contains(&ip("1.1.1.1"))
+    );
+
+    for invalid in ["10.0.0.0/33", "::/129", "10.0.0/8", "vidi", "10.0.0.0/"] {
+        assert!(invalid.parse::<Cidr>().is_err(), "{invalid}");
+    }
+}
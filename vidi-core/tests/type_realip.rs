@@ -3,33 +3,133 @@
 use vidi_core::{
     Request, RequestExt,
     header::{FORWARDED, HeaderValue},
-    types::RealIp,
+    types::{RealIp, TrustedProxies},
 };
 
 #[test]
 fn realip() {
+    // the headers are ignored without the trusted proxies
     let mut req = Request::default();
     req.headers_mut()
         .insert(RealIp::X_REAL_IP, HeaderValue::from_static("10.10.10.10"));
-    assert_eq!(req.realip(), Some(RealIp("10.10.10.10".parse().unwrap())));
-
-    let mut req = Request::default();
     req.headers_mut().insert(
         RealIp::X_FORWARDED_FOR,
         HeaderValue::from_static("10.10.10.10"),
     );
-    assert_eq!(req.realip(), Some(RealIp("10.10.10.10".parse().unwrap())));
-
-    let mut req = Request::default();
     req.headers_mut()
         .insert(FORWARDED, HeaderValue::from_static("for=10.10.10.10"));
-    assert_eq!(req.realip(), Some(RealIp("10.10.10.10".parse().unwrap())));
+    assert_eq!(req.realip(), None);
 
     let req = Request::default();
     assert_eq!(req.realip(), None);
 
     let mut req = Request::default();
-    req.extensions_mut()
-        .insert("1.1.1.1:80".parse::<std::net::SocketAddr>().unwrap());
+    req.extensions_mut().insert(Some(std::sync::Arc::new(
+        "1.1.1.1:80".parse::<std::net::SocketAddr>().unwrap(),
+    )));
+    req.headers_mut().insert(
+        RealIp::X_FORWARDED_FOR,
+        HeaderValue::from_static("10.10.10.10"),
+    );
     assert_eq!(req.realip(), Some(RealIp("1.1.1.1".parse().unwrap())));
 }
+
+#[test]
+fn realip_trusted_proxies() {
+    let request = |remote: &str, headers: &[(&'static str, &'static str)]| {
+        let mut req = Request::default();
+        req.extensions_mut().insert(Some(std::sync::Arc::new(
+            format!("{remote}:80")
+                .parse::<std::net::SocketAddr>()
+                .unwrap(),
+        )));
+        for (name, value) in headers {
+            req.headers_mut()
+                .append(*name, HeaderValue::from_static(value));
+        }
+        req
+    };
+    let ip = |ip: &str| Some(RealIp(ip.parse().unwrap()));
+
+    let proxies = TrustedProxies::new().cidrs(["10.0.0.0/8".parse().unwrap()]);
+
+    // the untrusted peer can not spoof
+    let req = request("1.1.1.1", &[(RealIp::X_FORWARDED_FOR, "2.2.2.2")]);
+    assert_eq!(RealIp::parse_with(&req, &proxies), ip("1.1.1.1"));
+    let req = request("1.1.1.1", &[(RealIp::X_REAL_IP, "2.2.2.2")]);
+    assert_eq!(RealIp::parse_with(&req, &proxies), ip("1.1.1.1"));
+
+    // walks from the right
+    let req = request(
+        "10.0.0.1",
+        &[(RealIp::X_FORWARDED_FOR, "6.6.6.6, 1.1.1.1, 10.0.0.2")],
+    );
+    assert_eq!(RealIp::parse_with(&req, &proxies), ip("1.1.1.1"));
+
+    let req = request(
+        "10.0.0.1",
+        &[
+            ("forwarded", "for=6.6.6.6"),
+            ("forwarded", "for=1.1.1.1;proto=https, for=10.0.0.2"),
+        ],
+    );
+    assert_eq!(RealIp::parse_with(&req, &proxies), ip("1.1.1.1"));
+
+    let req = request("10.0.0.1", &[(RealIp::X_REAL_IP, "1.1.1.1")]);
+    assert_eq!(RealIp::parse_with(&req, &proxies), ip("1.1.1.1"));
+
+    // all trusted
+    let req = request(
+        "10.0.0.1",
+        &[(RealIp::X_FORWARDED_FOR, "10.0.0.3, 10.0.0.2")],
+    );
+    assert_eq!(RealIp::parse_with(&req, &proxies), ip("10.0.0.3"));
+
+    // hops
+    let req = request(
+        "172.16.0.1",
+        &[(RealIp::X_FORWARDED_FOR, "6.6.6.6, 1.1.1.1, 172.16.0.2")],
+    );
+    assert_eq!(RealIp::parse_with(&req, &proxies), ip("172.16.0.1"));
+    assert_eq!(
+        RealIp::parse_with(&req, &TrustedProxies::new().hops(1)),
+        ip("172.16.0.2")
+    );
+    assert_eq!(
+        RealIp::parse_with(&req, &TrustedProxies::new().hops(2)),
+        ip("1.1.1.1")
+    );
+
+    // scheme and host of the client
+    let req = request(
+        "10.0.0.1",
+        &[
+            (RealIp::X_FORWARDED_FOR, "1.1.1.1, 10.0.0.2"),
+            (RealIp::X_FORWARDED_PROTO, "HTTPS, http"),
+            (RealIp::X_FORWARDED_HOST, "vidi.rs, internal"),
+        ],
+    );
+    let forwarded = proxies.resolve(&req);
+    assert_eq!(forwarded.proto.as_deref(), Some("https"));
+    assert_eq!(forwarded.host.as_deref(), Some("vidi.rs"));
+
+    let req = request("1.1.1.1", &[(RealIp::X_FORWARDED_PROTO, "https")]);
+    assert_eq!(proxies.resolve(&req).proto, None);
+
+    // without the remote address, e.g. on a Unix socket
+    let mut req = Request::default();
+    req.headers_mut()
+        .insert(RealIp::X_FORWARDED_FOR, HeaderValue::from_static("1.1.1.1"));
+    assert_eq!(RealIp::parse_with(&req, &TrustedProxies::new()), None);
+    assert_eq!(RealIp::parse_with(&req, &proxies), None);
+    assert_eq!(
+        RealIp::parse_with(&req, &TrustedProxies::new().hops(1)),
+        ip("1.1.1.1")
+    );
+
+    // the configured request
+    let mut req = request("10.0.0.1", &[(RealIp::X_FORWARDED_FOR, "6.6.6.6, 1.1.1.1")]);
+    assert_eq!(req.realip(), ip("10.0.0.1"));
+    req.extensions_mut().insert(proxies);
+    assert_eq!(req.realip(), ip("1.1.1.1"));
+}
@@ -0,0 +1 @@
+0123456789
\ No newline at end of file
@@ -0,0 +1,240 @@
+//! `Cache-Control` policies of the static files.
+//!
+//! ```
+//! use vidi_handlers::cache::CachePolicy;
+//!
+//! let policy = CachePolicy::new()
+//!     .fingerprinted(CachePolicy::IMMUTABLE)
+//!     .glob("index.html", CachePolicy::NO_CACHE)
+//!     .extension("css", "public, max-age=3600");
+//!
+//! assert_eq!(
+//!     policy.get("assets/app.3f2a9c1b.js").unwrap(),
+//!     CachePolicy::IMMUTABLE
+//! );
+//! ```
+//!
+//! The policies are applied by `serve::File::cache_control`, `serve::Dir::cache_control` and
+//! the same methods of `embed`.
+
+use vidi_core::header::HeaderValue;
+
+#[cfg(feature = "manifest")]
+mod manifest;
+
+#[cfg(feature = "manifest")]
+pub use manifest::Manifest;
+
+/// The `Cache-Control` policies of the static files.
+///
+/// The rules are matched in order, the first matched rule wins.
+#[derive(Clone, Debug, Default)]
+pub struct CachePolicy {
+    rules: Vec<(Rule, HeaderValue)>,
+}
+
+#[derive(Clone, Debug)]
+enum Rule {
+    Glob(String),
+    Fingerprinted,
+}
+
+impl CachePolicy {
+    /// The policy of the fingerprinted files, which never change.
+    pub const IMMUTABLE: &'static str = "public, max-age=31536000, immutable";
+
+    /// The policy of the files which must be revalidated, e.g. `index.html`.
+    pub const NO_CACHE: &'static str = "no-cache";
+
+    /// Creates an empty policy.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the `value` to the paths which match the glob `pattern`.
+    ///
+    /// `*` matches any characters except `/`, `?` matches any character except `/` and `**`
+    /// matches any directories. A pattern without `/` matches the file name only,
+    /// e.g. `index.html` matches `docs/index.html`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the `value` is not a valid header value.
+    #[must_use]
+    pub fn glob<V>(self, pattern: impl Into<String>, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.rule(Rule::Glob(pattern.into()), value)
+    }
+
+    /// Applies the `value` to the files with the extension, e.g. `css`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the `value` is not a valid header value.
+    #[must_use]
+    pub fn extension<V>(self, extension: &str, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.glob(format!("*.{}", extension.trim_start_matches('.')), value)
+    }
+
+    /// Applies the `value` to the fingerprinted files, e.g. `app.3f2a9c1b.js` or
+    /// `index-BvKJ4x2a.js`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the `value` is not a valid header value.
+    #[must_use]
+    pub fn fingerprinted<V>(self, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.rule(Rule::Fingerprinted, value)
+    }
+
+    /// Applies the `value` to the files which are not matched by the other rules.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the `value` is not a valid header value.
+    #[must_use]
+    pub fn fallback<V>(self, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.glob("**", value)
+    }
+
+    fn rule<V>(mut self, rule: Rule, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.rules
+            .push((rule, value.try_into().expect("invalid Cache-Control value")));
+        self
+    }
+
+    /// Gets the `Cache-Control` value of the relative path, e.g. `assets/app.js`.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&HeaderValue> {
+        let path = path.trim_start_matches('/');
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        self.rules
+            .iter()
+            .find(|(rule, _)| match rule {
+                Rule::Glob(pattern) if pattern.contains('/') || pattern == "**" => {
+                    glob(pattern.as_bytes(), path.as_bytes())
+                }
+                Rule::Glob(pattern) => glob(pattern.as_bytes(), name.as_bytes()),
+                Rule::Fingerprinted => is_fingerprinted(name),
+            })
+            .map(|(_, value)| value)
+    }
+}
+
+/// Checks if the file name contains a content hash before its extension,
+/// e.g. `app.3f2a9c1b.js` or `index-BvKJ4x2a.js`.
+///
+/// The hash has at least 8 characters, which are hexadecimal digits or a mix of
+/// letters and digits.
+#[must_use]
+pub fn is_fingerprinted(name: &str) -> bool {
+    let Some((stem, _)) = name.rsplit_once('.') else {
+        return false;
+    };
+    let Some(hash) = stem.rsplit(['.', '-']).next().filter(|hash| *hash != stem) else {
+        return false;
+    };
+
+    hash.len() >= 8
+        && hash.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        && (hash.bytes().all(|b| b.is_ascii_hexdigit())
+            || (hash.bytes().any(|b| b.is_ascii_digit())
+                && hash.bytes().any(|b| b.is_ascii_alphabetic())))
+}
+
+fn glob(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*'] => true,
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob(rest, path)
+                || path
+                    .iter()
+                    .position(|b| *b == b'/')
+                    .is_some_and(|n| glob(pattern, &path[n + 1..]))
+        }
+        [b'*', rest @ ..] => {
+            glob(rest, path) || matches!(path, [c, tail @ ..] if *c != b'/' && glob(pattern, tail))
+        }
+        [b'?', rest @ ..] => matches!(path, [c, tail @ ..] if *c != b'/' && glob(rest, tail)),
+        [p, rest @ ..] => matches!(path, [c, tail @ ..] if c == p && glob(rest, tail)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachePolicy, is_fingerprinted};
+
+    #[test]
+    fn fingerprinted() {
+        for name in [
+            "app.3f2a9c1b.js",
+            "index-BvKJ4x2a.js",
+            "style.min.0123456789abcdef.css",
+            "logo-deadbeef.svg",
+        ] {
+            assert!(is_fingerprinted(name), "{name}");
+        }
+        for name in [
+            "app.js",
+            "my-component.js",
+            "jquery-3.7.1.min.js",
+            "3f2a9c1b.js",
+            "README",
+            "app.3f2a9c.js",
+        ] {
+            assert!(!is_fingerprinted(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let policy = CachePolicy::new()
+            .fingerprinted(CachePolicy::IMMUTABLE)
+            .glob("index.html", CachePolicy::NO_CACHE)
+            .glob("fonts/**", "public, max-age=86400")
+            .glob("img/*.png", "public, max-age=600")
+            .extension(".css", "public, max-age=3600");
+
+        for (path, value) in [
+            ("assets/app.3f2a9c1b.js", Some(CachePolicy::IMMUTABLE)),
+            ("index.html", Some(CachePolicy::NO_CACHE)),
+            ("/docs/index.html", Some(CachePolicy::NO_CACHE)),
+            ("fonts/a/b.woff2", Some("public, max-age=86400")),
+            ("img/logo.png", Some("public, max-age=600")),
+            ("img/icons/logo.png", None),
+            ("css/site.css", Some("public, max-age=3600")),
+            ("app.js", None),
+        ] {
+            assert_eq!(
+                policy.get(path).map(|v| v.to_str().unwrap()),
+                value,
+                "{path}"
+            );
+        }
+
+        let policy = policy.fallback("no-store");
+        assert_eq!(policy.get("app.js").unwrap(), "no-store");
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+/// The asset manifest, which maps the logical names to the fingerprinted paths,
+/// e.g. `app.js` to `app.3f2a9c1b7d5e4f60.js`.
+///
+/// The assets are fingerprinted in a build script and the manifest is loaded by the templates.
+///
+/// ```no_run
+/// use vidi_handlers::cache::Manifest;
+///
+/// # fn main() -> std::io::Result<()> {
+/// // build.rs
+/// let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+/// println!("cargo::rerun-if-changed=assets");
+/// Manifest::build("assets", out_dir.join("assets"))?.write(out_dir.join("manifest.json"))?;
+///
+/// // main.rs
+/// let manifest = Manifest::load(out_dir.join("manifest.json"))?;
+/// assert!(manifest.path("app.js").starts_with("app."));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    assets: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// The length of the content hash in hexadecimal digits.
+    const HASH_LEN: usize = 16;
+
+    /// Creates an empty manifest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies the files of the `src` directory into the `dest` directory with the fingerprinted
+    /// names, the content hash is inserted before the extension.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the files could not be read or written.
+    pub fn build(src: impl AsRef<Path>, dest: impl AsRef<Path>) -> io::Result<Self> {
+        let src = src.as_ref();
+        let dest = dest.as_ref();
+        let mut manifest = Self::new();
+        let mut dirs = vec![PathBuf::new()];
+
+        while let Some(dir) = dirs.pop() {
+            fs::create_dir_all(dest.join(&dir))?;
+
+            for entry in fs::read_dir(src.join(&dir))? {
+                let entry = entry?;
+                let relative = dir.join(entry.file_name());
+
+                if entry.file_type()?.is_dir() {
+                    dirs.push(relative);
+                    continue;
+                }
+
+                let data = fs::read(entry.path())?;
+                let hash = hex::encode(Sha256::digest(&data));
+                let fingerprinted = fingerprint(&relative, &hash[..Self::HASH_LEN]);
+                fs::write(dest.join(&fingerprinted), data)?;
+                manifest.insert(slash(&relative), slash(&fingerprinted));
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Loads the manifest from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file could not be read or parsed.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the manifest from JSON, e.g. which is included by `include_str!`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the JSON is not an object of strings.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json)
+            .map(|assets| Self { assets })
+            .map_err(io::Error::other)
+    }
+
+    /// Writes the manifest into a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file could not be written.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    /// Serializes the manifest to JSON.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.assets).unwrap_or_default()
+    }
+
+    /// Inserts an asset.
+    pub fn insert(&mut self, name: impl Into<String>, path: impl Into<String>) {
+        self.assets.insert(name.into(), path.into());
+    }
+
+    /// Gets the fingerprinted path of the asset.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.assets
+            .get(name.trim_start_matches('/'))
+            .map(String::as_str)
+    }
+
+    /// Gets the fingerprinted path of the asset, or the name if it is not in the manifest.
+    #[must_use]
+    pub fn path<'a>(&'a self, name: &'a str) -> &'a str {
+        self.get(name).unwrap_or(name)
+    }
+
+    /// An iterator of the logical names and the fingerprinted paths.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.assets.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Inserts the hash before the extension, e.g. `css/site.css` to `css/site.<hash>.css`.
+fn fingerprint(path: &Path, hash: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem}.{hash}.{ext}"),
+        _ => format!("{name}.{hash}"),
+    };
+    path.with_file_name(name)
+}
+
+fn slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Manifest;
+    use crate::cache::is_fingerprinted;
+
+    #[test]
+    fn build() -> std::io::Result<()> {
+        let root = std::env::temp_dir().join(format!("vidi-manifest-{}", std::process::id()));
+        let src = root.join("src");
+        std::fs::create_dir_all(src.join("css"))?;
+        std::fs::write(src.join("app.js"), "console.log(1)")?;
+        std::fs::write(src.join("css/site.css"), "body {}")?;
+
+        let manifest = Manifest::build(&src, root.join("dist"))?;
+        assert_eq!(manifest.iter().count(), 2);
+
+        let app = manifest.get("app.js").unwrap();
+        let parts = app.split('.').collect::<Vec<_>>();
+        assert_eq!((parts[0], parts[2]), ("app", "js"));
+        assert!(is_fingerprinted(app));
+        assert_eq!(
+            std::fs::read_to_string(root.join("dist").join(app))?,
+            "console.log(1)"
+        );
+
+        let site = manifest.path("/css/site.css");
+        assert!(site.starts_with("css/site.") && is_fingerprinted(site));
+        assert_eq!(manifest.path("missing.js"), "missing.js");
+
+        manifest.write(root.join("manifest.json"))?;
+        assert_eq!(Manifest::load(root.join("manifest.json"))?, manifest);
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+}
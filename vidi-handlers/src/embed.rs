@@ -1,21 +1,39 @@
 //! Static files serving and embedding.
 
-use std::{borrow::Cow, marker::PhantomData};
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    ops,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
 use http_body_util::Full;
 use rust_embed::{EmbeddedFile, RustEmbed};
 use vidi_core::{
-    Handler, IntoResponse, Method, Request, RequestExt, Response, Result, StatusCode,
-    header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    Bytes, BytesMut, Handler, IntoResponse, Method, Request, RequestExt, Response, ResponseExt,
+    Result, StatusCode, headers::ETag,
 };
 
+use crate::engine::{Options, Representation, Selection, options};
+
+pub use crate::engine::Error;
+
 /// Serve a single embedded file.
 #[derive(Debug)]
-pub struct File<E>(Cow<'static, str>, PhantomData<E>);
+pub struct File<E> {
+    path: Cow<'static, str>,
+    options: Options,
+    _marker: PhantomData<E>,
+}
 
 impl<E> Clone for File<E> {
     fn clone(&self) -> Self {
-        Self(self.0.clone(), PhantomData)
+        Self {
+            path: self.path.clone(),
+            options: self.options.clone(),
+            _marker: PhantomData,
+        }
     }
 }
 
@@ -23,8 +41,14 @@ impl<E> File<E> {
     /// Serve a new file by the specified path.
     #[must_use]
     pub fn new(path: &'static str) -> Self {
-        Self(path.into(), PhantomData)
+        Self {
+            path: path.into(),
+            options: Options::default(),
+            _marker: PhantomData,
+        }
     }
+
+    options!();
 }
 
 #[vidi_core::async_trait]
@@ -35,23 +59,83 @@ where
     type Output = Result<Response>;
 
     async fn call(&self, req: Request) -> Self::Output {
-        serve::<E>(&self.0, &req)
+        serve::<E>(&self.path, E::get(&self.path), &req, &self.options)
     }
 }
 
 /// Serve a embedded directory.
 #[derive(Debug)]
-pub struct Dir<E>(PhantomData<E>);
+pub struct Dir<E> {
+    fallback: Option<&'static str>,
+    not_found: Option<&'static str>,
+    options: Options,
+    _marker: PhantomData<E>,
+}
 
 impl<E> Clone for Dir<E> {
     fn clone(&self) -> Self {
-        Self(PhantomData)
+        Self {
+            fallback: self.fallback,
+            not_found: self.not_found,
+            options: self.options.clone(),
+            _marker: PhantomData,
+        }
     }
 }
 
 impl<E> Default for Dir<E> {
     fn default() -> Self {
-        Self(PhantomData)
+        Self {
+            fallback: None,
+            not_found: None,
+            options: Options::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Dir<E> {
+    /// Enable the single-page application mode, the navigation requests of the missing paths
+    /// fall back to the embedded `index`, e.g. `index.html`.
+    ///
+    /// The missing assets, e.g. `/app.js`, are still not found.
+    #[must_use]
+    pub const fn spa(mut self, index: &'static str) -> Self {
+        self.fallback = Some(index);
+        self
+    }
+
+    /// Responds the custom embedded page if a file is not found, e.g. `404.html`.
+    #[must_use]
+    pub const fn not_found_page(mut self, page: &'static str) -> Self {
+        self.not_found = Some(page);
+        self
+    }
+
+    options!();
+}
+
+impl<E> Dir<E>
+where
+    E: RustEmbed,
+{
+    /// Falls back to the index of the single-page application or responds the not found page.
+    fn not_found(&self, req: &Request) -> Result<Response> {
+        if let Some(index) = self.fallback {
+            if crate::spa::is_navigation(req) {
+                if let Some(file) = E::get(index) {
+                    return serve::<E>(index, Some(file), req, &self.options);
+                }
+            }
+        }
+
+        if let Some(file) = self.not_found.and_then(E::get) {
+            let mut res = Response::html(Full::from(file.data));
+            *res.status_mut() = StatusCode::NOT_FOUND;
+            return Ok(res);
+        }
+
+        Err(StatusCode::NOT_FOUND.into_error())
     }
 }
 
@@ -63,46 +147,273 @@ where
     type Output = Result<Response>;
 
     async fn call(&self, req: Request) -> Self::Output {
-        serve::<E>(
-            req.route_info()
-                .params
-                .first()
-                .map(|(_, v)| v)
-                .map_or("index.html", |p| p),
-            &req,
-        )
+        let path = req
+            .route_info()
+            .params
+            .first()
+            .map(|(_, v)| v)
+            .map_or("index.html", |p| p);
+
+        match E::get(path) {
+            None if matches!(*req.method(), Method::GET | Method::HEAD) => self.not_found(&req),
+            file => serve::<E>(path, file, &req, &self.options),
+        }
     }
 }
 
-fn serve<E>(path: &str, req: &Request) -> Result<Response>
+fn serve<E>(
+    path: &str,
+    file: Option<EmbeddedFile>,
+    req: &Request,
+    options: &Options,
+) -> Result<Response>
 where
-    E: RustEmbed + Send + Sync + 'static,
+    E: RustEmbed,
 {
-    if Method::GET != req.method() {
-        Err(StatusCode::METHOD_NOT_ALLOWED.into_error())?;
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        Err(Error::MethodNotAllowed)?;
     }
 
-    match E::get(path) {
-        Some(EmbeddedFile { data, metadata }) => {
-            let hash = hex::encode(metadata.sha256_hash());
+    let Some(file) = file else {
+        Err(StatusCode::NOT_FOUND.into_error())?
+    };
 
-            if req
-                .headers()
-                .get(IF_NONE_MATCH)
-                .is_some_and(|etag| etag.to_str().unwrap_or("000000").eq(&hash))
-            {
-                Err(StatusCode::NOT_MODIFIED.into_error())?;
+    // the precompressed variants are embedded as the siblings, e.g. `app.js.br`
+    let (EmbeddedFile { data, metadata }, encoding) = options
+        .precompressed
+        .candidates(req.headers())
+        .into_iter()
+        .find_map(|encoding| {
+            E::get(&format!("{path}{}", encoding.extension())).map(|file| (file, Some(encoding)))
+        })
+        .unwrap_or((file, None));
+
+    let data = match data {
+        Cow::Borrowed(data) => Bytes::from_static(data),
+        Cow::Owned(data) => Bytes::from(data),
+    };
+
+    let representation = Representation {
+        len: data.len() as u64,
+        content_type: mime_guess::from_path(path).first_or_octet_stream(),
+        etag: ETag::from_str(&format!(r#""{}""#, hex::encode(metadata.sha256_hash()))).ok(),
+        modified: metadata
+            .last_modified()
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+        encoding,
+        vary: options.precompressed.is_enabled(),
+        cache_control: options.cache_control(path),
+    };
+    let selection = representation.select(req.headers(), options.max_ranges)?;
+
+    let res = match &selection {
+        Selection::NotModified => Response::empty(),
+        _ if req.method() == Method::HEAD => Response::empty(),
+        Selection::Full => Response::new(Full::from(data).into()),
+        Selection::Range(range) => Response::new(Full::from(slice(&data, range)).into()),
+        Selection::Multipart(multipart) => {
+            let mut body = BytesMut::with_capacity(usize::try_from(multipart.len).unwrap_or(0));
+            for (head, range) in &multipart.parts {
+                body.extend_from_slice(head);
+                body.extend_from_slice(&slice(&data, range));
             }
+            body.extend_from_slice(&multipart.trailer);
+            Response::new(Full::from(body.freeze()).into())
+        }
+    };
+
+    Ok(representation.respond(&selection, res))
+}
+
+/// Slices the data, the range is within the length of the data.
+#[allow(clippy::cast_possible_truncation)]
+fn slice(data: &Bytes, range: &ops::Range<u64>) -> Bytes {
+    data.slice(range.start as usize..range.end as usize)
+}
 
-            Response::builder()
-                .header(
-                    CONTENT_TYPE,
-                    mime_guess::from_path(path).first_or_octet_stream().as_ref(),
-                )
-                .header(ETAG, hash)
-                .body(Full::from(data).into())
-                .map_err(Into::into)
+#[cfg(test)]
+mod tests {
+    use super::{Dir, File};
+    use http_body_util::BodyExt;
+    use rust_embed::RustEmbed;
+    use std::sync::Arc;
+    use vidi_core::{
+        Handler, IntoResponse, Method, Request, Result, StatusCode,
+        header::{
+            ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, ETAG,
+            IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE, LAST_MODIFIED, RANGE,
+        },
+        types::{Params, RouteInfo},
+    };
+
+    #[derive(RustEmbed)]
+    #[folder = "fixtures/embed"]
+    struct Assets;
+
+    fn request(path: &'static str, headers: &[(&'static str, &str)]) -> Request {
+        let mut req: Request = Request::default();
+        req.extensions_mut().insert(Arc::new(RouteInfo {
+            id: 2,
+            pattern: "/*".to_string(),
+            params: Into::<Params>::into(vec![("*1", path)]),
+        }));
+        *req.uri_mut() = format!("/{path}").parse().unwrap();
+        for (name, value) in headers {
+            req.headers_mut().insert(*name, value.parse().unwrap());
         }
-        None => Err(StatusCode::NOT_FOUND.into_error()),
+        req
+    }
+
+    #[tokio::test]
+    async fn embed() -> Result<()> {
+        let serve = Dir::<Assets>::default();
+
+        let res = serve.call(request("app.js", &[])).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()[ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        let last_modified = res.headers()[LAST_MODIFIED].to_str().unwrap().to_string();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "0123456789");
+
+        // conditional requests
+        let res = serve
+            .call(request("app.js", &[(IF_NONE_MATCH.as_str(), &etag)]))
+            .await?;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[ETAG], etag);
+        let res = serve
+            .call(request(
+                "app.js",
+                &[(IF_MODIFIED_SINCE.as_str(), &last_modified)],
+            ))
+            .await?;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        // `If-None-Match` takes precedence over `If-Modified-Since`
+        let res = serve
+            .call(request(
+                "app.js",
+                &[
+                    (IF_NONE_MATCH.as_str(), "\"other\""),
+                    (IF_MODIFIED_SINCE.as_str(), &last_modified),
+                ],
+            ))
+            .await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        // `If-Match` takes precedence over `If-Unmodified-Since`
+        let res = serve
+            .call(request(
+                "app.js",
+                &[
+                    (IF_MATCH.as_str(), &etag),
+                    (
+                        IF_UNMODIFIED_SINCE.as_str(),
+                        "Thu, 01 Jan 1970 00:00:00 GMT",
+                    ),
+                ],
+            ))
+            .await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = serve
+            .call(request(
+                "app.js",
+                &[(
+                    IF_UNMODIFIED_SINCE.as_str(),
+                    "Thu, 01 Jan 1970 00:00:00 GMT",
+                )],
+            ))
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        // HEAD
+        let mut req = request("app.js", &[]);
+        *req.method_mut() = Method::HEAD;
+        let res = serve.call(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_LENGTH], "10");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert!(bytes.is_empty());
+
+        let mut req = request("app.js", &[]);
+        *req.method_mut() = Method::POST;
+        let res = serve.call(req).await.unwrap_err().into_response();
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        // ranges
+        let res = serve
+            .call(request("app.js", &[(RANGE.as_str(), "bytes=2-4")]))
+            .await?;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers()[CONTENT_RANGE], "bytes 2-4/10");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "234");
+
+        let res = serve
+            .call(request("app.js", &[(RANGE.as_str(), "bytes=0-0,-2")]))
+            .await?;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let length: usize = res.headers()[CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes.len(), length);
+
+        let res = serve
+            .call(request("app.js", &[(RANGE.as_str(), "bytes=20-")]))
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn embed_precompressed() -> Result<()> {
+        let serve = File::<Assets>::new("index.html").precompressed_gzip();
+
+        let res = serve
+            .call(request("", &[(ACCEPT_ENCODING.as_str(), "gzip, br")]))
+            .await?;
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "gzip");
+
+        let res = serve
+            .call(request("", &[(ACCEPT_ENCODING.as_str(), "br")]))
+            .await?;
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "<app>");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn embed_spa() -> Result<()> {
+        let serve = Dir::<Assets>::default()
+            .spa("index.html")
+            .not_found_page("404.html");
+        let html = "text/html,*/*;q=0.8";
+
+        let res = serve
+            .call(request("users/1", &[(ACCEPT.as_str(), html)]))
+            .await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "<app>");
+
+        let res = serve
+            .call(request("main.js", &[(ACCEPT.as_str(), html)]))
+            .await?;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "<missing>");
+
+        Ok(())
     }
 }
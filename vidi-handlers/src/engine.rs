@@ -0,0 +1,240 @@
+//! The shared engine of the static files: conditional requests, ranges and content negotiation.
+
+use std::{ops, time::SystemTime};
+
+use vidi_core::{
+    Response, StatusCode,
+    header::{ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, HeaderValue, VARY},
+    headers::{
+        AcceptRanges, ContentLength, ContentRange, ContentType, ETag, HeaderMap, HeaderMapExt,
+        IfMatch, IfModifiedSince, IfNoneMatch, IfRange, IfUnmodifiedSince, LastModified, Range,
+    },
+};
+
+mod error;
+mod precompressed;
+mod range;
+
+pub use error::Error;
+pub(crate) use precompressed::{Encoding, Precompressed};
+pub(crate) use range::Multipart;
+
+use crate::cache::CachePolicy;
+
+/// The options of serving the files.
+#[derive(Clone, Debug)]
+pub(crate) struct Options {
+    pub(crate) precompressed: Precompressed,
+    pub(crate) max_ranges: usize,
+    pub(crate) cache_control: Option<CachePolicy>,
+}
+
+impl Options {
+    /// Gets the `Cache-Control` value of the relative path.
+    pub(crate) fn cache_control(&self, path: &str) -> Option<HeaderValue> {
+        self.cache_control.as_ref()?.get(path).cloned()
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            precompressed: Precompressed::default(),
+            max_ranges: 16,
+            cache_control: None,
+        }
+    }
+}
+
+macro_rules! options {
+    () => {
+        /// Serves the precompressed `.br` sibling of the file, e.g. `app.js.br`, if the client
+        /// accepts `br`.
+        #[must_use]
+        pub const fn precompressed_br(mut self) -> Self {
+            self.options.precompressed.br = true;
+            self
+        }
+
+        /// Serves the precompressed `.zst` sibling of the file, e.g. `app.js.zst`, if the client
+        /// accepts `zstd`.
+        #[must_use]
+        pub const fn precompressed_zstd(mut self) -> Self {
+            self.options.precompressed.zstd = true;
+            self
+        }
+
+        /// Serves the precompressed `.gz` sibling of the file, e.g. `app.js.gz`, if the client
+        /// accepts `gzip`.
+        #[must_use]
+        pub const fn precompressed_gzip(mut self) -> Self {
+            self.options.precompressed.gzip = true;
+            self
+        }
+
+        /// Sets the maximum number of the ranges in a request, the whole file is served if there
+        /// are more ranges.
+        ///
+        /// Default is 16.
+        #[must_use]
+        pub const fn max_ranges(mut self, max: usize) -> Self {
+            self.options.max_ranges = max;
+            self
+        }
+
+        /// Sets the `Cache-Control` policies of the files.
+        #[must_use]
+        pub fn cache_control(mut self, policy: $crate::cache::CachePolicy) -> Self {
+            self.options.cache_control.replace(policy);
+            self
+        }
+    };
+}
+
+pub(crate) use options;
+
+/// The selected content of a representation.
+#[derive(Debug)]
+pub(crate) enum Selection {
+    NotModified,
+    Full,
+    Range(ops::Range<u64>),
+    Multipart(Multipart),
+}
+
+/// The selected representation of a file, RFC 9110 section 3.2.
+#[derive(Debug)]
+pub(crate) struct Representation {
+    pub(crate) len: u64,
+    pub(crate) content_type: mime_guess::Mime,
+    pub(crate) etag: Option<ETag>,
+    pub(crate) modified: Option<SystemTime>,
+    pub(crate) encoding: Option<Encoding>,
+    /// The response varies by `Accept-Encoding`.
+    pub(crate) vary: bool,
+    pub(crate) cache_control: Option<HeaderValue>,
+}
+
+impl Representation {
+    /// Evaluates the preconditions and the ranges, RFC 9110 section 13.2.2.
+    pub(crate) fn select(
+        &self,
+        headers: &HeaderMap,
+        max_ranges: usize,
+    ) -> Result<Selection, Error> {
+        let etag = self.etag.as_ref();
+
+        // `If-Unmodified-Since` is ignored when `If-Match` is present
+        let precondition_failed = match headers.typed_get::<IfMatch>() {
+            Some(if_match) => etag.is_some_and(|etag| !if_match.precondition_passes(etag)),
+            None => {
+                matches!((headers.typed_get::<IfUnmodifiedSince>(), self.modified), (Some(if_unmodified_since), Some(modified)) if !if_unmodified_since.precondition_passes(modified))
+            }
+        };
+        if precondition_failed {
+            Err(Error::PreconditionFailed)?;
+        }
+
+        // `If-Modified-Since` is ignored when `If-None-Match` is present
+        let not_modified = match headers.typed_get::<IfNoneMatch>() {
+            Some(if_none_match) => {
+                etag.is_some_and(|etag| !if_none_match.precondition_passes(etag))
+            }
+            None => {
+                matches!((headers.typed_get::<IfModifiedSince>(), self.modified), (Some(if_modified_since), Some(modified)) if !if_modified_since.is_modified(modified))
+            }
+        };
+        if not_modified {
+            return Ok(Selection::NotModified);
+        }
+
+        // the ranges are ignored if the `If-Range` is not matched
+        let last_modified = self.modified.map(LastModified::from);
+        let ranges = match headers.typed_get::<Range>() {
+            Some(range)
+                if headers
+                    .typed_get::<IfRange>()
+                    .is_none_or(|if_range| !if_range.is_modified(etag, last_modified.as_ref())) =>
+            {
+                range::satisfiable(&range, self.len, max_ranges)?
+            }
+            _ => None,
+        };
+
+        Ok(match ranges {
+            Some(mut ranges) if ranges.len() == 1 => {
+                let range = ranges.remove(0);
+                if range == (0..self.len) {
+                    Selection::Full
+                } else {
+                    Selection::Range(range)
+                }
+            }
+            Some(ranges) => {
+                Selection::Multipart(Multipart::new(ranges, self.content_type.as_ref(), self.len))
+            }
+            None => Selection::Full,
+        })
+    }
+
+    /// Inserts the headers of the selected content into the response.
+    pub(crate) fn respond(self, selection: &Selection, mut res: Response) -> Response {
+        let headers = res.headers_mut();
+
+        if self.vary {
+            headers.insert(VARY, HeaderValue::from_static(ACCEPT_ENCODING.as_str()));
+        }
+
+        if let Some(etag) = self.etag {
+            headers.typed_insert(etag);
+        }
+
+        if let Some(cache_control) = self.cache_control {
+            headers.insert(CACHE_CONTROL, cache_control);
+        }
+
+        let length = match selection {
+            Selection::NotModified => {
+                *res.status_mut() = StatusCode::NOT_MODIFIED;
+                return res;
+            }
+            Selection::Full => {
+                headers.typed_insert(ContentType::from(self.content_type));
+                self.len
+            }
+            Selection::Range(range) => {
+                headers.typed_insert(ContentType::from(self.content_type));
+                if let Ok(content_range) = ContentRange::bytes(range.clone(), self.len) {
+                    headers.typed_insert(content_range);
+                }
+                range.end - range.start
+            }
+            Selection::Multipart(multipart) => {
+                if let Ok(content_type) = HeaderValue::try_from(format!(
+                    "multipart/byteranges; boundary={}",
+                    multipart.boundary
+                )) {
+                    headers.insert(CONTENT_TYPE, content_type);
+                }
+                multipart.len
+            }
+        };
+
+        headers.typed_insert(AcceptRanges::bytes());
+        headers.typed_insert(ContentLength(length));
+
+        if let Some(encoding) = self.encoding {
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+        }
+
+        if let Some(modified) = self.modified {
+            headers.typed_insert(LastModified::from(modified));
+        }
+
+        if !matches!(selection, Selection::Full) {
+            *res.status_mut() = StatusCode::PARTIAL_CONTENT;
+        }
+
+        res
+    }
+}
@@ -1,4 +1,7 @@
-use vidi_core::{IntoResponse, Response, StatusCode, ThisError};
+use vidi_core::{
+    IntoResponse, Response, StatusCode, ThisError,
+    headers::{ContentRange, HeaderMapExt},
+};
 
 /// Static file serving Error.
 #[derive(Debug, ThisError)]
@@ -26,7 +29,11 @@ pub enum Error {
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        (
+        let content_range = match self {
+            Self::RangeUnsatisfied(len) => Some(ContentRange::unsatisfied_bytes(len)),
+            _ => None,
+        };
+        let mut res = (
             match self {
                 Self::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
                 Self::InvalidPath => StatusCode::BAD_REQUEST,
@@ -36,7 +43,11 @@ impl IntoResponse for Error {
             },
             self.to_string(),
         )
-            .into_response()
+            .into_response();
+        if let Some(content_range) = content_range {
+            res.headers_mut().typed_insert(content_range);
+        }
+        res
     }
 }
 
@@ -0,0 +1,148 @@
+//! Precompressed variants of the files, e.g. `app.js.br`.
+
+#[cfg(feature = "serve")]
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use vidi_core::header::{ACCEPT_ENCODING, HeaderMap};
+
+/// The encoding of a precompressed file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Brotli,
+    Zstd,
+    Gzip,
+}
+
+impl Encoding {
+    /// The preferred order if the qualities are equal.
+    const ALL: [Self; 3] = [Self::Brotli, Self::Zstd, Self::Gzip];
+
+    /// The value of `Content-Encoding`.
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// The extension of the sibling file.
+    pub(crate) const fn extension(self) -> &'static str {
+        match self {
+            Self::Brotli => ".br",
+            Self::Zstd => ".zst",
+            Self::Gzip => ".gz",
+        }
+    }
+
+    fn matches(self, coding: &str) -> bool {
+        coding.eq_ignore_ascii_case(self.name())
+            || (self == Self::Gzip && coding.eq_ignore_ascii_case("x-gzip"))
+    }
+}
+
+/// The enabled encodings of the precompressed files.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Precompressed {
+    pub(crate) br: bool,
+    pub(crate) zstd: bool,
+    pub(crate) gzip: bool,
+}
+
+impl Precompressed {
+    pub(crate) const fn is_enabled(self) -> bool {
+        self.br || self.zstd || self.gzip
+    }
+
+    const fn contains(self, encoding: Encoding) -> bool {
+        match encoding {
+            Encoding::Brotli => self.br,
+            Encoding::Zstd => self.zstd,
+            Encoding::Gzip => self.gzip,
+        }
+    }
+
+    /// The enabled encodings which are accepted by the client, the most acceptable first.
+    pub(crate) fn candidates(self, headers: &HeaderMap) -> Vec<Encoding> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+
+        let accepted = accepted(headers);
+        let mut candidates = Encoding::ALL
+            .into_iter()
+            .filter(|encoding| self.contains(*encoding))
+            .filter_map(|encoding| {
+                let q = accepted
+                    .iter()
+                    .find(|(coding, _)| encoding.matches(coding))
+                    .or_else(|| accepted.iter().find(|(coding, _)| *coding == "*"))
+                    .map(|(_, q)| *q)?;
+                (q > 0).then_some((encoding, q))
+            })
+            .collect::<Vec<_>>();
+        // stable, keeps the preferred order
+        candidates.sort_by_key(|(_, q)| std::cmp::Reverse(*q));
+        candidates
+            .into_iter()
+            .map(|(encoding, _)| encoding)
+            .collect()
+    }
+
+    /// Picks the sibling file of the most acceptable encoding, e.g. `app.js.br`.
+    #[cfg(feature = "serve")]
+    pub(crate) async fn negotiate(
+        self,
+        path: &Path,
+        headers: &HeaderMap,
+    ) -> Option<(PathBuf, Encoding)> {
+        for encoding in self.candidates(headers) {
+            let mut sibling = OsString::from(path);
+            sibling.push(encoding.extension());
+            let sibling = PathBuf::from(sibling);
+            if tokio::fs::metadata(&sibling)
+                .await
+                .is_ok_and(|metadata| metadata.is_file())
+            {
+                return Some((sibling, encoding));
+            }
+        }
+
+        None
+    }
+}
+
+/// Parses the codings of `Accept-Encoding` with the qualities in thousandths.
+fn accepted(headers: &HeaderMap) -> Vec<(&str, u16)> {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let coding = parts.next().filter(|coding| !coding.is_empty())?;
+            let q = parts
+                .find_map(|param| param.strip_prefix("q=").or(param.strip_prefix("Q=")))
+                .map_or(Some(1000), parse_quality)?;
+            Some((coding, q))
+        })
+        .collect()
+}
+
+/// Parses a quality value, e.g. `0.8` to `800`.
+fn parse_quality(q: &str) -> Option<u16> {
+    let (int, frac) = q.split_once('.').unwrap_or((q, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let frac = format!("{frac:0<3}").parse::<u16>().ok()?;
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
@@ -0,0 +1,116 @@
+//! Byte ranges, RFC 9110 section 14.
+
+use std::{
+    collections::Bound,
+    ops,
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use vidi_core::{Bytes, headers::Range};
+
+use super::Error;
+
+/// Resolves the satisfiable ranges, which are sorted and the overlapping or adjacent ranges are
+/// coalesced.
+///
+/// Returns `None` if there are more ranges than the `max`, the whole file is served then.
+pub(super) fn satisfiable(
+    range: &Range,
+    len: u64,
+    max: usize,
+) -> Result<Option<Vec<ops::Range<u64>>>, Error> {
+    let mut ranges = Vec::new();
+
+    for (start, end) in range.satisfiable_ranges(len) {
+        if ranges.len() == max {
+            return Ok(None);
+        }
+
+        let start = match start {
+            Bound::Included(n) => n,
+            Bound::Excluded(n) => n.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match end {
+            Bound::Included(n) => n.saturating_add(1),
+            Bound::Excluded(n) => n,
+            Bound::Unbounded => len,
+        }
+        .min(len);
+
+        if start < end {
+            ranges.push(start..end);
+        }
+    }
+
+    if ranges.is_empty() {
+        Err(Error::RangeUnsatisfied(len))?;
+    }
+
+    ranges.sort_by_key(|range| range.start);
+
+    let mut coalesced: Vec<ops::Range<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match coalesced.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => coalesced.push(range),
+        }
+    }
+
+    Ok(Some(coalesced))
+}
+
+/// Generates a boundary of the `multipart/byteranges`.
+fn boundary() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    format!(
+        "{:08x}{:016x}",
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The layout of a `multipart/byteranges` body.
+#[derive(Debug)]
+pub(crate) struct Multipart {
+    pub(crate) boundary: String,
+    /// The head and the range of each part.
+    pub(crate) parts: Vec<(Bytes, ops::Range<u64>)>,
+    pub(crate) trailer: Bytes,
+    /// The length of the whole body.
+    pub(crate) len: u64,
+}
+
+impl Multipart {
+    pub(super) fn new(ranges: Vec<ops::Range<u64>>, content_type: &str, len: u64) -> Self {
+        let boundary = boundary();
+        let mut length = 0;
+        let parts = ranges
+            .into_iter()
+            .map(|range| {
+                let head = Bytes::from(format!(
+                    "\r\n--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: bytes {}-{}/{len}\r\n\r\n",
+                    range.start,
+                    range.end - 1,
+                ));
+                length += head.len() as u64 + range.end - range.start;
+                (head, range)
+            })
+            .collect();
+        let trailer = Bytes::from(format!("\r\n--{boundary}--\r\n"));
+        length += trailer.len() as u64;
+
+        Self {
+            boundary,
+            parts,
+            trailer,
+            len: length,
+        }
+    }
+}
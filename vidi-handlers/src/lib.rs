@@ -8,11 +8,29 @@
 ))]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(any(feature = "serve", feature = "embed", feature = "manifest"))]
+pub mod cache;
+
+#[cfg(any(feature = "serve", feature = "embed"))]
+mod engine;
+
+#[cfg(any(feature = "serve", feature = "webdav"))]
+mod path;
+
 #[cfg(feature = "serve")]
 pub mod serve;
 
+#[cfg(any(feature = "serve", feature = "embed"))]
+mod spa;
+
 #[cfg(feature = "embed")]
 pub mod embed;
 
+#[cfg(feature = "tus")]
+pub mod tus;
+
+#[cfg(feature = "webdav")]
+pub mod webdav;
+
 #[cfg(feature = "prometheus")]
 pub mod prometheus;
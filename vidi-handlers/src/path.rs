@@ -0,0 +1,11 @@
+//! The traversal protection of the request paths.
+
+/// Splits the decoded path into the segments, the empty and the current segments are skipped.
+///
+/// Returns `None` if a segment starts with `..` or contains a backslash or a NUL.
+pub(crate) fn segments(p: &str) -> Option<Vec<&str>> {
+    p.split('/')
+        .filter(|seg| !seg.is_empty() && *seg != ".")
+        .map(|seg| (!seg.starts_with("..") && !seg.contains(['\\', '\0'])).then_some(seg))
+        .collect()
+}
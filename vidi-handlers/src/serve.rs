@@ -1,49 +1,57 @@
 //! Static file serving and directory listing.
 
 use std::{
-    collections::Bound,
-    io::{Seek, SeekFrom},
+    io::{self, SeekFrom},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
     time::SystemTime,
 };
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio_util::io::ReaderStream;
 
 use vidi_core::{
     Handler, IntoResponse, Method, Request, RequestExt, Response, ResponseExt, Result, StatusCode,
-    headers::{
-        AcceptRanges, ContentLength, ContentRange, ContentType, ETag, HeaderMap, HeaderMapExt,
-        IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince, LastModified, Range,
-    },
+    headers::{ETag, HeaderMap},
 };
 
+mod byteranges;
 mod directory;
-mod error;
 
-use directory::Directory;
-pub use error::Error;
+use crate::engine::{Encoding, Options, Representation, Selection, options};
+use byteranges::ByteRanges;
+use directory::Renderer;
+
+pub use crate::engine::Error;
+pub use directory::{Entry, EntryKind, HiddenFiles, Listing, Order, Sort, Symlinks};
 
 /// Serve a single file.
 #[derive(Clone, Debug)]
 pub struct File {
     path: PathBuf,
+    options: Options,
 }
 
 impl File {
     /// Serve a new file by the specified path.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the path is not exists.
-    #[must_use]
-    pub fn new(path: impl Into<PathBuf>) -> Self {
+    /// Will return `Err` if the path is not a file.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, Error> {
         let path = path.into();
 
-        assert!(path.exists(), "{} not found", path.to_string_lossy());
+        if !path.is_file() {
+            Err(not_found(&path))?;
+        }
 
-        Self { path }
+        Ok(Self {
+            path,
+            options: Options::default(),
+        })
     }
+
+    options!();
 }
 
 #[vidi_core::async_trait]
@@ -51,7 +59,8 @@ impl Handler<Request> for File {
     type Output = Result<Response>;
 
     async fn call(&self, req: Request) -> Self::Output {
-        serve(&self.path, req.headers())
+        let name = self.path.file_name().map_or(self.path.as_path(), Path::new);
+        serve(&self.path, name, req.method(), req.headers(), &self.options).await
     }
 }
 
@@ -61,25 +70,40 @@ pub struct Dir {
     path: PathBuf,
     listing: bool,
     unlisted: Option<Vec<&'static str>>,
+    hidden: HiddenFiles,
+    symlinks: Symlinks,
+    page_size: usize,
+    renderer: Option<Renderer>,
+    fallback: Option<PathBuf>,
+    not_found: Option<PathBuf>,
+    options: Options,
 }
 
 impl Dir {
     /// Serve a new directory by the specified path.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the path is not exists.
-    #[must_use]
-    pub fn new(path: impl Into<PathBuf>) -> Self {
+    /// Will return `Err` if the path is not a directory.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, Error> {
         let path = path.into();
 
-        assert!(path.exists(), "{} not found", path.to_string_lossy());
+        if !path.is_dir() {
+            Err(not_found(&path))?;
+        }
 
-        Self {
+        Ok(Self {
             path,
             listing: false,
             unlisted: None,
-        }
+            hidden: HiddenFiles::default(),
+            symlinks: Symlinks::default(),
+            page_size: 1000,
+            renderer: None,
+            fallback: None,
+            not_found: None,
+            options: Options::default(),
+        })
     }
 
     /// Enable directory listing, `disabled` by default.
@@ -95,6 +119,116 @@ impl Dir {
         self.unlisted.replace(unlisted);
         self
     }
+
+    /// Sets the policy of the hidden files, whose names start with `.`.
+    ///
+    /// Default is [`HiddenFiles::Allow`].
+    #[must_use]
+    pub const fn hidden_files(mut self, policy: HiddenFiles) -> Self {
+        self.hidden = policy;
+        self
+    }
+
+    /// Sets the policy of the symbolic links.
+    ///
+    /// Default is [`Symlinks::Follow`].
+    #[must_use]
+    pub const fn symlinks(mut self, policy: Symlinks) -> Self {
+        self.symlinks = policy;
+        self
+    }
+
+    /// Sets the maximum number of the entries in a page of the directory listing, which is also
+    /// the upper limit of the `per_page` query parameter.
+    ///
+    /// Default is 1000.
+    #[must_use]
+    pub const fn listing_page_size(mut self, size: usize) -> Self {
+        self.page_size = if size == 0 { 1 } else { size };
+        self
+    }
+
+    /// Renders the HTML of the directory listing by the custom renderer.
+    ///
+    /// The listing is still responded as JSON if the client accepts `application/json`.
+    #[must_use]
+    pub fn listing_renderer<F>(mut self, renderer: F) -> Self
+    where
+        F: Fn(&Listing) -> String + Send + Sync + 'static,
+    {
+        self.renderer.replace(Renderer(Arc::new(renderer)));
+        self
+    }
+
+    /// Checks the hidden files and the symbolic links of the path within the directory.
+    async fn is_allowed(&self, path: &Path, p: &str) -> bool {
+        if self.hidden == HiddenFiles::Deny && p.split('/').any(|seg| seg.starts_with('.')) {
+            return false;
+        }
+
+        if self.symlinks == Symlinks::Follow {
+            return true;
+        }
+
+        let (Ok(root), Ok(canonical)) = (
+            tokio::fs::canonicalize(&self.path).await,
+            tokio::fs::canonicalize(path).await,
+        ) else {
+            // the missing paths are handled later
+            return true;
+        };
+
+        match self.symlinks {
+            Symlinks::WithinRoot => canonical.starts_with(root),
+            // the canonical path is the same if there are no symbolic links
+            _ => {
+                p.split('/')
+                    .filter(|seg| !seg.is_empty() && *seg != ".")
+                    .fold(root, |path, seg| path.join(seg))
+                    == canonical
+            }
+        }
+    }
+
+    /// Enable the single-page application mode, the navigation requests of the missing paths
+    /// fall back to the `index`, e.g. `index.html`, which is relative to the directory.
+    ///
+    /// The missing assets, e.g. `/app.js`, are still not found.
+    #[must_use]
+    pub fn spa(mut self, index: impl Into<PathBuf>) -> Self {
+        self.fallback.replace(index.into());
+        self
+    }
+
+    /// Responds the custom page if a file is not found, e.g. `404.html`, which is relative to
+    /// the directory.
+    #[must_use]
+    pub fn not_found_page(mut self, page: impl Into<PathBuf>) -> Self {
+        self.not_found.replace(page.into());
+        self
+    }
+
+    /// Falls back to the index of the single-page application or responds the not found page.
+    async fn not_found(&self, req: &Request) -> Result<Response> {
+        if let Some(fallback) = &self.fallback {
+            let index = self.path.join(fallback);
+            if crate::spa::is_navigation(req) && is_file(&index).await {
+                return serve(&index, fallback, req.method(), req.headers(), &self.options).await;
+            }
+        }
+
+        if let Some(page) = &self.not_found {
+            if let Ok(data) = tokio::fs::read(self.path.join(page)).await {
+                let mut res = Response::html(data);
+                *res.status_mut() = StatusCode::NOT_FOUND;
+                return Ok(res);
+            }
+        }
+
+        Err(StatusCode::NOT_FOUND.into_error())
+    }
+
+    options!();
 }
 
 #[vidi_core::async_trait]
@@ -102,7 +236,7 @@ impl Handler<Request> for Dir {
     type Output = Result<Response>;
 
     async fn call(&self, req: Request) -> Self::Output {
-        if req.method() != Method::GET {
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
             Err(Error::MethodNotAllowed)?;
         }
 
@@ -114,214 +248,628 @@ impl Handler<Request> for Dir {
                 .decode_utf8()
                 .map_err(|_| Error::InvalidPath)?;
             sanitize_path(&mut path, &p)?;
+            if !self.is_allowed(&path, &p).await {
+                return self.not_found(&req).await;
+            }
             prev = true;
         }
 
-        if !path.exists() {
-            Err(StatusCode::NOT_FOUND.into_error())?;
-        }
+        let Ok(metadata) = tokio::fs::metadata(&path).await else {
+            return self.not_found(&req).await;
+        };
 
-        if path.is_file() {
-            return serve(&path, req.headers());
+        if metadata.is_file() {
+            let name = path.strip_prefix(&self.path).unwrap_or(&path);
+            return serve(&path, name, req.method(), req.headers(), &self.options).await;
         }
 
         let index = path.join("index.html");
-        if index.exists() {
-            return serve(&index, req.headers());
+        if is_file(&index).await {
+            let name = index.strip_prefix(&self.path).unwrap_or(&index);
+            return serve(&index, name, req.method(), req.headers(), &self.options).await;
         }
 
         if self.listing {
-            return Directory::new(req.path(), prev, &path, self.unlisted.as_ref())
+            return Listing::read(self, &req, &path, prev)
+                .await
                 .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_error())
-                .map(IntoResponse::into_response);
+                .map(|listing| listing.respond(&req, self.renderer.as_ref()));
         }
 
-        Ok(StatusCode::NOT_FOUND.into_response())
+        self.not_found(&req).await
     }
 }
 
-fn sanitize_path<'a>(path: &'a mut PathBuf, p: &'a str) -> Result<()> {
-    for seg in p.split('/') {
-        if seg.starts_with("..") {
-            return Err(StatusCode::NOT_FOUND.into_error());
-        }
-        if seg.contains('\\') {
-            return Err(StatusCode::NOT_FOUND.into_error());
-        }
-        path.push(seg);
-    }
+fn sanitize_path(path: &mut PathBuf, p: &str) -> Result<()> {
+    path.extend(crate::path::segments(p).ok_or_else(|| StatusCode::NOT_FOUND.into_error())?);
     Ok(())
 }
 
-fn extract_etag(mtime: &SystemTime, size: u64) -> Option<ETag> {
-    ETag::from_str(&format!(
-        r#""{}-{}""#,
-        mtime
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .ok()?
-            .as_millis(),
-        size
+fn not_found(path: &Path) -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} not found", path.to_string_lossy()),
     ))
+}
+
+async fn is_file(path: &Path) -> bool {
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_file())
+}
+
+fn extract_etag(mtime: &SystemTime, size: u64, encoding: Option<Encoding>) -> Option<ETag> {
+    let mtime = mtime
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .as_millis();
+    ETag::from_str(&match encoding {
+        Some(encoding) => format!(r#""{mtime}-{size}-{}""#, encoding.name()),
+        None => format!(r#""{mtime}-{size}""#),
+    })
     .ok()
 }
 
-#[inline]
-fn serve(path: &Path, headers: &HeaderMap) -> Result<Response> {
-    let mut file = std::fs::File::open(path).map_err(Error::Io)?;
+/// Converts the relative path to the `/` separated path, e.g. `assets/app.js`.
+fn slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+async fn serve(
+    path: &Path,
+    name: &Path,
+    method: &Method,
+    headers: &HeaderMap,
+    options: &Options,
+) -> Result<Response> {
+    let (mut file, encoding) = match options.precompressed.negotiate(path, headers).await {
+        Some((sibling, encoding)) => (
+            tokio::fs::File::open(sibling).await.map_err(Error::Io)?,
+            Some(encoding),
+        ),
+        None => (tokio::fs::File::open(path).await.map_err(Error::Io)?, None),
+    };
     let metadata = file
         .metadata()
+        .await
         .map_err(|_| StatusCode::NOT_FOUND.into_error())?;
+    let modified = metadata.modified().ok();
+
+    let representation = Representation {
+        len: metadata.len(),
+        content_type: mime_guess::from_path(path).first_or_octet_stream(),
+        etag: modified.and_then(|modified| extract_etag(&modified, metadata.len(), encoding)),
+        modified,
+        encoding,
+        vary: options.precompressed.is_enabled(),
+        cache_control: options.cache_control(&slash(name)),
+    };
+    let selection = representation.select(headers, options.max_ranges)?;
+
+    let res = match &selection {
+        Selection::NotModified => Response::empty(),
+        _ if method == Method::HEAD => Response::empty(),
+        Selection::Full => Response::stream(ReaderStream::new(file)),
+        Selection::Range(range) => {
+            file.seek(SeekFrom::Start(range.start))
+                .await
+                .map_err(Error::Io)?;
+            Response::stream(ReaderStream::new(file.take(range.end - range.start)))
+        }
+        Selection::Multipart(multipart) => Response::stream(ByteRanges::new(file, multipart)),
+    };
 
-    let mut etag = None;
-    let mut last_modified = None;
-    let mut content_range = None;
-    let mut max = metadata.len();
+    Ok(representation.respond(&selection, res))
+}
 
-    if let Ok(modified) = metadata.modified() {
-        etag = extract_etag(&modified, max);
+#[cfg(test)]
+mod tests {
+    use super::{Dir, File};
+    use std::{
+        io,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+    use vidi_core::{
+        Handler, IntoResponse, Request, Result, StatusCode,
+        types::{Params, RouteInfo},
+    };
 
-        if matches!((headers.typed_get::<IfMatch>(), &etag), (Some(if_match), Some(etag)) if !if_match.precondition_passes(etag))
-            || matches!(headers.typed_get::<IfUnmodifiedSince>(), Some(if_unmodified_since) if !if_unmodified_since.precondition_passes(modified))
-        {
-            Err(Error::PreconditionFailed)?;
+    /// A temporary directory with the files, which is removed on drop.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str, files: &[(&str, &str)]) -> io::Result<Self> {
+            let root = std::env::temp_dir().join(format!("vidi-{name}-{}", std::process::id()));
+            for (path, data) in files {
+                let path = root.join(path);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, data)?;
+            }
+            Ok(Self(root))
         }
 
-        if matches!((headers.typed_get::<IfNoneMatch>(), &etag), (Some(if_no_match), Some(etag)) if !if_no_match.precondition_passes(etag))
-            || matches!(headers.typed_get::<IfModifiedSince>(), Some(if_modified_since) if !if_modified_since.is_modified(modified))
-        {
-            return Ok(StatusCode::NOT_MODIFIED.into_response());
+        fn path(&self) -> &Path {
+            &self.0
         }
-
-        last_modified.replace(LastModified::from(modified));
     }
 
-    // See https://github.com/hyperium/headers/pull/155
-    if let Some((start, end)) = headers
-        .typed_get::<Range>()
-        .and_then(|range| range.satisfiable_ranges(100).next())
-    {
-        let start = match start {
-            Bound::Included(n) => n,
-            Bound::Excluded(n) => n + 1,
-            Bound::Unbounded => 0,
-        };
-        let end = match end {
-            Bound::Included(n) => n + 1,
-            Bound::Excluded(n) => n,
-            Bound::Unbounded => max,
-        };
-
-        if end < start || end > max {
-            Err(Error::RangeUnsatisfied(max))?;
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
         }
+    }
 
-        if start != 0 || end != max {
-            if let Ok(range) = ContentRange::bytes(start..end, max) {
-                max = end - start;
-                content_range.replace(range);
-                file.seek(SeekFrom::Start(start)).map_err(Error::Io)?;
-            }
-        }
+    /// Creates a request of the path which is matched by the `/*` route.
+    fn request(path: &str) -> Request {
+        let mut req: Request = Request::default();
+        req.extensions_mut().insert(Arc::new(RouteInfo {
+            id: 2,
+            pattern: "/*".to_string(),
+            params: Into::<Params>::into(vec![("*1", path)]),
+        }));
+        *req.uri_mut() = format!("/{path}").parse().unwrap();
+        req
     }
 
-    let mut res = if content_range.is_some() {
-        // max = end - start
-        Response::stream(ReaderStream::new(tokio::fs::File::from_std(file).take(max)))
-    } else {
-        Response::stream(ReaderStream::new(tokio::fs::File::from_std(file)))
-    };
+    #[tokio::test]
+    async fn file() -> Result<()> {
+        let serve = File::new("src/serve.rs")?;
 
-    let headers = res.headers_mut();
+        let result = serve.call(request("serve.rs")).await;
 
-    headers.typed_insert(AcceptRanges::bytes());
-    headers.typed_insert(ContentLength(max));
-    headers.typed_insert(ContentType::from(
-        mime_guess::from_path(path).first_or_octet_stream(),
-    ));
+        assert_eq!(result.unwrap().status(), StatusCode::OK);
 
-    if let Some(etag) = etag {
-        headers.typed_insert(etag);
-    }
+        let result = serve.call(request("serve")).await;
 
-    if let Some(last_modified) = last_modified {
-        headers.typed_insert(last_modified);
-    }
+        assert_eq!(result.unwrap().status(), StatusCode::OK);
 
-    if let Some(content_range) = content_range {
-        headers.typed_insert(content_range);
-        *res.status_mut() = StatusCode::PARTIAL_CONTENT;
+        Ok(())
     }
 
-    Ok(res)
-}
+    #[tokio::test]
+    async fn dir() -> Result<()> {
+        let serve = Dir::new("src/serve")?;
 
-#[cfg(test)]
-mod tests {
-    use super::{Dir, File};
-    use std::sync::Arc;
-    use vidi_core::{
-        Handler, IntoResponse, Request, Result, StatusCode,
-        types::{Params, RouteInfo},
-    };
+        let result = serve.call(request("list.tpl")).await;
+
+        assert_eq!(result.unwrap().status(), StatusCode::OK);
+
+        let result = serve
+            .call(request("list"))
+            .await
+            .map_err(IntoResponse::into_response);
+
+        assert_eq!(result.unwrap_err().status(), StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
 
     #[tokio::test]
-    async fn file() -> Result<()> {
-        let serve = File::new("src/serve.rs");
+    async fn precompressed() -> Result<()> {
+        use http_body_util::BodyExt;
+        use vidi_core::header::{
+            ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RANGE, VARY,
+        };
 
-        let mut req: Request = Request::default();
-        req.extensions_mut().insert(Arc::new(RouteInfo {
-            id: 2,
-            pattern: "/*".to_string(),
-            params: Into::<Params>::into(vec![("*1", "serve.rs")]),
-        }));
-        *req.uri_mut() = "/serve.rs".parse().unwrap();
+        let dir = Fixture::new(
+            "precompressed",
+            &[
+                ("app.js", "raw"),
+                ("app.js.br", "brotli"),
+                ("app.js.gz", "gzip"),
+            ],
+        )?;
+
+        let serve = Dir::new(dir.path())?
+            .precompressed_br()
+            .precompressed_zstd()
+            .precompressed_gzip();
+        let call = |accept: &'static str, range: Option<&'static str>| {
+            let mut req = request("app.js");
+            req.headers_mut()
+                .insert(ACCEPT_ENCODING, accept.parse().unwrap());
+            if let Some(range) = range {
+                req.headers_mut().insert(RANGE, range.parse().unwrap());
+            }
+            serve.call(req)
+        };
 
-        let result = serve.call(req).await;
+        for (accept, encoding, body) in [
+            ("gzip, deflate, br", Some("br"), "brotli"),
+            ("gzip", Some("gzip"), "gzip"),
+            ("br;q=0.5, gzip", Some("gzip"), "gzip"),
+            ("br;q=0, *", Some("gzip"), "gzip"),
+            ("zstd", None, "raw"),
+            ("identity", None, "raw"),
+        ] {
+            let res = call(accept, None).await?;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers()
+                    .get(CONTENT_ENCODING)
+                    .map(|v| v.to_str().unwrap()),
+                encoding,
+                "{accept}"
+            );
+            assert_eq!(res.headers().get(VARY).unwrap(), "accept-encoding");
+            assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "text/javascript");
+            let bytes = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(bytes, body);
+        }
 
-        assert_eq!(result.unwrap().status(), StatusCode::OK);
+        // the ETag of each encoding
+        let br = call("br", None).await?;
+        let raw = call("identity", None).await?;
+        let etag = br.headers().get(ETAG).unwrap().clone();
+        assert_ne!(Some(&etag), raw.headers().get(ETAG));
+
+        let mut req = request("app.js");
+        req.headers_mut()
+            .insert(ACCEPT_ENCODING, "br".parse().unwrap());
+        req.headers_mut().insert(IF_NONE_MATCH, etag);
+        let res = serve.call(req).await?;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept-encoding");
+
+        // ranges of the precompressed variant
+        let res = call("br", Some("bytes=1-3")).await?;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "br");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "rot");
 
-        let mut req: Request = Request::default();
-        req.extensions_mut().insert(Arc::new(RouteInfo {
-            id: 2,
-            pattern: "/*".to_string(),
-            params: Into::<Params>::into(vec![("*1", "serve")]),
-        }));
-        *req.uri_mut() = "/serve".parse().unwrap();
+        Ok(())
+    }
 
-        let result = serve.call(req).await;
+    #[tokio::test]
+    async fn ranges() -> Result<()> {
+        use http_body_util::BodyExt;
+        use vidi_core::header::{CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, IF_RANGE, RANGE};
+
+        let dir = Fixture::new("ranges", &[("digits.txt", "0123456789")])?;
+
+        let serve = File::new(dir.path().join("digits.txt"))?.max_ranges(3);
+        let call = |range: &'static str, if_range: Option<String>| {
+            let mut req: Request = Request::default();
+            req.headers_mut().insert(RANGE, range.parse().unwrap());
+            if let Some(if_range) = if_range {
+                req.headers_mut()
+                    .insert(IF_RANGE, if_range.parse().unwrap());
+            }
+            serve.call(req)
+        };
 
-        assert_eq!(result.unwrap().status(), StatusCode::OK);
+        // multiple ranges
+        let res = call("bytes=0-1, 7-", None).await?;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = res.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+        let length: usize = res.headers()[CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes.len(), length);
+        assert_eq!(
+            bytes,
+            format!(
+                "\r\n--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+                 \r\n--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 7-9/10\r\n\r\n789\
+                 \r\n--{boundary}--\r\n"
+            )
+        );
+
+        // overlapping and adjacent ranges are coalesced
+        let res = call("bytes=4-5, 1-2, 2-3", None).await?;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers().get(CONTENT_RANGE).unwrap(), "bytes 1-5/10");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "12345");
+
+        // too many ranges, serves the whole file
+        let res = call("bytes=0-0, 2-2, 4-4, 6-6", None).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes, "0123456789");
+
+        // unsatisfiable
+        let err = call("bytes=20-30", None).await.unwrap_err();
+        let res = err.into_response();
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(res.headers().get(CONTENT_RANGE).unwrap(), "bytes */10");
+
+        // `If-Range`
+        let etag = serve
+            .call(Request::default())
+            .await?
+            .headers()
+            .get(vidi_core::header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let res = call("bytes=0-1", Some(etag)).await?;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let res = call("bytes=0-1", Some("\"stale\"".to_string())).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = call(
+            "bytes=0-1",
+            Some("Sat, 01 Jan 2000 00:00:00 GMT".to_string()),
+        )
+        .await?;
+        assert_eq!(res.status(), StatusCode::OK);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn dir() -> Result<()> {
-        let serve = Dir::new("src/serve");
+    async fn cache_control() -> Result<()> {
+        use crate::cache::CachePolicy;
+        use vidi_core::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+
+        let dir = Fixture::new(
+            "cache-control",
+            &[
+                ("index.html", "<html>"),
+                ("assets/app.3f2a9c1b.js", "app"),
+                ("assets/app.js", "app"),
+            ],
+        )?;
+
+        let serve = Dir::new(dir.path())?.cache_control(
+            CachePolicy::new()
+                .fingerprinted(CachePolicy::IMMUTABLE)
+                .glob("index.html", CachePolicy::NO_CACHE),
+        );
+        for (path, value) in [
+            ("", Some(CachePolicy::NO_CACHE)),
+            ("index.html", Some(CachePolicy::NO_CACHE)),
+            ("assets/app.3f2a9c1b.js", Some(CachePolicy::IMMUTABLE)),
+            ("assets/app.js", None),
+        ] {
+            let res = serve.call(request(path)).await?;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers()
+                    .get(CACHE_CONTROL)
+                    .map(|v| v.to_str().unwrap()),
+                value,
+                "{path}"
+            );
+        }
 
-        let mut req: Request = Request::default();
-        req.extensions_mut().insert(Arc::new(RouteInfo {
-            id: 2,
-            pattern: "/*".to_string(),
-            params: Into::<Params>::into(vec![("*1", "list.tpl")]),
-        }));
-        *req.uri_mut() = "/list.tpl".parse().unwrap();
+        // revalidation keeps the policy
+        let etag = serve.call(request("index.html")).await?.headers()[ETAG].clone();
+        let mut req = request("index.html");
+        req.headers_mut().insert(IF_NONE_MATCH, etag);
+        let res = serve.call(req).await?;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[CACHE_CONTROL], CachePolicy::NO_CACHE);
 
-        let result = serve.call(req).await;
+        let serve = File::new(dir.path().join("index.html"))?
+            .cache_control(CachePolicy::new().glob("index.html", CachePolicy::NO_CACHE));
+        let res = serve.call(Request::default()).await?;
+        assert_eq!(res.headers()[CACHE_CONTROL], CachePolicy::NO_CACHE);
 
-        assert_eq!(result.unwrap().status(), StatusCode::OK);
+        Ok(())
+    }
 
-        let mut req: Request = Request::default();
-        req.extensions_mut().insert(Arc::new(RouteInfo {
-            id: 2,
-            pattern: "/*".to_string(),
-            params: Into::<Params>::into(vec![("*1", "list")]),
-        }));
-        *req.uri_mut() = "/list".parse().unwrap();
+    #[tokio::test]
+    async fn spa() -> Result<()> {
+        use http_body_util::BodyExt;
+        use vidi_core::header::ACCEPT;
+
+        let dir = Fixture::new(
+            "spa",
+            &[
+                ("index.html", "<app>"),
+                ("404.html", "<missing>"),
+                ("assets/app.js", "app"),
+            ],
+        )?;
+
+        let call = |serve: Dir, path: &'static str, accept: &'static str| async move {
+            let mut req = request(path);
+            req.headers_mut().insert(ACCEPT, accept.parse().unwrap());
+            let res = serve
+                .call(req)
+                .await
+                .unwrap_or_else(IntoResponse::into_response);
+            let status = res.status();
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            (status, body)
+        };
+        let html = "text/html,application/xhtml+xml,*/*;q=0.8";
+
+        let serve = Dir::new(dir.path())?.spa("index.html");
+        assert_eq!(
+            call(serve.clone(), "users/1", html).await,
+            (StatusCode::OK, "<app>".into())
+        );
+        assert_eq!(
+            call(serve.clone(), "assets/app.js", "*/*").await,
+            (StatusCode::OK, "app".into())
+        );
+        // missing assets and non-navigation requests
+        assert_eq!(
+            call(serve.clone(), "assets/main.js", html).await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            call(serve.clone(), "users/1", "application/json").await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        let serve = serve.not_found_page("404.html");
+        assert_eq!(
+            call(serve.clone(), "assets/main.js", html).await,
+            (StatusCode::NOT_FOUND, "<missing>".into())
+        );
+        assert_eq!(
+            call(serve, "users/1", html).await,
+            (StatusCode::OK, "<app>".into())
+        );
+
+        Ok(())
+    }
 
-        let result = serve.call(req).await.map_err(IntoResponse::into_response);
+    #[tokio::test]
+    async fn listing() -> Result<()> {
+        use super::{HiddenFiles, Symlinks};
+        use http_body_util::BodyExt;
+        use vidi_core::header::{ACCEPT, CONTENT_TYPE};
+
+        let fixture = Fixture::new(
+            "listing",
+            &[
+                ("public/a.txt", "a"),
+                ("public/b.txt", "bbb"),
+                ("public/c <d>.txt", "cc"),
+                ("public/.env", "secret"),
+                ("outside.txt", "outside"),
+            ],
+        )?;
+        let root = fixture.path();
+        let dir = root.join("public");
+        std::fs::create_dir(dir.join("sub"))?;
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("a.txt"), dir.join("in"))?;
+            std::os::unix::fs::symlink(root.join("outside.txt"), dir.join("out"))?;
+        }
 
-        assert_eq!(result.unwrap_err().status(), StatusCode::NOT_FOUND);
+        let call = |serve: Dir, path: &'static str, query: &'static str, accept: &'static str| async move {
+            let mut req = request(path);
+            *req.uri_mut() = format!("/{path}{query}").parse().unwrap();
+            req.headers_mut().insert(ACCEPT, accept.parse().unwrap());
+            let res = serve
+                .call(req)
+                .await
+                .unwrap_or_else(IntoResponse::into_response);
+            let status = res.status();
+            let content_type = res.headers().get(CONTENT_TYPE).cloned();
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            (status, content_type, body)
+        };
+        let json = |body: &[u8]| serde_json::from_slice::<serde_json::Value>(body).unwrap();
+        let names = |value: &serde_json::Value| {
+            value["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["name"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let serve = Dir::new(root)?.listing().unlisted(vec!["outside.txt"]);
+
+        // JSON
+        let (status, content_type, body) =
+            call(serve.clone(), "public", "", "application/json").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.unwrap(), "application/json");
+        let value = json(&body);
+        assert_eq!(value["path"], "/public/");
+        assert_eq!(value["parent"], "/");
+        let entry = &value["entries"][1];
+        assert_eq!(entry["name"], "a.txt");
+        assert_eq!(entry["url"], "/public/a.txt");
+        assert_eq!(entry["type"], "file");
+        assert_eq!(entry["size"], 1);
+        assert!(entry["mtime"].is_u64());
+        let (_, _, body) = call(serve.clone(), "", "", "application/json").await;
+        assert_eq!(names(&json(&body)), ["public"]);
+        assert_eq!(json(&body)["entries"][0]["url"], "/public/");
+
+        // sorting and pagination
+        let (_, _, body) = call(
+            serve.clone(),
+            "public",
+            "?sort=size&order=desc&per_page=2",
+            "application/json",
+        )
+        .await;
+        let value = json(&body);
+        #[cfg(not(unix))]
+        assert_eq!(names(&value), [".env", "b.txt"]);
+        #[cfg(unix)]
+        assert_eq!(names(&value), ["out", ".env"]);
+        assert_eq!(value["per_page"], 2);
+        let (_, _, body) = call(
+            serve.clone().listing_page_size(1),
+            "public",
+            "?page=2&per_page=50",
+            "application/json",
+        )
+        .await;
+        let value = json(&body);
+        assert_eq!(names(&value), ["a.txt"]);
+        assert_eq!(value["per_page"], 1);
+
+        // HTML
+        let (_, content_type, body) =
+            call(serve.clone(), "public", "?per_page=4", "text/html").await;
+        assert_eq!(content_type.unwrap(), "text/html; charset=utf-8");
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"href="/public/c%20%3Cd%3E.txt""#));
+        assert!(html.contains("c &lt;d&gt;.txt"));
+        assert!(html.contains("page=2&amp;per_page=4"));
+
+        // hidden files
+        let unlisted = serve.clone().hidden_files(HiddenFiles::Unlisted);
+        let (_, _, body) = call(unlisted.clone(), "public", "", "application/json").await;
+        assert!(!names(&json(&body)).contains(&".env".to_string()));
+        assert_eq!(
+            call(unlisted, "public/.env", "", "*/*").await.0,
+            StatusCode::OK
+        );
+        let deny = serve.clone().hidden_files(HiddenFiles::Deny);
+        assert_eq!(
+            call(deny, "public/.env", "", "*/*").await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        // symbolic links
+        #[cfg(unix)]
+        {
+            let within = Dir::new(&dir)?.listing().symlinks(Symlinks::WithinRoot);
+            let (_, _, body) = call(within.clone(), "", "", "application/json").await;
+            let value = json(&body);
+            assert!(names(&value).contains(&"in".to_string()));
+            assert!(!names(&value).contains(&"out".to_string()));
+            assert_eq!(call(within.clone(), "in", "", "*/*").await.2, "a");
+            assert_eq!(
+                call(within, "out", "", "*/*").await.0,
+                StatusCode::NOT_FOUND
+            );
+
+            let deny = Dir::new(&dir)?.listing().symlinks(Symlinks::Deny);
+            let (_, _, body) = call(deny.clone(), "", "", "application/json").await;
+            assert!(!names(&json(&body)).contains(&"in".to_string()));
+            assert_eq!(
+                call(deny.clone(), "in", "", "*/*").await.0,
+                StatusCode::NOT_FOUND
+            );
+            assert_eq!(call(deny, "a.txt", "", "*/*").await.0, StatusCode::OK);
+        }
+
+        // custom renderer
+        let custom =
+            serve.listing_renderer(|listing| format!("{}: {}", listing.path, listing.total));
+        let (_, _, body) = call(custom.clone(), "", "", "text/html").await;
+        assert_eq!(body, "/: 1");
+        let (_, content_type, _) = call(custom, "", "", "application/json").await;
+        assert_eq!(content_type.unwrap(), "application/json");
 
         Ok(())
     }
@@ -0,0 +1,92 @@
+//! The `multipart/byteranges` body of a file.
+
+use std::{
+    collections::VecDeque,
+    io::{self, SeekFrom},
+    ops,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeek},
+};
+use tokio_stream::Stream;
+use tokio_util::io::poll_read_buf;
+use vidi_core::{Bytes, BytesMut};
+
+use crate::engine::Multipart;
+
+/// The size of the chunks which are read from the file.
+const CHUNK: usize = 64 * 1024;
+
+/// The body of the `multipart/byteranges`, the parts are read from the file in order.
+#[derive(Debug)]
+pub(super) struct ByteRanges {
+    file: File,
+    parts: VecDeque<(Bytes, ops::Range<u64>)>,
+    trailer: Option<Bytes>,
+    state: State,
+    buf: BytesMut,
+}
+
+#[derive(Debug)]
+enum State {
+    Next,
+    Seek(u64),
+    Read(u64),
+}
+
+impl ByteRanges {
+    pub(super) fn new(file: File, multipart: &Multipart) -> Self {
+        Self {
+            file,
+            parts: multipart.parts.iter().cloned().collect(),
+            trailer: Some(multipart.trailer.clone()),
+            state: State::Next,
+            buf: BytesMut::new(),
+        }
+    }
+}
+
+impl Stream for ByteRanges {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            match this.state {
+                State::Next => {
+                    let Some((head, range)) = this.parts.pop_front() else {
+                        return Poll::Ready(this.trailer.take().map(Ok));
+                    };
+                    if let Err(err) =
+                        Pin::new(&mut this.file).start_seek(SeekFrom::Start(range.start))
+                    {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    this.state = State::Seek(range.end - range.start);
+                    return Poll::Ready(Some(Ok(head)));
+                }
+                State::Seek(remaining) => {
+                    ready!(Pin::new(&mut this.file).poll_complete(cx))?;
+                    this.state = State::Read(remaining);
+                }
+                State::Read(0) => this.state = State::Next,
+                State::Read(remaining) => {
+                    let limit = remaining.min(CHUNK as u64);
+                    this.buf.reserve(CHUNK);
+                    let mut take = (&mut this.file).take(limit);
+                    let n = ready!(poll_read_buf(Pin::new(&mut take), cx, &mut this.buf))?;
+                    if n == 0 {
+                        return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+                    }
+                    this.state = State::Read(remaining - n as u64);
+                    return Poll::Ready(Some(Ok(this.buf.split().freeze())));
+                }
+            }
+        }
+    }
+}
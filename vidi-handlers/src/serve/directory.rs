@@ -3,146 +3,465 @@
 //! <https://github.com/vercel/serve-handler> MIT
 
 use std::{
-    ffi::OsStr,
-    fmt::{Display, Formatter, Result},
-    fs::read_dir,
-    path::{Path, PathBuf},
-    str::FromStr,
+    fmt::{self, Display, Formatter},
+    path::Path,
+    sync::Arc,
+    time::SystemTime,
 };
 
-use vidi_core::{IntoResponse, Response, ResponseExt};
+use http_body_util::Full;
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use serde::Serialize;
+use vidi_core::{
+    Request, RequestExt, Response, ResponseExt,
+    header::{ACCEPT, HeaderValue},
+};
+
+use super::Dir;
+
+/// The characters which are encoded in the URLs of the entries.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The policy of the hidden files, whose names start with `.`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HiddenFiles {
+    /// Lists and serves the hidden files.
+    #[default]
+    Allow,
+    /// Serves the hidden files but excludes them from the listing.
+    Unlisted,
+    /// Neither lists nor serves the hidden files.
+    Deny,
+}
+
+/// The policy of the symbolic links.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Follows the symbolic links.
+    #[default]
+    Follow,
+    /// Follows the symbolic links whose targets are within the directory.
+    WithinRoot,
+    /// Neither lists nor serves the symbolic links.
+    Deny,
+}
+
+/// The sort key of a listing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// By the name.
+    #[default]
+    Name,
+    /// By the size.
+    Size,
+    /// By the modification time.
+    Mtime,
+}
+
+/// The sort order of a listing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    /// Ascending.
+    #[default]
+    Asc,
+    /// Descending.
+    Desc,
+}
 
-#[derive(Debug)]
-pub(super) struct Directory {
-    name: String,
-    paths: Paths,
-    files: Files,
+/// The kind of an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    /// A file.
+    File,
+    /// A directory.
+    Directory,
 }
 
-impl Directory {
-    pub(super) fn new(
-        base: &str,
-        prev: bool,
-        root: &Path,
-        unlisted: Option<&Vec<&'static str>>,
-    ) -> Option<Self> {
-        let mut entries = read_dir(root).ok()?;
+/// An entry of a listing.
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct Entry {
+    /// The file name.
+    pub name: String,
+    /// The URL of the entry, the directories end with `/`.
+    pub url: String,
+    /// The kind of the entry, it is the kind of the target if the entry is a symbolic link.
+    #[serde(rename = "type")]
+    pub kind: EntryKind,
+    /// The entry is a symbolic link.
+    pub symlink: bool,
+    /// The size in bytes, `0` for the directories.
+    pub size: u64,
+    /// The modification time in seconds since the Unix epoch.
+    pub mtime: Option<u64>,
+}
+
+/// A page of a directory listing, which is responded as JSON if the client accepts
+/// `application/json`, otherwise is rendered as HTML.
+///
+/// The query parameters `sort` (`name`, `size` or `mtime`), `order` (`asc` or `desc`), `page`
+/// and `per_page` are supported.
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct Listing {
+    /// The path of the directory, e.g. `/docs/`.
+    pub path: String,
+    /// The URL of the parent directory.
+    pub parent: Option<String>,
+    /// The entries of the current page.
+    pub entries: Vec<Entry>,
+    /// The sort key.
+    pub sort: Sort,
+    /// The sort order.
+    pub order: Order,
+    /// The current page, starts at `1`.
+    pub page: usize,
+    /// The maximum number of the entries in a page.
+    pub per_page: usize,
+    /// The total number of the entries.
+    pub total: usize,
+}
+
+impl Listing {
+    /// The total number of the pages.
+    #[must_use]
+    pub const fn pages(&self) -> usize {
+        self.total.div_ceil(self.per_page)
+    }
+
+    /// The URL of the page with the current sorting.
+    #[must_use]
+    pub fn page_url(&self, page: usize) -> String {
+        let sort = match self.sort {
+            Sort::Name => "name",
+            Sort::Size => "size",
+            Sort::Mtime => "mtime",
+        };
+        let order = match self.order {
+            Order::Asc => "asc",
+            Order::Desc => "desc",
+        };
+        format!(
+            "{}?sort={sort}&order={order}&page={page}&per_page={}",
+            self.path, self.per_page
+        )
+    }
+
+    /// Reads a page of the directory.
+    pub(super) async fn read(dir: &Dir, req: &Request, root: &Path, prev: bool) -> Option<Self> {
+        let query = Query::parse(req.uri().query().unwrap_or_default());
+        let base = req.path().trim_matches('/');
+        let prefix = if base.is_empty() {
+            "/".to_string()
+        } else {
+            format!("/{base}/")
+        };
+        let canonical = match dir.symlinks {
+            Symlinks::WithinRoot => tokio::fs::canonicalize(&dir.path).await.ok(),
+            _ => None,
+        };
 
-        let mut files = Vec::new();
+        let mut entries = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(root).await.ok()?;
 
-        while let Some(Ok(entry)) = entries.next() {
-            let name = entry.file_name().to_str()?.to_string();
+        while let Ok(Some(entry)) = read_dir.next_entry().await {
+            let Some(name) = entry.file_name().to_str().map(ToString::to_string) else {
+                continue;
+            };
 
-            if unlisted
+            if dir
+                .unlisted
                 .as_ref()
-                .filter(|unlisted| unlisted.contains(&name.as_str()))
-                .is_some()
+                .is_some_and(|unlisted| unlisted.contains(&name.as_str()))
+                || (dir.hidden != HiddenFiles::Allow && name.starts_with('.'))
             {
                 continue;
             }
 
-            let kind = entry.file_type().ok()?.is_file();
-            let path = entry.path();
-            let ext = path
-                .extension()
-                .and_then(OsStr::to_str)
-                .map(ToString::to_string);
+            let Ok(file_type) = entry.file_type().await else {
+                continue;
+            };
+            let symlink = file_type.is_symlink();
+            if symlink {
+                match (dir.symlinks, &canonical) {
+                    (Symlinks::Follow, _) => {}
+                    (Symlinks::WithinRoot, Some(canonical)) => {
+                        if !tokio::fs::canonicalize(entry.path())
+                            .await
+                            .is_ok_and(|target| target.starts_with(canonical))
+                        {
+                            continue;
+                        }
+                    }
+                    _ => continue,
+                }
+            }
 
-            let mut url = base.trim_matches('/').to_owned();
-            url.push('/');
-            url.push_str(path.strip_prefix(root).ok()?.to_str()?);
+            // follows the symbolic links, the broken links are skipped
+            let Ok(metadata) = tokio::fs::metadata(entry.path()).await else {
+                continue;
+            };
+            let kind = if metadata.is_dir() {
+                EntryKind::Directory
+            } else {
+                EntryKind::File
+            };
 
-            files.push((url, name.clone(), kind, ext, name));
-        }
+            let mut url = prefix.clone();
+            url.extend(utf8_percent_encode(&name, SEGMENT));
+            if kind == EntryKind::Directory {
+                url.push('/');
+            }
 
-        files.sort_by_key(|f| f.1.clone());
-
-        let curr = PathBuf::from_str(base).ok()?;
-
-        if prev {
-            let parent = curr.parent()?;
-            files.insert(
-                0,
-                (
-                    parent.join("").to_str()?.strip_prefix('/')?.to_string(),
-                    parent
-                        .file_name()
-                        .and_then(OsStr::to_str)
-                        .unwrap_or_default()
-                        .to_string(),
-                    false,
-                    None,
-                    "..".to_string(),
-                ),
-            );
+            entries.push(Entry {
+                name,
+                url,
+                kind,
+                symlink,
+                size: if metadata.is_file() {
+                    metadata.len()
+                } else {
+                    0
+                },
+                mtime: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|mtime| mtime.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .map(|mtime| mtime.as_secs()),
+            });
         }
 
-        let mut paths = Vec::new();
-
-        for a in curr.ancestors() {
-            if let (Some(u), Some(n)) = (a.join("").to_str(), a.file_name().and_then(OsStr::to_str))
-            {
-                paths.push((u.to_string(), n.to_owned() + "/"));
+        entries.sort_by(|a, b| {
+            match query.sort {
+                Sort::Name => a.name.cmp(&b.name),
+                Sort::Size => a.size.cmp(&b.size),
+                Sort::Mtime => a.mtime.cmp(&b.mtime),
             }
+            .then_with(|| a.name.cmp(&b.name))
+        });
+        if query.order == Order::Desc {
+            entries.reverse();
         }
 
-        paths.reverse();
+        let total = entries.len();
+        let per_page = query
+            .per_page
+            .unwrap_or(dir.page_size)
+            .clamp(1, dir.page_size);
+        let page = query.page.max(1);
+        let entries = entries
+            .into_iter()
+            .skip((page - 1).saturating_mul(per_page))
+            .take(per_page)
+            .collect();
+
+        let parent = prev.then(|| match base.rsplit_once('/') {
+            Some((parent, _)) => format!("/{parent}/"),
+            None => "/".to_string(),
+        });
 
         Some(Self {
-            name: base.to_string(),
-            paths: Paths(paths),
-            files: Files(files),
+            path: prefix,
+            parent,
+            entries,
+            sort: query.sort,
+            order: query.order,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    /// Responds the listing as JSON or HTML.
+    pub(super) fn respond(&self, req: &Request, renderer: Option<&Renderer>) -> Response {
+        let json = req
+            .headers()
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| HeaderValue::to_str(value).ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|item| item.split(';').next())
+            .any(|mime| mime.trim().eq_ignore_ascii_case("application/json"));
+
+        if json {
+            if let Ok(body) = serde_json::to_vec(self) {
+                return Response::with(
+                    Full::from(body),
+                    mime_guess::mime::APPLICATION_JSON.as_ref(),
+                );
+            }
+        }
+
+        Response::html(match renderer {
+            Some(renderer) => (renderer.0)(self),
+            None => Directory(self).to_string(),
         })
     }
 }
 
-impl IntoResponse for Directory {
-    fn into_response(self) -> Response {
-        Response::html(self.to_string())
+/// A custom renderer of the directory listing.
+#[derive(Clone)]
+pub(super) struct Renderer(pub(super) Arc<dyn Fn(&Listing) -> String + Send + Sync>);
+
+impl fmt::Debug for Renderer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Renderer").finish()
+    }
+}
+
+/// The query parameters of a listing.
+#[derive(Debug, Default)]
+struct Query {
+    sort: Sort,
+    order: Order,
+    page: usize,
+    per_page: Option<usize>,
+}
+
+impl Query {
+    fn parse(query: &str) -> Self {
+        let mut parsed = Self {
+            page: 1,
+            ..Self::default()
+        };
+
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match (key, value) {
+                ("sort", "name") => parsed.sort = Sort::Name,
+                ("sort", "size") => parsed.sort = Sort::Size,
+                ("sort", "mtime") => parsed.sort = Sort::Mtime,
+                ("order", "asc") => parsed.order = Order::Asc,
+                ("order", "desc") => parsed.order = Order::Desc,
+                ("page", page) => parsed.page = page.parse().unwrap_or(1),
+                ("per_page", per_page) => parsed.per_page = per_page.parse().ok(),
+                _ => {}
+            }
+        }
+
+        parsed
     }
 }
 
-impl Display for Directory {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+/// Escapes the HTML text.
+struct Escape<'a>(&'a str);
+
+impl Display for Escape<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                '\'' => f.write_str("&#39;")?,
+                c => write!(f, "{c}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The default HTML template of the listing.
+struct Directory<'a>(&'a Listing);
+
+impl Display for Directory<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
             include_str!("list.tpl"),
-            name = self.name,
-            paths = self.paths,
-            files = self.files
+            name = Escape(&self.0.path),
+            paths = Paths(&self.0.path),
+            files = Files(self.0),
+            pagination = Pagination(self.0),
         )
     }
 }
 
-/// Path: (url, name)
-#[derive(Debug)]
-struct Paths(Vec<(String, String)>);
+/// The breadcrumbs of the path.
+struct Paths<'a>(&'a str);
 
-impl Display for Paths {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        for (url, name) in &self.0 {
-            writeln!(f, r#"<a href="{url}">{name}</a>"#)?;
+impl Display for Paths<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut url = String::from("/");
+        for name in self.0.split('/').filter(|name| !name.is_empty()) {
+            url.push_str(name);
+            url.push('/');
+            writeln!(f, r#"<a href="{}">{}/</a>"#, Escape(&url), Escape(name))?;
         }
         Ok(())
     }
 }
 
-/// File: (relative, title, kind, ext, base)
-#[derive(Debug)]
-struct Files(Vec<(String, String, bool, Option<String>, String)>);
+/// The entries of the current page.
+struct Files<'a>(&'a Listing);
 
-impl Display for Files {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        #[allow(clippy::unwrap_or_default)]
-        for (relative, title, kind, ext, base) in &self.0 {
+impl Display for Files<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(parent) = &self.0.parent {
+            writeln!(
+                f,
+                r#"<li><a href="{}" title=".." class="folder">..</a></li>"#,
+                Escape(parent)
+            )?;
+        }
+        for entry in &self.0.entries {
+            let ext = Path::new(&entry.name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default();
             writeln!(
                 f,
-                r#"<li><a href="/{}" title="{}" class="{} {}">{}</a></li>"#,
-                relative,
-                title,
-                if *kind { "file" } else { "folder" },
-                ext.as_ref().map_or("", |ext| ext),
-                base
+                r#"<li><a href="{}" title="{}" class="{} {}">{}</a></li>"#,
+                Escape(&entry.url),
+                Escape(&entry.name),
+                match entry.kind {
+                    EntryKind::File => "file",
+                    EntryKind::Directory => "folder",
+                },
+                Escape(ext),
+                Escape(&entry.name),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The links of the previous and next pages.
+struct Pagination<'a>(&'a Listing);
+
+impl Display for Pagination<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let listing = self.0;
+        if listing.pages() <= 1 {
+            return Ok(());
+        }
+        if listing.page > 1 {
+            write!(
+                f,
+                r#"<a href="{}">&larr;</a> "#,
+                Escape(&listing.page_url(listing.page - 1))
+            )?;
+        }
+        write!(f, "{} / {}", listing.page, listing.pages())?;
+        if listing.page < listing.pages() {
+            write!(
+                f,
+                r#" <a href="{}">&rarr;</a>"#,
+                Escape(&listing.page_url(listing.page + 1))
             )?;
         }
         Ok(())
@@ -155,26 +474,54 @@ mod tests {
 
     #[test]
     fn serve_directory() {
-        let d = Directory {
-            name: "viz".to_string(),
-            paths: Paths(vec![("src/main.rs".to_string(), "main".to_string())]),
-            files: Files(vec![
-                (
-                    ".".to_string(),
-                    "src".to_string(),
-                    true,
-                    None,
-                    "src".to_string(),
-                ),
-                (
-                    ".".to_string(),
-                    "lib".to_string(),
-                    false,
-                    Some("rs".to_string()),
-                    "lib.rs".to_string(),
-                ),
-            ]),
+        let listing = Listing {
+            path: "/vidi/<src>/".to_string(),
+            parent: Some("/vidi/".to_string()),
+            entries: vec![
+                Entry {
+                    name: "src".to_string(),
+                    url: "/vidi/src/".to_string(),
+                    kind: EntryKind::Directory,
+                    symlink: false,
+                    size: 0,
+                    mtime: None,
+                },
+                Entry {
+                    name: "lib.rs".to_string(),
+                    url: "/vidi/lib.rs".to_string(),
+                    kind: EntryKind::File,
+                    symlink: false,
+                    size: 42,
+                    mtime: Some(0),
+                },
+            ],
+            sort: Sort::Name,
+            order: Order::Asc,
+            page: 1,
+            per_page: 1,
+            total: 2,
         };
-        assert!(!d.to_string().is_empty());
+        let html = Directory(&listing).to_string();
+        assert!(html.contains(r#"class="file rs""#));
+        assert!(html.contains("&lt;src&gt;"));
+        assert!(html.contains("?sort=name&amp;order=asc&amp;page=2&amp;per_page=1"));
+
+        let json = serde_json::to_value(&listing).unwrap();
+        assert_eq!(json["entries"][1]["type"], "file");
+        assert_eq!(json["entries"][1]["size"], 42);
+        assert_eq!(json["total"], 2);
+    }
+
+    #[test]
+    fn query() {
+        let query = Query::parse("sort=size&order=desc&page=3&per_page=50&x=1");
+        assert_eq!(query.sort, Sort::Size);
+        assert_eq!(query.order, Order::Desc);
+        assert_eq!(query.page, 3);
+        assert_eq!(query.per_page, Some(50));
+
+        let query = Query::parse("page=x");
+        assert_eq!(query.page, 1);
+        assert_eq!(query.sort, Sort::Name);
     }
 }
@@ -148,6 +148,7 @@
       <ul id="files">
 				{files}
       </ul>
+      <nav id="pagination">{pagination}</nav>
 		</main>
   </body>
 </html>
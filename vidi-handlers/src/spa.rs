@@ -0,0 +1,27 @@
+//! Single-page application fallback.
+
+use vidi_core::{
+    Method, Request, RequestExt,
+    header::{ACCEPT, HeaderValue},
+};
+
+/// Checks if the request is a navigation of a browser, which accepts HTML and its path has no
+/// file extension, e.g. `/users/1` but not `/assets/app.js`.
+pub(crate) fn is_navigation(req: &Request) -> bool {
+    let name = req.path().rsplit('/').next().unwrap_or_default();
+
+    matches!(*req.method(), Method::GET | Method::HEAD)
+        && !name.contains('.')
+        && req
+            .headers()
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| HeaderValue::to_str(value).ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|item| item.split(';').next())
+            .map(str::trim)
+            .any(|mime| {
+                mime.eq_ignore_ascii_case("text/html")
+                    || mime.eq_ignore_ascii_case("application/xhtml+xml")
+            })
+}
@@ -0,0 +1,771 @@
+//! Resumable uploads, the [tus] protocol 1.0.0.
+//!
+//! The `creation`, `creation-with-upload`, `creation-defer-length`, `termination` and
+//! `expiration` extensions are supported.
+//!
+//! ```
+//! use std::time::Duration;
+//! use vidi_core::{IntoResponse, StatusCode};
+//! use vidi_handlers::tus::{Tus, Upload};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let tus = Tus::new(std::env::temp_dir().join("uploads"))?
+//!     .max_size(1024 * 1024 * 1024)
+//!     .expiration(Duration::from_secs(24 * 60 * 60))
+//!     .on_complete(|upload: Upload| async move {
+//!         if upload.metadata.get("filename").is_none() {
+//!             Err(StatusCode::BAD_REQUEST.into_error())?;
+//!         }
+//!         Ok(())
+//!     });
+//! // e.g. `Router::new().any("/files/", tus.clone()).any("/files/:id", tus)`
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [tus]: <https://tus.io/protocols/resumable-upload>
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use vidi_core::{
+    Body, BoxHandler, Handler, IntoResponse, Method, Request, RequestExt, Response, ResponseExt,
+    Result, StatusCode,
+    header::{CACHE_CONTROL, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, LOCATION},
+    headers::{self, Header},
+    types::PayloadError,
+};
+
+mod storage;
+
+pub use storage::{LocalDisk, Storage, Upload};
+
+/// The version of the protocol.
+const VERSION: &str = "1.0.0";
+
+/// The supported extensions.
+const EXTENSIONS: &str =
+    "creation,creation-with-upload,creation-defer-length,termination,expiration";
+
+/// The media type of the `PATCH` body.
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const TUS_VERSION: HeaderName = HeaderName::from_static("tus-version");
+const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_DEFER_LENGTH: HeaderName = HeaderName::from_static("upload-defer-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+
+/// The tus upload handler over a storage.
+///
+/// It should be routed for the creation URL and the upload URLs, e.g. `/files/` and `/files/:id`.
+#[derive(Debug)]
+pub struct Tus<S = LocalDisk> {
+    storage: Arc<S>,
+    max_size: Option<u64>,
+    expiration: Option<Duration>,
+    hooks: Vec<BoxHandler<Upload, Result<()>>>,
+    /// The uploads which are receiving the bytes.
+    busy: Arc<Mutex<HashSet<String>>>,
+}
+
+impl<S> Clone for Tus<S> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            max_size: self.max_size,
+            expiration: self.expiration,
+            hooks: self.hooks.clone(),
+            busy: self.busy.clone(),
+        }
+    }
+}
+
+impl Tus {
+    /// Stores the uploads in the local directory, which is created if it is missing.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the directory could not be created.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        LocalDisk::new(dir).map(Self::with_storage)
+    }
+}
+
+impl<S> Tus<S> {
+    /// Stores the uploads in the storage.
+    #[must_use]
+    pub fn with_storage(storage: S) -> Self {
+        Self {
+            storage: Arc::new(storage),
+            max_size: None,
+            expiration: None,
+            hooks: Vec::new(),
+            busy: Arc::default(),
+        }
+    }
+
+    /// Sets the maximum size of an upload.
+    #[must_use]
+    pub const fn max_size(mut self, max: u64) -> Self {
+        self.max_size = Some(max);
+        self
+    }
+
+    /// Expires the unfinished uploads after the duration since the last activity.
+    #[must_use]
+    pub const fn expiration(mut self, duration: Duration) -> Self {
+        self.expiration = Some(duration);
+        self
+    }
+
+    /// Appends a hook which is called in order after an upload is completed.
+    ///
+    /// The error of a hook is responded for the request which completes the upload.
+    #[must_use]
+    pub fn on_complete<H>(mut self, hook: H) -> Self
+    where
+        H: Handler<Upload, Output = Result<()>> + Clone,
+    {
+        self.hooks.push(BoxHandler::new(hook));
+        self
+    }
+}
+
+impl<S> Tus<S>
+where
+    S: Storage,
+{
+    /// Deletes the expired uploads, returns the number of the deleted uploads.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the uploads could not be listed or deleted.
+    pub async fn purge(&self) -> io::Result<usize> {
+        let mut count = 0;
+        for id in self.storage.list().await? {
+            if self
+                .storage
+                .get(&id)
+                .await
+                .is_ok_and(|upload| upload.is_expired())
+            {
+                self.storage.delete(&id).await?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Gets the upload, `410 Gone` if it is expired.
+    async fn get(&self, id: &str) -> Result<Upload> {
+        let upload = self.storage.get(id).await.map_err(into_error)?;
+        if upload.is_expired() {
+            Err(StatusCode::GONE.into_error())?;
+        }
+        Ok(upload)
+    }
+
+    /// Marks the upload is receiving the bytes, `423 Locked` if it is already busy.
+    fn acquire(&self, id: &str) -> Result<Busy> {
+        let mut busy = self.busy.lock().unwrap_or_else(PoisonError::into_inner);
+        if !busy.insert(id.to_string()) {
+            Err(StatusCode::LOCKED.into_error())?;
+        }
+        Ok(Busy {
+            busy: self.busy.clone(),
+            id: id.to_string(),
+        })
+    }
+
+    /// Appends the body and updates the upload, the hooks are called if it is completed.
+    ///
+    /// The upload must not be completed before, so the hooks are called once.
+    async fn append(&self, upload: &mut Upload, body: Body) -> Result<()> {
+        let max = upload
+            .length
+            .or(self.max_size)
+            .map_or(u64::MAX, |max| max.saturating_sub(upload.offset));
+        let result = self.storage.append(&upload.id, body, max).await;
+        if let Ok(offset) = result.as_ref() {
+            upload.offset = *offset;
+        } else if let Ok(current) = self.storage.get(&upload.id).await {
+            upload.offset = current.offset;
+        }
+
+        upload.expires = if upload.is_complete() {
+            None
+        } else {
+            self.expiration
+                .map(|expiration| SystemTime::now() + expiration)
+        };
+        self.storage.update(upload).await.map_err(into_error)?;
+
+        // the excess bytes are discarded, the upload may still be completed
+        if upload.is_complete() {
+            for hook in &self.hooks {
+                hook.call(upload.clone()).await?;
+            }
+        }
+
+        result.map(|_| ()).map_err(into_error)
+    }
+
+    fn options(&self) -> Response {
+        let mut res = status(StatusCode::NO_CONTENT);
+        let headers = res.headers_mut();
+        headers.insert(TUS_VERSION, HeaderValue::from_static(VERSION));
+        headers.insert(TUS_EXTENSION, HeaderValue::from_static(EXTENSIONS));
+        if let Some(max) = self.max_size {
+            headers.insert(TUS_MAX_SIZE, HeaderValue::from(max));
+        }
+        res
+    }
+
+    async fn create(&self, req: &mut Request, prefix: &str) -> Result<Response> {
+        let headers = req.headers();
+        let length = match (
+            number(headers, &UPLOAD_LENGTH)?,
+            headers.get(UPLOAD_DEFER_LENGTH),
+        ) {
+            (Some(length), None) => Some(length),
+            (None, Some(defer)) if defer == "1" => None,
+            _ => Err(StatusCode::BAD_REQUEST.into_error())?,
+        };
+        if length
+            .zip(self.max_size)
+            .is_some_and(|(length, max)| length > max)
+        {
+            Err(StatusCode::PAYLOAD_TOO_LARGE.into_error())?;
+        }
+        let metadata = match headers.get(UPLOAD_METADATA) {
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(decode_metadata)
+                .ok_or_else(|| StatusCode::BAD_REQUEST.into_error())?,
+            None => BTreeMap::new(),
+        };
+        let with_upload = is_offset_octet_stream(headers);
+
+        let mut upload = Upload {
+            id: id()?,
+            offset: 0,
+            length,
+            metadata,
+            expires: self
+                .expiration
+                .map(|expiration| SystemTime::now() + expiration),
+        };
+        self.storage.create(&upload).await.map_err(into_error)?;
+
+        // the creation with upload
+        let body = match req.incoming() {
+            Ok(body) if with_upload => body,
+            _ => Body::Empty,
+        };
+        let _busy = self.acquire(&upload.id)?;
+        self.append(&mut upload, body).await?;
+
+        let mut res = status(StatusCode::CREATED);
+        let headers = res.headers_mut();
+        if let Ok(location) = HeaderValue::try_from(format!("{prefix}{}", upload.id)) {
+            headers.insert(LOCATION, location);
+        }
+        if with_upload {
+            headers.insert(UPLOAD_OFFSET, HeaderValue::from(upload.offset));
+        }
+        if let Some(expires) = upload.expires {
+            headers.insert(UPLOAD_EXPIRES, http_date(expires));
+        }
+        Ok(res)
+    }
+
+    async fn head(&self, id: &str) -> Result<Response> {
+        let upload = self.get(id).await?;
+
+        let mut res = Response::empty();
+        let headers = res.headers_mut();
+        headers.insert(UPLOAD_OFFSET, HeaderValue::from(upload.offset));
+        match upload.length {
+            Some(length) => headers.insert(UPLOAD_LENGTH, HeaderValue::from(length)),
+            None => headers.insert(UPLOAD_DEFER_LENGTH, HeaderValue::from_static("1")),
+        };
+        if !upload.metadata.is_empty() {
+            if let Ok(metadata) = HeaderValue::try_from(encode_metadata(&upload.metadata)) {
+                headers.insert(UPLOAD_METADATA, metadata);
+            }
+        }
+        if let Some(expires) = upload.expires {
+            headers.insert(UPLOAD_EXPIRES, http_date(expires));
+        }
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        Ok(res)
+    }
+
+    async fn patch(&self, req: &mut Request, id: &str) -> Result<Response> {
+        if !is_offset_octet_stream(req.headers()) {
+            Err(PayloadError::UnsupportedMediaType(
+                OFFSET_OCTET_STREAM
+                    .parse()
+                    .unwrap_or(mime::APPLICATION_OCTET_STREAM),
+            ))?;
+        }
+        let Some(offset) = number(req.headers(), &UPLOAD_OFFSET)? else {
+            Err(StatusCode::BAD_REQUEST.into_error())?
+        };
+        let length = number(req.headers(), &UPLOAD_LENGTH)?;
+
+        let _busy = self.acquire(id)?;
+        let mut upload = self.get(id).await?;
+        if upload.is_complete() {
+            Err(StatusCode::FORBIDDEN.into_error())?;
+        }
+        if offset != upload.offset {
+            Err(StatusCode::CONFLICT.into_error())?;
+        }
+        // declares the deferred length
+        match (upload.length, length) {
+            (_, None) => {}
+            (None, Some(length)) => {
+                if length < upload.offset {
+                    Err(StatusCode::BAD_REQUEST.into_error())?;
+                }
+                if self.max_size.is_some_and(|max| length > max) {
+                    Err(StatusCode::PAYLOAD_TOO_LARGE.into_error())?;
+                }
+                upload.length = Some(length);
+            }
+            (Some(current), Some(length)) if current == length => {}
+            _ => Err(StatusCode::BAD_REQUEST.into_error())?,
+        }
+
+        let body = match req.incoming() {
+            Ok(body) => body,
+            Err(PayloadError::Empty) => Body::Empty,
+            Err(e) => Err(e)?,
+        };
+        self.append(&mut upload, body).await?;
+
+        let mut res = status(StatusCode::NO_CONTENT);
+        let headers = res.headers_mut();
+        headers.insert(UPLOAD_OFFSET, HeaderValue::from(upload.offset));
+        if let Some(expires) = upload.expires {
+            headers.insert(UPLOAD_EXPIRES, http_date(expires));
+        }
+        Ok(res)
+    }
+
+    async fn delete(&self, id: &str) -> Result<Response> {
+        let _busy = self.acquire(id)?;
+        self.storage.get(id).await.map_err(into_error)?;
+        self.storage.delete(id).await.map_err(into_error)?;
+        Ok(status(StatusCode::NO_CONTENT))
+    }
+}
+
+#[vidi_core::async_trait]
+impl<S> Handler<Request> for Tus<S>
+where
+    S: Storage,
+{
+    type Output = Result<Response>;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let path = req.path();
+        let id = req
+            .route_info()
+            .params
+            .first()
+            .map_or("", |(_, v)| v.as_str())
+            .to_string();
+        let mut prefix = path.strip_suffix(id.as_str()).unwrap_or(path).to_string();
+        if !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+
+        let method = req.method().clone();
+        let resumable = req
+            .headers()
+            .get(TUS_RESUMABLE)
+            .is_some_and(|value| value == VERSION);
+
+        let result = match method {
+            Method::OPTIONS => Ok(self.options()),
+            _ if !resumable => {
+                let mut res = status(StatusCode::PRECONDITION_FAILED);
+                res.headers_mut()
+                    .insert(TUS_VERSION, HeaderValue::from_static(VERSION));
+                Ok(res)
+            }
+            Method::POST if id.is_empty() => self.create(&mut req, &prefix).await,
+            Method::HEAD if !id.is_empty() => self.head(&id).await,
+            Method::PATCH if !id.is_empty() => self.patch(&mut req, &id).await,
+            Method::DELETE if !id.is_empty() => self.delete(&id).await,
+            _ => Err(StatusCode::METHOD_NOT_ALLOWED.into_error()),
+        };
+
+        // the version is responded for the errors too
+        let mut res = result.unwrap_or_else(IntoResponse::into_response);
+        res.headers_mut()
+            .insert(TUS_RESUMABLE, HeaderValue::from_static(VERSION));
+        Ok(res)
+    }
+}
+
+/// Removes the upload from the busy uploads on drop.
+struct Busy {
+    busy: Arc<Mutex<HashSet<String>>>,
+    id: String,
+}
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        self.busy
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.id);
+    }
+}
+
+/// Generates a random ID of 32 hexadecimal digits.
+fn id() -> Result<String> {
+    let mut buf = [0; 16];
+    getrandom::fill(&mut buf)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_error())?;
+    Ok(format!("{:032x}", u128::from_ne_bytes(buf)))
+}
+
+/// Checks the ID is generated by the handler.
+fn is_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Parses the header as a number, `400 Bad Request` if it is invalid.
+fn number(headers: &HeaderMap, name: &HeaderName) -> Result<Option<u64>> {
+    headers
+        .get(name)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| StatusCode::BAD_REQUEST.into_error())
+        })
+        .transpose()
+}
+
+fn is_offset_octet_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value == OFFSET_OCTET_STREAM)
+}
+
+/// Decodes the `Upload-Metadata`, e.g. `filename d29ybGRfZG9taW5hdGlvbi5wZGY=,is_confidential`.
+fn decode_metadata(value: &str) -> Option<BTreeMap<String, String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once(' ').unwrap_or((pair, ""));
+            let value = STANDARD.decode(value.trim()).ok()?;
+            Some((
+                key.to_string(),
+                String::from_utf8_lossy(&value).into_owned(),
+            ))
+        })
+        .collect()
+}
+
+fn encode_metadata(metadata: &BTreeMap<String, String>) -> String {
+    metadata
+        .iter()
+        .map(|(key, value)| {
+            if value.is_empty() {
+                key.clone()
+            } else {
+                format!("{key} {}", STANDARD.encode(value))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Maps the I/O error of the storage to the status.
+#[allow(clippy::needless_pass_by_value)]
+fn into_error(e: io::Error) -> vidi_core::Error {
+    match e.kind() {
+        io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        io::ErrorKind::InvalidData => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+    .into_error()
+}
+
+/// Formats the time as an HTTP date.
+fn http_date(time: SystemTime) -> HeaderValue {
+    let mut values = Vec::new();
+    headers::Expires::from(time).encode(&mut values);
+    values.pop().unwrap_or_else(|| HeaderValue::from_static(""))
+}
+
+fn status(code: StatusCode) -> Response {
+    let mut res = Response::empty();
+    *res.status_mut() = code;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tus, Upload};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use vidi_core::{
+        Handler, Method, Request, Response, StatusCode,
+        types::{Params, RouteInfo},
+    };
+
+    async fn call(
+        tus: &Tus,
+        method: Method,
+        id: &str,
+        headers: &[(&'static str, &str)],
+        body: &'static str,
+    ) -> Response {
+        let mut req: Request = Request::new(http_body_util::Full::from(body).into());
+        *req.method_mut() = method;
+        req.extensions_mut().insert(Arc::new(RouteInfo {
+            id: 2,
+            pattern: "/files/:id".to_string(),
+            params: Into::<Params>::into(vec![("id", id)]),
+        }));
+        *req.uri_mut() = format!("/files/{id}").parse().unwrap();
+        for (name, value) in headers {
+            req.headers_mut().insert(*name, value.parse().unwrap());
+        }
+        tus.call(req).await.unwrap()
+    }
+
+    fn header<'a>(res: &'a Response, name: &str) -> &'a str {
+        res.headers()[name].to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn tus() -> vidi_core::Result<()> {
+        let dir = std::env::temp_dir().join(format!("vidi-tus-{}", std::process::id()));
+        let completed = Arc::new(Mutex::new(Vec::new()));
+        let tus = Tus::new(&dir)?
+            .max_size(16)
+            .expiration(Duration::from_secs(60))
+            .on_complete({
+                let completed = completed.clone();
+                move |upload: Upload| {
+                    let completed = completed.clone();
+                    async move {
+                        completed.lock().unwrap().push(upload);
+                        Ok(())
+                    }
+                }
+            });
+        let resumable = ("tus-resumable", "1.0.0");
+        let stream = ("content-type", "application/offset+octet-stream");
+
+        let res = call(&tus, Method::OPTIONS, "", &[], "").await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(header(&res, "tus-max-size"), "16");
+        assert_eq!(header(&res, "tus-resumable"), "1.0.0");
+        let res = call(&tus, Method::POST, "", &[("upload-length", "10")], "").await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(header(&res, "tus-version"), "1.0.0");
+
+        // creation
+        let res = call(
+            &tus,
+            Method::POST,
+            "",
+            &[resumable, ("upload-length", "17")],
+            "",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let res = call(
+            &tus,
+            Method::POST,
+            "",
+            &[
+                resumable,
+                ("upload-length", "10"),
+                ("upload-metadata", "filename aGVsbG8udHh0,private"),
+            ],
+            "",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert!(res.headers().contains_key("upload-expires"));
+        let id = header(&res, "location")
+            .strip_prefix("/files/")
+            .unwrap()
+            .to_string();
+
+        let res = call(&tus, Method::HEAD, &id, &[resumable], "").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(header(&res, "upload-offset"), "0");
+        assert_eq!(header(&res, "upload-length"), "10");
+        assert_eq!(
+            header(&res, "upload-metadata"),
+            "filename aGVsbG8udHh0,private"
+        );
+        assert_eq!(header(&res, "cache-control"), "no-store");
+
+        // appending
+        let res = call(
+            &tus,
+            Method::PATCH,
+            &id,
+            &[resumable, ("upload-offset", "0")],
+            "x",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let res = call(
+            &tus,
+            Method::PATCH,
+            &id,
+            &[resumable, stream, ("upload-offset", "0")],
+            "01234",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(header(&res, "upload-offset"), "5");
+        let res = call(
+            &tus,
+            Method::PATCH,
+            &id,
+            &[resumable, stream, ("upload-offset", "0")],
+            "01234",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert!(completed.lock().unwrap().is_empty());
+        let res = call(
+            &tus,
+            Method::PATCH,
+            &id,
+            &[resumable, stream, ("upload-offset", "5")],
+            "5678",
+        )
+        .await;
+        assert_eq!(header(&res, "upload-offset"), "9");
+        let res = call(
+            &tus,
+            Method::PATCH,
+            &id,
+            &[resumable, stream, ("upload-offset", "9")],
+            "9ab",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        {
+            let completed = completed.lock().unwrap();
+            assert_eq!(completed.len(), 1);
+            assert_eq!(completed[0].id, id);
+            assert_eq!(completed[0].metadata["filename"], "hello.txt");
+            assert_eq!(completed[0].metadata["private"], "");
+        }
+        assert_eq!(std::fs::read_to_string(dir.join(&id))?, "0123456789");
+        let res = call(&tus, Method::HEAD, &id, &[resumable], "").await;
+        assert_eq!(header(&res, "upload-offset"), "10");
+        assert!(!res.headers().contains_key("upload-expires"));
+
+        // the completed upload is immutable, the hooks are not called again
+        let res = call(
+            &tus,
+            Method::PATCH,
+            &id,
+            &[resumable, stream, ("upload-offset", "10")],
+            "",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(completed.lock().unwrap().len(), 1);
+
+        // the deferred length and the creation with upload
+        let res = call(
+            &tus,
+            Method::POST,
+            "",
+            &[resumable, stream, ("upload-defer-length", "1")],
+            "abc",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(header(&res, "upload-offset"), "3");
+        let deferred = header(&res, "location")
+            .strip_prefix("/files/")
+            .unwrap()
+            .to_string();
+        let res = call(&tus, Method::HEAD, &deferred, &[resumable], "").await;
+        assert_eq!(header(&res, "upload-defer-length"), "1");
+        let res = call(
+            &tus,
+            Method::PATCH,
+            &deferred,
+            &[
+                resumable,
+                stream,
+                ("upload-offset", "3"),
+                ("upload-length", "4"),
+            ],
+            "d",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(completed.lock().unwrap().len(), 2);
+
+        // termination
+        let res = call(&tus, Method::DELETE, &deferred, &[resumable], "").await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = call(&tus, Method::HEAD, &deferred, &[resumable], "").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = call(&tus, Method::HEAD, "../../etc/passwd", &[resumable], "").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // expiration
+        let expired = tus.clone().expiration(Duration::ZERO);
+        let res = call(
+            &expired,
+            Method::POST,
+            "",
+            &[resumable, ("upload-length", "1")],
+            "",
+        )
+        .await;
+        let id = header(&res, "location")
+            .strip_prefix("/files/")
+            .unwrap()
+            .to_string();
+        let res = call(&expired, Method::HEAD, &id, &[resumable], "").await;
+        assert_eq!(res.status(), StatusCode::GONE);
+        assert_eq!(expired.purge().await?, 1);
+        let res = call(&expired, Method::HEAD, &id, &[resumable], "").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! The storages of the uploads.
+
+use std::{collections::BTreeMap, io, path::PathBuf, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio_stream::StreamExt;
+use vidi_core::Body;
+
+/// An upload.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Upload {
+    /// The ID of the upload.
+    pub id: String,
+    /// The number of the received bytes, which is the size of the stored data.
+    #[serde(skip)]
+    pub offset: u64,
+    /// The total size, `None` if the length is deferred.
+    pub length: Option<u64>,
+    /// The decoded `Upload-Metadata`, e.g. `filename`.
+    pub metadata: BTreeMap<String, String>,
+    /// The expiration time of the unfinished upload.
+    pub expires: Option<SystemTime>,
+}
+
+impl Upload {
+    /// Checks all the bytes are received.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.length == Some(self.offset)
+    }
+
+    /// Checks the unfinished upload is expired.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expires
+            .is_some_and(|expires| expires <= SystemTime::now())
+    }
+}
+
+/// The storage of the [`Tus`][super::Tus] handler.
+#[vidi_core::async_trait]
+pub trait Storage: Send + Sync + 'static {
+    /// Creates an empty upload.
+    async fn create(&self, upload: &Upload) -> io::Result<()>;
+
+    /// Gets the upload with the current offset.
+    async fn get(&self, id: &str) -> io::Result<Upload>;
+
+    /// Updates the information of the upload, e.g. the deferred length and the expiration.
+    async fn update(&self, upload: &Upload) -> io::Result<()>;
+
+    /// Appends at most `max` bytes of the body, returns the new offset.
+    ///
+    /// The received bytes must be kept if the body fails, e.g. the connection is lost. Returns
+    /// [`io::ErrorKind::InvalidData`] if the body exceeds the `max`.
+    async fn append(&self, id: &str, body: Body, max: u64) -> io::Result<u64>;
+
+    /// Deletes the upload.
+    async fn delete(&self, id: &str) -> io::Result<()>;
+
+    /// Lists the IDs of the uploads.
+    async fn list(&self) -> io::Result<Vec<String>>;
+}
+
+/// The storage of a local directory, the data and the information of an upload are stored in
+/// `{id}` and `{id}.json`.
+#[derive(Clone, Debug)]
+pub struct LocalDisk {
+    dir: PathBuf,
+}
+
+impl LocalDisk {
+    /// Creates a storage by the specified directory, which is created if it is missing.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the directory could not be created.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// The path of the data of the upload, e.g. to move the completed file.
+    #[must_use]
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    /// Resolves the paths of the data and the information, the invalid IDs are rejected.
+    fn paths(&self, id: &str) -> io::Result<(PathBuf, PathBuf)> {
+        if !super::is_id(id) {
+            Err(io::Error::from(io::ErrorKind::NotFound))?;
+        }
+        Ok((self.dir.join(id), self.dir.join(format!("{id}.json"))))
+    }
+
+    async fn write_info(&self, upload: &Upload) -> io::Result<()> {
+        let (_, info) = self.paths(&upload.id)?;
+        tokio::fs::write(info, serde_json::to_vec(upload)?).await
+    }
+}
+
+#[vidi_core::async_trait]
+impl Storage for LocalDisk {
+    async fn create(&self, upload: &Upload) -> io::Result<()> {
+        let (data, _) = self.paths(&upload.id)?;
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(data)
+            .await?;
+        self.write_info(upload).await
+    }
+
+    async fn get(&self, id: &str) -> io::Result<Upload> {
+        let (data, info) = self.paths(id)?;
+        let mut upload: Upload = serde_json::from_slice(&tokio::fs::read(info).await?)?;
+        upload.offset = tokio::fs::metadata(data).await?.len();
+        Ok(upload)
+    }
+
+    async fn update(&self, upload: &Upload) -> io::Result<()> {
+        self.write_info(upload).await
+    }
+
+    async fn append(&self, id: &str, mut body: Body, max: u64) -> io::Result<u64> {
+        let (data, _) = self.paths(id)?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&data)
+            .await?;
+
+        let mut remaining = max;
+        let result = loop {
+            let chunk = match body.next().await {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => break Err(e),
+                None => break Ok(()),
+            };
+            if chunk.len() as u64 > remaining {
+                let len = usize::try_from(remaining).unwrap_or(usize::MAX);
+                if let Err(e) = file.write_all(&chunk[..len]).await {
+                    break Err(e);
+                }
+                break Err(io::ErrorKind::InvalidData.into());
+            }
+            if let Err(e) = file.write_all(&chunk).await {
+                break Err(e);
+            }
+            remaining -= chunk.len() as u64;
+        };
+
+        // the received bytes are kept
+        file.flush().await?;
+        result?;
+        Ok(file.metadata().await?.len())
+    }
+
+    async fn delete(&self, id: &str) -> io::Result<()> {
+        let (data, info) = self.paths(id)?;
+        tokio::fs::remove_file(info).await?;
+        match tokio::fs::remove_file(data).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> io::Result<Vec<String>> {
+        let mut ids = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            if let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".json"))
+                .filter(|id| super::is_id(id))
+            {
+                ids.push(id.to_string());
+            }
+        }
+        Ok(ids)
+    }
+}
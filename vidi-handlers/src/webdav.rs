@@ -0,0 +1,1169 @@
+//! `WebDAV`, class 1 and 2, [RFC 4918].
+//!
+//! The custom methods, e.g. `PROPFIND`, should be registered for the route:
+//!
+//! ```
+//! use vidi_handlers::webdav::{self, MemoryFs, WebDav};
+//!
+//! let dav = WebDav::with_fs(MemoryFs::new());
+//! // e.g. `webdav::methods().fold(Route::new(), |route, method| route.on(method, dav.clone()))`
+//! let methods = webdav::methods().collect::<Vec<_>>();
+//! assert!(methods.iter().any(|method| method == "PROPFIND"));
+//! ```
+//!
+//! The dead properties and the locks are kept in memory.
+//!
+//! [RFC 4918]: <https://www.rfc-editor.org/rfc/rfc4918>
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::SystemTime,
+};
+
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use vidi_core::{
+    Body, Handler, IntoResponse, Method, Request, RequestExt, Response, ResponseExt, Result,
+    StatusCode,
+    header::{ALLOW, CONTENT_LENGTH, HOST, HeaderName, HeaderValue},
+    headers::{self, Header, HeaderMapExt},
+    types::PayloadError,
+};
+
+mod fs;
+mod lock;
+mod xml;
+
+pub use fs::{FileSystem, LocalFs, MemoryFs, Metadata};
+
+use lock::Locks;
+use xml::{DAV, Element, Name};
+
+/// The methods of the `WebDAV` handler.
+const METHODS: [&str; 12] = [
+    "OPTIONS",
+    "GET",
+    "HEAD",
+    "PUT",
+    "DELETE",
+    "MKCOL",
+    "COPY",
+    "MOVE",
+    "PROPFIND",
+    "PROPPATCH",
+    "LOCK",
+    "UNLOCK",
+];
+
+/// The characters which are encoded in the hrefs.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b']')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The methods of the `WebDAV` handler, which should be registered for the route.
+pub fn methods() -> impl Iterator<Item = Method> {
+    METHODS
+        .into_iter()
+        .filter_map(|method| Method::from_bytes(method.as_bytes()).ok())
+}
+
+/// The state of the dead properties and the locks.
+#[derive(Debug, Default)]
+struct State {
+    locks: Locks,
+    props: HashMap<String, BTreeMap<Name, String>>,
+}
+
+impl State {
+    /// Removes the properties and the locks of the path and its descendants.
+    fn remove(&mut self, path: &str) {
+        self.props
+            .retain(|key, _| key != path && !is_descendant(key, path));
+        self.locks.remove(path);
+    }
+
+    /// Moves or copies the properties of the path and its descendants.
+    fn transfer(&mut self, from: &str, to: &str, keep: bool) {
+        let keys = self
+            .props
+            .keys()
+            .filter(|key| *key == from || is_descendant(key, from))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            let props = if keep {
+                self.props.get(&key).cloned()
+            } else {
+                self.props.remove(&key)
+            };
+            if let Some(props) = props {
+                self.props
+                    .insert(format!("{to}{}", &key[from.len()..]), props);
+            }
+        }
+    }
+}
+
+/// The `WebDAV` handler over a file system.
+#[derive(Debug)]
+pub struct WebDav<F = LocalFs> {
+    fs: Arc<F>,
+    state: Arc<Mutex<State>>,
+}
+
+impl<F> Clone for WebDav<F> {
+    fn clone(&self) -> Self {
+        Self {
+            fs: self.fs.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl WebDav {
+    /// Serves the local directory by the specified path.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the path is not a directory.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        LocalFs::new(path).map(Self::with_fs)
+    }
+}
+
+impl<F> WebDav<F> {
+    /// Serves the file system.
+    #[must_use]
+    pub fn with_fs(fs: F) -> Self {
+        Self {
+            fs: Arc::new(fs),
+            state: Arc::default(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[vidi_core::async_trait]
+impl<F> Handler<Request> for WebDav<F>
+where
+    F: FileSystem,
+{
+    type Output = Result<Response>;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let (prefix, path) = target(&req)?;
+        let dav = Dav {
+            prefix,
+            tokens: req
+                .headers()
+                .get("if")
+                .and_then(|value| value.to_str().ok())
+                .map(lock::tokens)
+                .unwrap_or_default(),
+        };
+
+        match req.method().as_str() {
+            "OPTIONS" => Ok(options()),
+            "GET" | "HEAD" => self.get(&req, &path).await,
+            "PUT" => self.put(&mut req, &dav, &path).await,
+            "DELETE" => self.delete(&dav, &path).await,
+            "MKCOL" => self.mkcol(&mut req, &dav, &path).await,
+            "COPY" => self.transfer(&req, &dav, &path, false).await,
+            "MOVE" => self.transfer(&req, &dav, &path, true).await,
+            "PROPFIND" => self.propfind(&mut req, &dav, &path).await,
+            "PROPPATCH" => self.proppatch(&mut req, &dav, &path).await,
+            "LOCK" => self.lock(&mut req, &dav, &path).await,
+            "UNLOCK" => self.unlock(&req, &path),
+            _ => Err(StatusCode::METHOD_NOT_ALLOWED.into_error()),
+        }
+    }
+}
+
+/// The context of a request.
+struct Dav {
+    /// The path of the mount point, e.g. `/dav/`.
+    prefix: String,
+    /// The submitted lock tokens.
+    tokens: Vec<String>,
+}
+
+impl Dav {
+    /// The href of the path, the collections end with `/`.
+    fn href(&self, path: &str, is_dir: bool) -> String {
+        let mut href = self.prefix.clone();
+        for (i, seg) in path.split('/').filter(|seg| !seg.is_empty()).enumerate() {
+            if i > 0 {
+                href.push('/');
+            }
+            href.extend(utf8_percent_encode(seg, SEGMENT));
+        }
+        if is_dir && !path.is_empty() {
+            href.push('/');
+        }
+        href
+    }
+}
+
+impl<F> WebDav<F>
+where
+    F: FileSystem,
+{
+    async fn metadata(&self, path: &str) -> Result<Metadata> {
+        self.fs.metadata(path).await.map_err(into_error)
+    }
+
+    /// Checks the parent of the path is a collection, `409 Conflict` otherwise.
+    async fn check_parent(&self, path: &str) -> Result<()> {
+        let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+        match self.fs.metadata(parent).await {
+            Ok(metadata) if metadata.is_dir && !path.is_empty() => Ok(()),
+            _ => Err(StatusCode::CONFLICT.into_error()),
+        }
+    }
+
+    /// Checks the submitted tokens of the locks, `423 Locked` otherwise.
+    fn check_locks(&self, dav: &Dav, path: &str, deep: bool) -> Result<()> {
+        if self.state().locks.check(path, deep, &dav.tokens) {
+            Ok(())
+        } else {
+            Err(StatusCode::LOCKED.into_error())
+        }
+    }
+
+    /// Removes the file or the collection with its properties and locks.
+    async fn remove(&self, path: &str, metadata: Metadata) -> Result<()> {
+        if metadata.is_dir {
+            self.fs.remove_dir(path).await
+        } else {
+            self.fs.remove_file(path).await
+        }
+        .map_err(into_error)?;
+        self.state().remove(path);
+        Ok(())
+    }
+
+    async fn get(&self, req: &Request, path: &str) -> Result<Response> {
+        let metadata = self.metadata(path).await?;
+        if metadata.is_dir {
+            Err(StatusCode::METHOD_NOT_ALLOWED.into_error())?;
+        }
+
+        let mut res = if req.method() == Method::HEAD {
+            Response::empty()
+        } else {
+            Response::new(self.fs.read(path).await.map_err(into_error)?)
+        };
+        let headers = res.headers_mut();
+        headers.typed_insert(headers::ContentLength(metadata.len));
+        headers.typed_insert(headers::ContentType::from(
+            mime_guess::from_path(path).first_or_octet_stream(),
+        ));
+        if let Some(etag) = etag(&metadata).and_then(|etag| etag.parse::<headers::ETag>().ok()) {
+            headers.typed_insert(etag);
+        }
+        if let Some(modified) = metadata.modified {
+            headers.typed_insert(headers::LastModified::from(modified));
+        }
+        Ok(res)
+    }
+
+    async fn put(&self, req: &mut Request, dav: &Dav, path: &str) -> Result<Response> {
+        let existed = match self.fs.metadata(path).await {
+            Ok(metadata) if metadata.is_dir => Err(StatusCode::METHOD_NOT_ALLOWED.into_error())?,
+            Ok(_) => true,
+            Err(_) => false,
+        };
+        self.check_parent(path).await?;
+        self.check_locks(dav, path, false)?;
+
+        let body = match req.incoming() {
+            Ok(body) => body,
+            Err(PayloadError::Empty) => Body::Empty,
+            Err(e) => Err(e)?,
+        };
+        self.fs.write(path, body).await.map_err(into_error)?;
+
+        Ok(status(if existed {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::CREATED
+        }))
+    }
+
+    async fn delete(&self, dav: &Dav, path: &str) -> Result<Response> {
+        let metadata = self.metadata(path).await?;
+        if path.is_empty() {
+            Err(StatusCode::FORBIDDEN.into_error())?;
+        }
+        self.check_locks(dav, path, true)?;
+        self.remove(path, metadata).await?;
+        Ok(status(StatusCode::NO_CONTENT))
+    }
+
+    async fn mkcol(&self, req: &mut Request, dav: &Dav, path: &str) -> Result<Response> {
+        match req.bytes().await {
+            Ok(body) if !body.is_empty() => Err(StatusCode::UNSUPPORTED_MEDIA_TYPE.into_error())?,
+            Ok(_) | Err(PayloadError::Empty) => {}
+            Err(e) => Err(e)?,
+        }
+        if self.fs.metadata(path).await.is_ok() {
+            Err(StatusCode::METHOD_NOT_ALLOWED.into_error())?;
+        }
+        self.check_parent(path).await?;
+        self.check_locks(dav, path, false)?;
+        self.fs.create_dir(path).await.map_err(into_error)?;
+        Ok(status(StatusCode::CREATED))
+    }
+
+    /// Copies or moves the resource to the `Destination`.
+    async fn transfer(
+        &self,
+        req: &Request,
+        dav: &Dav,
+        path: &str,
+        moving: bool,
+    ) -> Result<Response> {
+        let to = destination(req, &dav.prefix)?;
+        let overwrite = !req
+            .headers()
+            .get("overwrite")
+            .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"F"));
+        let deep = match depth(req) {
+            Some(0) if !moving => false,
+            None => true,
+            _ => Err(StatusCode::BAD_REQUEST.into_error())?,
+        };
+
+        let metadata = self.metadata(path).await?;
+        if path.is_empty() || to == path || is_descendant(&to, path) {
+            Err(StatusCode::FORBIDDEN.into_error())?;
+        }
+        self.check_parent(&to).await?;
+        if moving {
+            self.check_locks(dav, path, true)?;
+        }
+
+        let created = match self.fs.metadata(&to).await {
+            Ok(_) if !overwrite => Err(StatusCode::PRECONDITION_FAILED.into_error())?,
+            Ok(existing) => {
+                self.check_locks(dav, &to, true)?;
+                self.remove(&to, existing).await?;
+                false
+            }
+            Err(_) => {
+                self.check_locks(dav, &to, false)?;
+                true
+            }
+        };
+
+        if moving {
+            self.fs.rename(path, &to).await.map_err(into_error)?;
+            let mut state = self.state();
+            state.locks.remove(path);
+            state.transfer(path, &to, false);
+        } else {
+            let mut pending = vec![(path.to_string(), to.clone(), metadata)];
+            while let Some((from, to, metadata)) = pending.pop() {
+                if !metadata.is_dir {
+                    self.fs.copy_file(&from, &to).await.map_err(into_error)?;
+                    continue;
+                }
+                self.fs.create_dir(&to).await.map_err(into_error)?;
+                if !deep {
+                    continue;
+                }
+                for name in self.fs.read_dir(&from).await.map_err(into_error)? {
+                    let child = join(&from, &name);
+                    let metadata = self.metadata(&child).await?;
+                    pending.push((child, join(&to, &name), metadata));
+                }
+            }
+            let mut state = self.state();
+            if deep {
+                state.transfer(path, &to, true);
+            } else if let Some(props) = state.props.get(path).cloned() {
+                state.props.insert(to, props);
+            }
+        }
+
+        Ok(status(if created {
+            StatusCode::CREATED
+        } else {
+            StatusCode::NO_CONTENT
+        }))
+    }
+
+    async fn propfind(&self, req: &mut Request, dav: &Dav, path: &str) -> Result<Response> {
+        let find = match body(req).await? {
+            None => Find::All,
+            Some(root) if root.is("propfind") => {
+                if root.child("propname").is_some() {
+                    Find::Names
+                } else if let Some(prop) = root.child("prop") {
+                    Find::Props(prop.elements().map(|prop| prop.name.clone()).collect())
+                } else {
+                    Find::All
+                }
+            }
+            Some(_) => Err(StatusCode::BAD_REQUEST.into_error())?,
+        };
+
+        let metadata = self.metadata(path).await?;
+        let depth = depth(req);
+        let mut resources = vec![(path.to_string(), metadata, 0)];
+        let mut i = 0;
+        while i < resources.len() {
+            let (parent, metadata, level) = &resources[i];
+            if metadata.is_dir && depth.is_none_or(|depth| *level < depth) {
+                let (parent, level) = (parent.clone(), level + 1);
+                let mut names = self.fs.read_dir(&parent).await.map_err(into_error)?;
+                names.sort();
+                for name in names {
+                    let child = join(&parent, &name);
+                    if let Ok(metadata) = self.fs.metadata(&child).await {
+                        resources.push((child, metadata, level));
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let mut xml = multistatus();
+        for (path, metadata, _) in &resources {
+            let mut found = String::new();
+            let mut missing = String::new();
+            let live = self.live(dav, path, metadata);
+            let dead = self.state().props.get(path).cloned().unwrap_or_default();
+
+            match &find {
+                Find::All | Find::Names => {
+                    let names = matches!(find, Find::Names);
+                    for (name, value) in live.iter().chain(&dead) {
+                        name.write(&mut found, if names { "" } else { value });
+                    }
+                }
+                Find::Props(props) => {
+                    for name in props {
+                        match live.get(name).or_else(|| dead.get(name)) {
+                            Some(value) => name.write(&mut found, value),
+                            None => name.write(&mut missing, ""),
+                        }
+                    }
+                }
+            }
+
+            let _ = write!(
+                xml,
+                "<D:response><D:href>{}</D:href>",
+                xml::escape(&dav.href(path, metadata.is_dir))
+            );
+            propstat(&mut xml, &found, StatusCode::OK);
+            propstat(&mut xml, &missing, StatusCode::NOT_FOUND);
+            xml.push_str("</D:response>");
+        }
+        xml.push_str("</D:multistatus>");
+
+        Ok(xml_response(StatusCode::MULTI_STATUS, xml))
+    }
+
+    /// The live properties of the resource.
+    fn live(&self, dav: &Dav, path: &str, metadata: &Metadata) -> BTreeMap<Name, String> {
+        let mut props = BTreeMap::new();
+        let mut insert = |local: &str, value: String| {
+            props.insert(Name::dav(local), value);
+        };
+
+        insert(
+            "resourcetype",
+            if metadata.is_dir {
+                "<D:collection/>".to_string()
+            } else {
+                String::new()
+            },
+        );
+        if !metadata.is_dir {
+            insert("getcontentlength", metadata.len.to_string());
+            insert(
+                "getcontenttype",
+                mime_guess::from_path(path)
+                    .first_or_octet_stream()
+                    .to_string(),
+            );
+            if let Some(etag) = etag(metadata) {
+                insert("getetag", xml::escape(&etag));
+            }
+        }
+        if let Some(modified) = metadata.modified {
+            insert("getlastmodified", http_date(modified));
+        }
+        insert(
+            "supportedlock",
+            ["exclusive", "shared"]
+                .map(|scope| {
+                    format!(
+                        "<D:lockentry><D:lockscope><D:{scope}/></D:lockscope>\
+                         <D:locktype><D:write/></D:locktype></D:lockentry>"
+                    )
+                })
+                .concat(),
+        );
+
+        let mut discovery = String::new();
+        for lock in self.state().locks.discover(path) {
+            lock.write(&mut discovery, &xml::escape(&dav.href(&lock.path, true)));
+        }
+        insert("lockdiscovery", discovery);
+
+        props
+    }
+
+    async fn proppatch(&self, req: &mut Request, dav: &Dav, path: &str) -> Result<Response> {
+        let metadata = self.metadata(path).await?;
+        self.check_locks(dav, path, false)?;
+
+        let Some(root) = body(req).await?.filter(|root| root.is("propertyupdate")) else {
+            Err(StatusCode::BAD_REQUEST.into_error())?
+        };
+        let mut updates = Vec::new();
+        for instruction in root.elements() {
+            let set = match instruction {
+                element if element.is("set") => true,
+                element if element.is("remove") => false,
+                _ => continue,
+            };
+            for prop in instruction
+                .child("prop")
+                .iter()
+                .flat_map(|prop| prop.elements())
+            {
+                updates.push((prop.name.clone(), set.then(|| prop.inner_xml())));
+            }
+        }
+
+        // the updates are atomic, the live properties are protected
+        let forbidden = updates.iter().any(|(name, _)| name.ns == DAV);
+        let mut found = String::new();
+        let mut protected = String::new();
+        let mut failed = String::new();
+        {
+            let mut state = self.state();
+            for (name, value) in updates {
+                if forbidden {
+                    if name.ns == DAV {
+                        name.write(&mut protected, "");
+                    } else {
+                        name.write(&mut failed, "");
+                    }
+                    continue;
+                }
+                let props = state.props.entry(path.to_string()).or_default();
+                match value {
+                    Some(value) => props.insert(name.clone(), value),
+                    None => props.remove(&name),
+                };
+                name.write(&mut found, "");
+            }
+        }
+
+        let mut xml = multistatus();
+        let _ = write!(
+            xml,
+            "<D:response><D:href>{}</D:href>",
+            xml::escape(&dav.href(path, metadata.is_dir))
+        );
+        propstat(&mut xml, &found, StatusCode::OK);
+        propstat(&mut xml, &protected, StatusCode::FORBIDDEN);
+        propstat(&mut xml, &failed, StatusCode::FAILED_DEPENDENCY);
+        xml.push_str("</D:response></D:multistatus>");
+
+        Ok(xml_response(StatusCode::MULTI_STATUS, xml))
+    }
+
+    async fn lock(&self, req: &mut Request, dav: &Dav, path: &str) -> Result<Response> {
+        let timeout = req
+            .headers()
+            .get("timeout")
+            .and_then(|value| value.to_str().ok())
+            .and_then(lock::timeout);
+
+        let (code, xml, token) = match body(req).await? {
+            // refreshes the lock by the submitted token
+            None => {
+                let mut state = self.state();
+                let Some(lock) = state.locks.refresh(path, &dav.tokens, timeout) else {
+                    Err(StatusCode::PRECONDITION_FAILED.into_error())?
+                };
+                let mut xml = String::new();
+                lock.write(&mut xml, &xml::escape(&dav.href(&lock.path, true)));
+                (StatusCode::OK, xml, None)
+            }
+            Some(root) if root.is("lockinfo") => {
+                let exclusive = root
+                    .child("lockscope")
+                    .is_none_or(|scope| scope.child("shared").is_none());
+                if root
+                    .child("locktype")
+                    .is_some_and(|kind| kind.child("write").is_none())
+                {
+                    Err(StatusCode::UNPROCESSABLE_ENTITY.into_error())?;
+                }
+                let infinite = match depth(req) {
+                    Some(0) => false,
+                    None => true,
+                    _ => Err(StatusCode::BAD_REQUEST.into_error())?,
+                };
+                let owner = root.child("owner").map(Element::inner_xml);
+
+                // an empty resource is created for the unmapped path
+                let created = self.fs.metadata(path).await.is_err();
+                if created {
+                    self.check_parent(path).await?;
+                    self.check_locks(dav, path, false)?;
+                }
+
+                let (xml, token) = {
+                    let mut state = self.state();
+                    let Some(lock) = state.locks.lock(path, exclusive, infinite, owner, timeout)
+                    else {
+                        Err(StatusCode::LOCKED.into_error())?
+                    };
+                    let mut xml = String::new();
+                    lock.write(&mut xml, &xml::escape(&dav.href(path, false)));
+                    (xml, lock.token.clone())
+                };
+
+                if created {
+                    if let Err(e) = self.fs.write(path, Body::Empty).await {
+                        self.state().locks.unlock(path, &token);
+                        Err(into_error(e))?;
+                    }
+                }
+
+                let code = if created {
+                    StatusCode::CREATED
+                } else {
+                    StatusCode::OK
+                };
+                (code, xml, Some(token))
+            }
+            Some(_) => Err(StatusCode::BAD_REQUEST.into_error())?,
+        };
+
+        let mut res = xml_response(
+            code,
+            format!(
+                r#"<?xml version="1.0" encoding="utf-8"?><D:prop xmlns:D="DAV:"><D:lockdiscovery>{xml}</D:lockdiscovery></D:prop>"#
+            ),
+        );
+        if let Some(value) =
+            token.and_then(|token| HeaderValue::try_from(format!("<{token}>")).ok())
+        {
+            res.headers_mut()
+                .insert(HeaderName::from_static("lock-token"), value);
+        }
+        Ok(res)
+    }
+
+    fn unlock(&self, req: &Request, path: &str) -> Result<Response> {
+        let Some(token) = req
+            .headers()
+            .get("lock-token")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().trim_start_matches('<').trim_end_matches('>'))
+        else {
+            Err(StatusCode::BAD_REQUEST.into_error())?
+        };
+
+        if self.state().locks.unlock(path, token) {
+            Ok(status(StatusCode::NO_CONTENT))
+        } else {
+            Err(StatusCode::CONFLICT.into_error())
+        }
+    }
+}
+
+/// The properties to find.
+enum Find {
+    All,
+    Names,
+    Props(Vec<Name>),
+}
+
+/// Resolves the mount point and the relative path of the request.
+fn target(req: &Request) -> Result<(String, String)> {
+    let full = req.path();
+    let param = req
+        .route_info()
+        .params
+        .first()
+        .map_or("", |(_, v)| v.as_str());
+
+    let mut prefix = full.strip_suffix(param).unwrap_or(full).to_string();
+    if !prefix.ends_with('/') {
+        prefix.push('/');
+    }
+
+    Ok((prefix, resolve(param)?))
+}
+
+/// Decodes and validates the relative path.
+fn resolve(raw: &str) -> Result<String> {
+    let decoded = percent_decode_str(raw)
+        .decode_utf8()
+        .map_err(|_| StatusCode::BAD_REQUEST.into_error())?;
+    crate::path::segments(&decoded)
+        .map(|segments| segments.join("/"))
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_error())
+}
+
+/// Resolves the relative path of the `Destination` header, which must be under the mount point
+/// of this server.
+fn destination(req: &Request, prefix: &str) -> Result<String> {
+    let Some(value) = req
+        .headers()
+        .get("destination")
+        .and_then(|value| value.to_str().ok())
+    else {
+        Err(StatusCode::BAD_REQUEST.into_error())?
+    };
+
+    // the absolute URI is stripped to the path, its authority must be the host
+    let path = match value.split_once("://") {
+        Some((_, rest)) => {
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            let host = req
+                .headers()
+                .get(HOST)
+                .and_then(|value| value.to_str().ok())
+                .or_else(|| req.uri().authority().map(AsRef::as_ref));
+            if !host.is_some_and(|host| host.eq_ignore_ascii_case(authority)) {
+                Err(StatusCode::BAD_GATEWAY.into_error())?;
+            }
+            rest.find('/').map_or("/", |i| &rest[i..])
+        }
+        None => value,
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+
+    match path.strip_prefix(prefix) {
+        Some(rest) => resolve(rest),
+        None if format!("{path}/") == prefix => Ok(String::new()),
+        None => Err(StatusCode::BAD_GATEWAY.into_error()),
+    }
+}
+
+/// Parses the `Depth` header, `None` is infinity.
+fn depth(req: &Request) -> Option<usize> {
+    match req.headers().get("depth").map(HeaderValue::as_bytes) {
+        Some(b"0") => Some(0),
+        Some(b"1") => Some(1),
+        _ => None,
+    }
+}
+
+/// Reads and parses the XML body, `None` if it is empty.
+async fn body(req: &mut Request) -> Result<Option<Element>> {
+    let bytes = match req.bytes().await {
+        Ok(bytes) => bytes,
+        Err(PayloadError::Empty) => return Ok(None),
+        Err(e) => Err(e)?,
+    };
+    let text = std::str::from_utf8(&bytes).map_err(|_| StatusCode::BAD_REQUEST.into_error())?;
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    xml::parse(text)
+        .map(Some)
+        .ok_or_else(|| StatusCode::BAD_REQUEST.into_error())
+}
+
+/// Checks the path is a descendant of the ancestor, the root is `""`.
+fn is_descendant(path: &str, ancestor: &str) -> bool {
+    if ancestor.is_empty() {
+        return !path.is_empty();
+    }
+    path.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Joins the name to the relative path.
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}/{name}")
+    }
+}
+
+/// Maps the I/O error to the status.
+#[allow(clippy::needless_pass_by_value)]
+fn into_error(e: io::Error) -> vidi_core::Error {
+    let code = match e.kind() {
+        io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        io::ErrorKind::AlreadyExists => StatusCode::METHOD_NOT_ALLOWED,
+        io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        io::ErrorKind::InvalidInput => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (code, e.to_string()).into_error()
+}
+
+/// The weak validator of the file.
+fn etag(metadata: &Metadata) -> Option<String> {
+    let mtime = metadata
+        .modified?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .as_millis();
+    Some(format!(r#""{mtime}-{}""#, metadata.len))
+}
+
+/// Formats the time as an HTTP date.
+fn http_date(time: SystemTime) -> String {
+    let mut values = Vec::new();
+    headers::LastModified::from(time).encode(&mut values);
+    values
+        .first()
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn status(code: StatusCode) -> Response {
+    let mut res = Response::empty();
+    *res.status_mut() = code;
+    res
+}
+
+fn options() -> Response {
+    let mut res = Response::empty();
+    let headers = res.headers_mut();
+    headers.insert(
+        HeaderName::from_static("dav"),
+        HeaderValue::from_static("1, 2"),
+    );
+    headers.insert(
+        HeaderName::from_static("ms-author-via"),
+        HeaderValue::from_static("DAV"),
+    );
+    if let Ok(allow) = HeaderValue::try_from(METHODS.join(", ")) {
+        headers.insert(ALLOW, allow);
+    }
+    headers.insert(CONTENT_LENGTH, HeaderValue::from_static("0"));
+    res
+}
+
+fn multistatus() -> String {
+    r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#.to_string()
+}
+
+/// Writes the `DAV:propstat` element if there are properties.
+fn propstat(xml: &mut String, props: &str, code: StatusCode) {
+    if !props.is_empty() {
+        let _ = write!(
+            xml,
+            "<D:propstat><D:prop>{props}</D:prop><D:status>HTTP/1.1 {code}</D:status></D:propstat>"
+        );
+    }
+}
+
+fn xml_response(code: StatusCode, xml: String) -> Response {
+    let mut res = Response::with(
+        http_body_util::Full::from(xml),
+        "application/xml; charset=utf-8",
+    );
+    *res.status_mut() = code;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryFs, WebDav};
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use vidi_core::{
+        Handler, IntoResponse, Method, Request, StatusCode,
+        types::{Params, RouteInfo},
+    };
+
+    async fn call(
+        dav: &WebDav<MemoryFs>,
+        method: &str,
+        path: &'static str,
+        headers: &[(&'static str, &str)],
+        body: &'static str,
+    ) -> (StatusCode, vidi_core::header::HeaderMap, String) {
+        let mut req: Request = Request::new(http_body_util::Full::from(body).into());
+        *req.method_mut() = Method::from_bytes(method.as_bytes()).unwrap();
+        req.extensions_mut().insert(Arc::new(RouteInfo {
+            id: 2,
+            pattern: "/dav/*".to_string(),
+            params: Into::<Params>::into(vec![("*1", path)]),
+        }));
+        *req.uri_mut() = format!("/dav/{path}").parse().unwrap();
+        for (name, value) in headers {
+            req.headers_mut().insert(*name, value.parse().unwrap());
+        }
+        let res = dav
+            .call(req)
+            .await
+            .unwrap_or_else(IntoResponse::into_response);
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        (status, headers, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn webdav() {
+        let dav = WebDav::with_fs(MemoryFs::new());
+
+        let (status, headers, _) = call(&dav, "OPTIONS", "", &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["dav"], "1, 2");
+
+        // collections and files
+        assert_eq!(
+            call(&dav, "MKCOL", "docs", &[], "").await.0,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            call(&dav, "MKCOL", "docs", &[], "").await.0,
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            call(&dav, "MKCOL", "a/b", &[], "").await.0,
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            call(&dav, "PUT", "docs/a%20b.txt", &[], "hello").await.0,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            call(&dav, "PUT", "docs/a%20b.txt", &[], "hello!").await.0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            call(&dav, "PUT", "x/y.txt", &[], "").await.0,
+            StatusCode::CONFLICT
+        );
+        let (status, headers, body) = call(&dav, "GET", "docs/a%20b.txt", &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-type"], "text/plain");
+        assert_eq!(body, "hello!");
+        assert_eq!(
+            call(&dav, "GET", "../etc/passwd", &[], "").await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        // properties
+        let (status, _, body) = call(&dav, "PROPFIND", "", &[("depth", "1")], "").await;
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        assert!(body.contains("<D:href>/dav/</D:href>"));
+        assert!(body.contains("<D:href>/dav/docs/</D:href>"));
+        assert!(!body.contains("a%20b.txt"));
+        let (_, _, body) = call(&dav, "PROPFIND", "", &[], "").await;
+        assert!(body.contains("<D:href>/dav/docs/a%20b.txt</D:href>"));
+        assert!(body.contains("<D:getcontentlength>6</D:getcontentlength>"));
+
+        let update = r#"<?xml version="1.0"?>
+            <D:propertyupdate xmlns:D="DAV:" xmlns:Z="urn:z">
+              <D:set><D:prop><Z:author>Jim &amp; Roy</Z:author></D:prop></D:set>
+            </D:propertyupdate>"#;
+        let (status, _, body) = call(&dav, "PROPPATCH", "docs", &[], update).await;
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        assert!(body.contains(r#"<author xmlns="urn:z"/>"#));
+        assert!(body.contains("200 OK"));
+        let protected = r#"<D:propertyupdate xmlns:D="DAV:">
+              <D:set><D:prop><D:getetag>x</D:getetag><title xmlns="urn:z">t</title></D:prop></D:set>
+            </D:propertyupdate>"#;
+        let (_, _, body) = call(&dav, "PROPPATCH", "docs", &[], protected).await;
+        assert!(body.contains("403 Forbidden"));
+        assert!(body.contains("424 Failed Dependency"));
+
+        let find = r#"<D:propfind xmlns:D="DAV:"><D:prop><Z:author xmlns:Z="urn:z"/><Z:title xmlns:Z="urn:z"/><D:resourcetype/></D:prop></D:propfind>"#;
+        let (_, _, body) = call(&dav, "PROPFIND", "docs", &[("depth", "0")], find).await;
+        assert!(body.contains(r#"<author xmlns="urn:z">Jim &amp; Roy</author>"#));
+        assert!(body.contains(r#"<title xmlns="urn:z"/>"#));
+        assert!(body.contains("404 Not Found"));
+        assert!(body.contains("<D:resourcetype><D:collection/></D:resourcetype>"));
+        let names = r#"<D:propfind xmlns:D="DAV:"><D:propname/></D:propfind>"#;
+        let (_, _, body) = call(&dav, "PROPFIND", "docs", &[("depth", "0")], names).await;
+        assert!(body.contains(r#"<author xmlns="urn:z"/>"#));
+
+        // copy and move
+        let (status, ..) = call(
+            &dav,
+            "COPY",
+            "docs",
+            &[
+                ("destination", "http://localhost/dav/copy"),
+                ("host", "localhost"),
+            ],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(
+            call(&dav, "GET", "copy/a%20b.txt", &[], "").await.2,
+            "hello!"
+        );
+        let (_, _, body) = call(&dav, "PROPFIND", "copy", &[("depth", "0")], find).await;
+        assert!(body.contains("Jim &amp; Roy"));
+        let (status, ..) = call(
+            &dav,
+            "COPY",
+            "docs",
+            &[("destination", "/dav/copy"), ("overwrite", "F")],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+        let (status, ..) = call(&dav, "MOVE", "copy", &[("destination", "/dav/moved/")], "").await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(
+            call(&dav, "GET", "copy/a%20b.txt", &[], "").await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            call(&dav, "GET", "moved/a%20b.txt", &[], "").await.2,
+            "hello!"
+        );
+        let (status, ..) = call(
+            &dav,
+            "MOVE",
+            "moved",
+            &[("destination", "/other/moved")],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        let (status, ..) = call(
+            &dav,
+            "MOVE",
+            "moved",
+            &[
+                ("destination", "http://other/dav/elsewhere"),
+                ("host", "localhost"),
+            ],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            call(&dav, "GET", "moved/a%20b.txt", &[], "").await.2,
+            "hello!"
+        );
+        let (status, ..) = call(
+            &dav,
+            "MOVE",
+            "docs",
+            &[("destination", "/dav/docs/sub")],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // locks
+        let info = r#"<D:lockinfo xmlns:D="DAV:"><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype><D:owner><D:href>mailto:jim@example.com</D:href></D:owner></D:lockinfo>"#;
+        let (status, headers, body) =
+            call(&dav, "LOCK", "docs", &[("timeout", "Second-60")], info).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<D:timeout>Second-60</D:timeout>"));
+        assert!(body.contains("mailto:jim@example.com"));
+        let token = headers["lock-token"].to_str().unwrap().to_string();
+        let condition = format!("({token})");
+        assert_eq!(
+            call(&dav, "LOCK", "docs/a%20b.txt", &[], info).await.0,
+            StatusCode::LOCKED
+        );
+        assert_eq!(
+            call(&dav, "PUT", "docs/a%20b.txt", &[], "x").await.0,
+            StatusCode::LOCKED
+        );
+        assert_eq!(
+            call(&dav, "PUT", "docs/a%20b.txt", &[("if", &condition)], "x")
+                .await
+                .0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            call(&dav, "DELETE", "docs", &[], "").await.0,
+            StatusCode::LOCKED
+        );
+        let (status, ..) = call(
+            &dav,
+            "MOVE",
+            "moved",
+            &[("destination", "/dav/docs/moved")],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::LOCKED);
+        let (_, _, body) = call(&dav, "PROPFIND", "docs/a%20b.txt", &[("depth", "0")], "").await;
+        assert!(body.contains("<D:lockroot><D:href>/dav/docs/</D:href></D:lockroot>"));
+        let (status, _, body) = call(
+            &dav,
+            "LOCK",
+            "docs",
+            &[("if", &condition), ("timeout", "Second-120")],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Second-120"));
+        assert_eq!(
+            call(
+                &dav,
+                "UNLOCK",
+                "docs",
+                &[("lock-token", "<urn:uuid:x>")],
+                ""
+            )
+            .await
+            .0,
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            call(&dav, "UNLOCK", "docs", &[("lock-token", &token)], "")
+                .await
+                .0,
+            StatusCode::NO_CONTENT
+        );
+        let (status, headers, _) = call(&dav, "LOCK", "new.txt", &[], info).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(headers.contains_key("lock-token"));
+        assert_eq!(call(&dav, "GET", "new.txt", &[], "").await.2, "");
+
+        // deletion
+        assert_eq!(
+            call(&dav, "DELETE", "docs", &[], "").await.0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            call(&dav, "GET", "docs/a%20b.txt", &[], "").await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            call(&dav, "DELETE", "docs", &[], "").await.0,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
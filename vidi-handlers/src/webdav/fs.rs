@@ -0,0 +1,355 @@
+//! The file systems of the `WebDAV` handler.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+    time::SystemTime,
+};
+
+use http_body_util::Full;
+use tokio::io::AsyncWriteExt;
+use tokio_stream::StreamExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+use vidi_core::{Body, Bytes, BytesMut};
+
+/// The metadata of a file or a directory.
+#[derive(Clone, Copy, Debug)]
+pub struct Metadata {
+    /// Is a directory.
+    pub is_dir: bool,
+    /// The size in bytes of a file.
+    pub len: u64,
+    /// The modification time.
+    pub modified: Option<SystemTime>,
+}
+
+/// The storage of the [`WebDav`][super::WebDav] handler.
+///
+/// The paths are the `/` separated relative paths which are validated by the handler, e.g.
+/// `docs/readme.md`, the root is `""`.
+#[vidi_core::async_trait]
+pub trait FileSystem: Send + Sync + 'static {
+    /// Gets the metadata of the file or the directory.
+    async fn metadata(&self, path: &str) -> io::Result<Metadata>;
+
+    /// Lists the names of the entries of the directory.
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<String>>;
+
+    /// Reads the file as a body.
+    async fn read(&self, path: &str) -> io::Result<Body>;
+
+    /// Creates or truncates the file and writes the body, the parent must be a directory.
+    async fn write(&self, path: &str, body: Body) -> io::Result<()>;
+
+    /// Creates the directory, the parent must be a directory.
+    async fn create_dir(&self, path: &str) -> io::Result<()>;
+
+    /// Removes the file.
+    async fn remove_file(&self, path: &str) -> io::Result<()>;
+
+    /// Removes the directory and all of its contents.
+    async fn remove_dir(&self, path: &str) -> io::Result<()>;
+
+    /// Copies the file, the destination is overwritten.
+    async fn copy_file(&self, from: &str, to: &str) -> io::Result<()>;
+
+    /// Renames the file or the directory, the destination must not exist.
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+}
+
+/// The file system of a local directory.
+#[derive(Clone, Debug)]
+pub struct LocalFs {
+    root: PathBuf,
+}
+
+impl LocalFs {
+    /// Creates a file system by the specified directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the path is not a directory.
+    pub fn new(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+
+        if !root.is_dir() {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", root.to_string_lossy()),
+            ))?;
+        }
+
+        Ok(Self { root })
+    }
+
+    /// Resolves the relative path, the traversals are rejected.
+    fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let segments = crate::path::segments(path)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        let mut resolved = self.root.clone();
+        resolved.extend(segments);
+        Ok(resolved)
+    }
+}
+
+#[vidi_core::async_trait]
+impl FileSystem for LocalFs {
+    async fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        let metadata = tokio::fs::metadata(self.resolve(path)?).await?;
+        Ok(Metadata {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(self.resolve(path)?).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            // the names which are not UTF-8 are skipped
+            if let Ok(name) = entry.file_name().into_string() {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+
+    async fn read(&self, path: &str) -> io::Result<Body> {
+        let file = tokio::fs::File::open(self.resolve(path)?).await?;
+        Ok(Body::from_stream(ReaderStream::new(file)))
+    }
+
+    async fn write(&self, path: &str, body: Body) -> io::Result<()> {
+        let mut file = tokio::fs::File::create(self.resolve(path)?).await?;
+        tokio::io::copy(&mut StreamReader::new(body), &mut file).await?;
+        file.flush().await
+    }
+
+    async fn create_dir(&self, path: &str) -> io::Result<()> {
+        tokio::fs::create_dir(self.resolve(path)?).await
+    }
+
+    async fn remove_file(&self, path: &str) -> io::Result<()> {
+        tokio::fs::remove_file(self.resolve(path)?).await
+    }
+
+    async fn remove_dir(&self, path: &str) -> io::Result<()> {
+        tokio::fs::remove_dir_all(self.resolve(path)?).await
+    }
+
+    async fn copy_file(&self, from: &str, to: &str) -> io::Result<()> {
+        tokio::fs::copy(self.resolve(from)?, self.resolve(to)?)
+            .await
+            .map(|_| ())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let to = self.resolve(to)?;
+        if tokio::fs::try_exists(&to).await? {
+            Err(io::Error::from(io::ErrorKind::AlreadyExists))?;
+        }
+        tokio::fs::rename(self.resolve(from)?, to).await
+    }
+}
+
+/// A node of the memory file system.
+#[derive(Debug)]
+enum Node {
+    Dir(SystemTime),
+    File(Bytes, SystemTime),
+}
+
+/// The in-memory file system, e.g. for the tests.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    nodes: Mutex<BTreeMap<String, Node>>,
+}
+
+impl MemoryFs {
+    /// Creates an empty file system.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn nodes(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Node>> {
+        self.nodes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The parent of the path, the root has no parent.
+fn parent(path: &str) -> Option<&str> {
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.rsplit_once('/').map_or("", |(parent, _)| parent))
+}
+
+/// Checks the path is a directory, the root is always a directory.
+fn is_dir(nodes: &BTreeMap<String, Node>, path: &str) -> bool {
+    path.is_empty() || matches!(nodes.get(path), Some(Node::Dir(_)))
+}
+
+/// Checks the parent of the path is a directory.
+fn check_parent(nodes: &BTreeMap<String, Node>, path: &str) -> io::Result<()> {
+    match parent(path) {
+        Some(parent) if is_dir(nodes, parent) => Ok(()),
+        Some(_) => Err(io::ErrorKind::NotFound.into()),
+        None => Err(io::ErrorKind::PermissionDenied.into()),
+    }
+}
+
+/// The paths of the descendants of the directory.
+fn descendants(nodes: &BTreeMap<String, Node>, path: &str) -> Vec<String> {
+    nodes
+        .keys()
+        .filter(|key| super::is_descendant(key, path))
+        .cloned()
+        .collect()
+}
+
+#[vidi_core::async_trait]
+impl FileSystem for MemoryFs {
+    async fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        if path.is_empty() {
+            return Ok(Metadata {
+                is_dir: true,
+                len: 0,
+                modified: None,
+            });
+        }
+        match self.nodes().get(path) {
+            Some(Node::Dir(modified)) => Ok(Metadata {
+                is_dir: true,
+                len: 0,
+                modified: Some(*modified),
+            }),
+            Some(Node::File(data, modified)) => Ok(Metadata {
+                is_dir: false,
+                len: data.len() as u64,
+                modified: Some(*modified),
+            }),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        let nodes = self.nodes();
+        if !is_dir(&nodes, path) {
+            Err(io::ErrorKind::NotFound)?;
+        }
+        Ok(descendants(&nodes, path)
+            .into_iter()
+            .filter_map(|key| {
+                let name = key.rsplit_once('/').map_or(key.as_str(), |(_, name)| name);
+                (parent(&key) == Some(path)).then(|| name.to_string())
+            })
+            .collect())
+    }
+
+    async fn read(&self, path: &str) -> io::Result<Body> {
+        match self.nodes().get(path) {
+            Some(Node::File(data, _)) => Ok(Full::new(data.clone()).into()),
+            Some(Node::Dir(_)) => Err(io::ErrorKind::InvalidInput.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    async fn write(&self, path: &str, mut body: Body) -> io::Result<()> {
+        let mut data = BytesMut::new();
+        while let Some(chunk) = body.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+
+        let mut nodes = self.nodes();
+        check_parent(&nodes, path)?;
+        if is_dir(&nodes, path) {
+            Err(io::ErrorKind::InvalidInput)?;
+        }
+        nodes.insert(
+            path.to_string(),
+            Node::File(data.freeze(), SystemTime::now()),
+        );
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        check_parent(&nodes, path)?;
+        if nodes.contains_key(path) {
+            Err(io::ErrorKind::AlreadyExists)?;
+        }
+        nodes.insert(path.to_string(), Node::Dir(SystemTime::now()));
+        Ok(())
+    }
+
+    async fn remove_file(&self, path: &str) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::File(..)) => {
+                nodes.remove(path);
+                Ok(())
+            }
+            Some(Node::Dir(_)) => Err(io::ErrorKind::InvalidInput.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    async fn remove_dir(&self, path: &str) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::Dir(_)) => {
+                for key in descendants(&nodes, path) {
+                    nodes.remove(&key);
+                }
+                nodes.remove(path);
+                Ok(())
+            }
+            Some(Node::File(..)) => Err(io::ErrorKind::InvalidInput.into()),
+            None if path.is_empty() => Err(io::ErrorKind::PermissionDenied.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    async fn copy_file(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        let data = match nodes.get(from) {
+            Some(Node::File(data, _)) => data.clone(),
+            Some(Node::Dir(_)) => Err(io::ErrorKind::InvalidInput)?,
+            None => Err(io::ErrorKind::NotFound)?,
+        };
+        check_parent(&nodes, to)?;
+        if is_dir(&nodes, to) {
+            Err(io::ErrorKind::InvalidInput)?;
+        }
+        nodes.insert(to.to_string(), Node::File(data, SystemTime::now()));
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        if from.is_empty() || !nodes.contains_key(from) {
+            Err(io::ErrorKind::NotFound)?;
+        }
+        if is_dir(&nodes, to) || nodes.contains_key(to) {
+            Err(io::ErrorKind::AlreadyExists)?;
+        }
+        if super::is_descendant(to, from) {
+            Err(io::ErrorKind::InvalidInput)?;
+        }
+        check_parent(&nodes, to)?;
+
+        for key in descendants(&nodes, from) {
+            if let Some(node) = nodes.remove(&key) {
+                nodes.insert(format!("{to}{}", &key[from.len()..]), node);
+            }
+        }
+        if let Some(node) = nodes.remove(from) {
+            nodes.insert(to.to_string(), node);
+        }
+        Ok(())
+    }
+}
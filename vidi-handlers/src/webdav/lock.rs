@@ -0,0 +1,286 @@
+//! The write locks, RFC 4918 section 6.
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Write,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use super::is_descendant;
+
+/// The default timeout of a lock.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// The maximum timeout of a lock.
+const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// An active lock.
+#[derive(Debug)]
+pub(super) struct Lock {
+    pub(super) token: String,
+    /// The root of the lock.
+    pub(super) path: String,
+    pub(super) exclusive: bool,
+    /// The lock covers the descendants.
+    pub(super) infinite: bool,
+    /// The owner as the inner XML.
+    pub(super) owner: Option<String>,
+    pub(super) timeout: Duration,
+    expires: Instant,
+}
+
+impl Lock {
+    /// Checks the lock covers the path.
+    fn covers(&self, path: &str) -> bool {
+        self.path == path || (self.infinite && is_descendant(path, &self.path))
+    }
+
+    /// Writes the `DAV:activelock` element.
+    pub(super) fn write(&self, xml: &mut String, href: &str) {
+        let _ = write!(
+            xml,
+            "<D:activelock><D:locktype><D:write/></D:locktype><D:lockscope><D:{}/></D:lockscope>\
+             <D:depth>{}</D:depth>{}<D:timeout>Second-{}</D:timeout>\
+             <D:locktoken><D:href>{}</D:href></D:locktoken>\
+             <D:lockroot><D:href>{href}</D:href></D:lockroot></D:activelock>",
+            if self.exclusive {
+                "exclusive"
+            } else {
+                "shared"
+            },
+            if self.infinite { "infinity" } else { "0" },
+            self.owner
+                .as_ref()
+                .map(|owner| format!("<D:owner>{owner}</D:owner>"))
+                .unwrap_or_default(),
+            self.timeout.as_secs(),
+            self.token,
+        );
+    }
+}
+
+/// The active locks.
+#[derive(Debug, Default)]
+pub(super) struct Locks {
+    locks: Vec<Lock>,
+}
+
+impl Locks {
+    /// Removes the expired locks.
+    fn purge(&mut self) {
+        let now = Instant::now();
+        self.locks.retain(|lock| lock.expires > now);
+    }
+
+    /// The locks which cover the path, and the locks of the descendants if the operation is deep.
+    fn applied<'a>(&'a self, path: &'a str, deep: bool) -> impl Iterator<Item = &'a Lock> {
+        self.locks
+            .iter()
+            .filter(move |lock| lock.covers(path) || (deep && is_descendant(&lock.path, path)))
+    }
+
+    /// The locks which cover the path.
+    pub(super) fn discover(&mut self, path: &str) -> Vec<&Lock> {
+        self.purge();
+        self.locks.iter().filter(|lock| lock.covers(path)).collect()
+    }
+
+    /// Checks the submitted tokens of a modification, the token of each exclusive lock and one of
+    /// the tokens of the shared locks are required.
+    pub(super) fn check(&mut self, path: &str, deep: bool, tokens: &[String]) -> bool {
+        self.purge();
+        let submitted = |lock: &&Lock| tokens.contains(&lock.token);
+        let mut shared = self
+            .applied(path, deep)
+            .filter(|lock| !lock.exclusive)
+            .peekable();
+        self.applied(path, deep)
+            .filter(|lock| lock.exclusive)
+            .all(|lock| submitted(&lock))
+            && (shared.peek().is_none() || shared.any(|lock| submitted(&lock)))
+    }
+
+    /// Creates a lock, returns `None` if it conflicts with the active locks.
+    pub(super) fn lock(
+        &mut self,
+        path: &str,
+        exclusive: bool,
+        infinite: bool,
+        owner: Option<String>,
+        timeout: Option<Duration>,
+    ) -> Option<&Lock> {
+        self.purge();
+        if self
+            .applied(path, infinite)
+            .any(|lock| exclusive || lock.exclusive)
+        {
+            return None;
+        }
+
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT).min(MAX_TIMEOUT);
+        self.locks.push(Lock {
+            token: token(),
+            path: path.to_string(),
+            exclusive,
+            infinite,
+            owner,
+            timeout,
+            expires: Instant::now() + timeout,
+        });
+        self.locks.last()
+    }
+
+    /// Refreshes a lock which covers the path by the submitted tokens.
+    pub(super) fn refresh(
+        &mut self,
+        path: &str,
+        tokens: &[String],
+        timeout: Option<Duration>,
+    ) -> Option<&Lock> {
+        self.purge();
+        let lock = self
+            .locks
+            .iter_mut()
+            .find(|lock| lock.covers(path) && tokens.contains(&lock.token))?;
+        lock.timeout = timeout.unwrap_or(DEFAULT_TIMEOUT).min(MAX_TIMEOUT);
+        lock.expires = Instant::now() + lock.timeout;
+        Some(lock)
+    }
+
+    /// Removes the lock which covers the path.
+    pub(super) fn unlock(&mut self, path: &str, token: &str) -> bool {
+        self.purge();
+        let len = self.locks.len();
+        self.locks
+            .retain(|lock| !(lock.token == token && lock.covers(path)));
+        self.locks.len() != len
+    }
+
+    /// Removes the locks of the path and its descendants.
+    pub(super) fn remove(&mut self, path: &str) {
+        self.locks
+            .retain(|lock| lock.path != path && !is_descendant(&lock.path, path));
+    }
+}
+
+/// Parses the `Timeout` header, the first supported value is used.
+pub(super) fn timeout(value: &str) -> Option<Duration> {
+    value.split(',').map(str::trim).find_map(|value| {
+        if value.eq_ignore_ascii_case("Infinite") {
+            Some(MAX_TIMEOUT)
+        } else {
+            value
+                .strip_prefix("Second-")
+                .and_then(|secs| secs.parse().ok())
+                .map(Duration::from_secs)
+        }
+    })
+}
+
+/// Collects the state tokens of the `If` header, RFC 4918 section 10.4.
+///
+/// The conditions are not evaluated, the submitted tokens are checked against the locks.
+pub(super) fn tokens(value: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut rest = value;
+
+    while let Some(i) = rest.find(['(', ')', '<', '[']) {
+        let c = rest.as_bytes()[i];
+        rest = &rest[i + 1..];
+        match c {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b'[' => match rest.find(']') {
+                Some(end) => rest = &rest[end + 1..],
+                None => break,
+            },
+            _ => match rest.find('>') {
+                Some(end) => {
+                    if depth > 0 {
+                        tokens.push(rest[..end].to_string());
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => break,
+            },
+        }
+    }
+
+    tokens
+}
+
+/// Generates a lock token.
+fn token() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    let high = hasher.finish();
+    hasher.write_u64(high);
+    let low = hasher.finish();
+
+    format!(
+        "urn:uuid:{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0x0fff,
+        ((low >> 48) & 0x3fff) | 0x8000,
+        low & 0xffff_ffff_ffff,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks() {
+        let mut locks = Locks::default();
+
+        let token = locks
+            .lock("a", true, true, None, None)
+            .unwrap()
+            .token
+            .clone();
+        assert!(token.starts_with("urn:uuid:"));
+        assert_eq!(token.len(), 45);
+        assert!(locks.lock("a/b", false, false, None, None).is_none());
+        assert!(locks.lock("", true, true, None, None).is_none());
+        assert!(locks.lock("", true, false, None, None).is_some());
+
+        assert!(!locks.check("a/b", false, &[]));
+        assert!(locks.check("a/b", false, std::slice::from_ref(&token)));
+        assert!(locks.check("b", false, &[]));
+
+        let shared = locks
+            .lock("c", false, false, None, None)
+            .unwrap()
+            .token
+            .clone();
+        assert!(locks.lock("c", false, false, None, None).is_some());
+        assert!(locks.lock("c", true, false, None, None).is_none());
+        assert!(!locks.check("c", false, &[]));
+        assert!(locks.check("c", false, std::slice::from_ref(&shared)));
+
+        assert!(!locks.unlock("b", &token));
+        assert!(locks.unlock("a/b", &token));
+        assert!(locks.check("a/b", false, &[]));
+        assert_eq!(locks.discover("c").len(), 2);
+        locks.remove("c");
+        assert!(locks.discover("c").is_empty());
+    }
+
+    #[test]
+    fn headers() {
+        assert_eq!(
+            tokens(r#"<http://x/a> (<urn:uuid:1> ["etag"]) (Not <DAV:no-lock>)"#),
+            ["urn:uuid:1", "DAV:no-lock"]
+        );
+        assert_eq!(timeout("Infinite, Second-4100000000"), Some(MAX_TIMEOUT));
+        assert_eq!(timeout("Second-60"), Some(Duration::from_secs(60)));
+        assert_eq!(timeout("Minute-1"), None);
+    }
+}
@@ -0,0 +1,317 @@
+//! A minimal XML reader and writer of the `WebDAV` bodies.
+//!
+//! The DTDs are rejected, the entities are limited to the predefined and the character ones.
+
+use std::fmt::Write;
+
+/// The namespace of the `WebDAV` elements.
+pub(super) const DAV: &str = "DAV:";
+
+/// The maximum depth of the elements.
+const MAX_DEPTH: usize = 64;
+
+/// The expanded name of an element.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct Name {
+    pub(super) ns: String,
+    pub(super) local: String,
+}
+
+impl Name {
+    pub(super) fn dav(local: &str) -> Self {
+        Self {
+            ns: DAV.to_string(),
+            local: local.to_string(),
+        }
+    }
+
+    /// Writes the element with the inner XML, the `DAV:` namespace is bound to the `D` prefix.
+    pub(super) fn write(&self, xml: &mut String, inner: &str) {
+        if self.ns == DAV {
+            if inner.is_empty() {
+                let _ = write!(xml, "<D:{}/>", self.local);
+            } else {
+                let _ = write!(xml, "<D:{0}>{inner}</D:{0}>", self.local);
+            }
+        } else if inner.is_empty() {
+            let _ = write!(xml, r#"<{} xmlns="{}"/>"#, self.local, escape(&self.ns));
+        } else {
+            let _ = write!(
+                xml,
+                r#"<{0} xmlns="{1}">{inner}</{0}>"#,
+                self.local,
+                escape(&self.ns)
+            );
+        }
+    }
+}
+
+/// A node of an element.
+#[derive(Debug)]
+pub(super) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// An element.
+#[derive(Debug)]
+pub(super) struct Element {
+    pub(super) name: Name,
+    pub(super) children: Vec<Node>,
+}
+
+impl Element {
+    /// Checks the name of the element in the `DAV:` namespace.
+    pub(super) fn is(&self, local: &str) -> bool {
+        self.name.ns == DAV && self.name.local == local
+    }
+
+    /// The child elements.
+    pub(super) fn elements(&self) -> impl Iterator<Item = &Self> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// The first child element in the `DAV:` namespace.
+    pub(super) fn child(&self, local: &str) -> Option<&Self> {
+        self.elements().find(|element| element.is(local))
+    }
+
+    /// Serializes the children, the namespaces are declared on each element.
+    pub(super) fn inner_xml(&self) -> String {
+        let mut xml = String::new();
+        for node in &self.children {
+            match node {
+                Node::Element(element) => element.name.write(&mut xml, &element.inner_xml()),
+                Node::Text(text) => xml.push_str(&escape(text)),
+            }
+        }
+        xml
+    }
+}
+
+/// Escapes the text and the attribute values.
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Decodes the entities of the text.
+fn unescape(text: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let end = rest[start..].find(';')? + start;
+        let entity = &rest[start + 1..end];
+        decoded.push(match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => entity.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        });
+        rest = &rest[end + 1..];
+    }
+    decoded.push_str(rest);
+    Some(decoded)
+}
+
+/// An open element and the count of its namespace declarations.
+struct Open {
+    qname: String,
+    element: Element,
+    scopes: usize,
+}
+
+/// Parses the document, returns `None` if it is malformed.
+pub(super) fn parse(input: &str) -> Option<Element> {
+    // the prefixes and the URIs of the namespaces in scope, `None` is the default namespace
+    let mut scopes: Vec<(Option<String>, String)> = Vec::new();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut root = None;
+    let mut rest = input.trim_start_matches('\u{feff}');
+
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix("<?") {
+            rest = &tail[tail.find("?>")? + 2..];
+        } else if let Some(tail) = rest.strip_prefix("<!--") {
+            rest = &tail[tail.find("-->")? + 3..];
+        } else if let Some(tail) = rest.strip_prefix("<![CDATA[") {
+            let end = tail.find("]]>")?;
+            stack
+                .last_mut()?
+                .element
+                .children
+                .push(Node::Text(tail[..end].to_string()));
+            rest = &tail[end + 3..];
+        } else if rest.starts_with("<!") {
+            // the DTDs are not supported
+            return None;
+        } else if let Some(tail) = rest.strip_prefix("</") {
+            let end = tail.find('>')?;
+            let open = stack.pop()?;
+            if open.qname != tail[..end].trim_end() {
+                return None;
+            }
+            scopes.truncate(scopes.len() - open.scopes);
+            match stack.last_mut() {
+                Some(parent) => parent.element.children.push(Node::Element(open.element)),
+                None => root = Some(open.element),
+            }
+            rest = &tail[end + 1..];
+        } else if let Some(tail) = rest.strip_prefix('<') {
+            if root.is_some() || stack.len() == MAX_DEPTH {
+                return None;
+            }
+            let (open, closed, tail) = start_tag(tail, &mut scopes)?;
+            rest = tail;
+            if closed {
+                scopes.truncate(scopes.len() - open.scopes);
+                match stack.last_mut() {
+                    Some(parent) => parent.element.children.push(Node::Element(open.element)),
+                    None => root = Some(open.element),
+                }
+            } else {
+                stack.push(open);
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = unescape(&rest[..end])?;
+            match stack.last_mut() {
+                Some(open) => open.element.children.push(Node::Text(text)),
+                None if text.trim().is_empty() => {}
+                None => return None,
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    if stack.is_empty() { root } else { None }
+}
+
+/// Parses a start tag after the `<`, returns the element, whether it is self-closing and the
+/// rest of the input.
+fn start_tag<'a>(
+    input: &'a str,
+    scopes: &mut Vec<(Option<String>, String)>,
+) -> Option<(Open, bool, &'a str)> {
+    let end = input.find(|c: char| c.is_whitespace() || c == '/' || c == '>')?;
+    let qname = &input[..end];
+    let mut rest = &input[end..];
+    let mut attrs = Vec::new();
+
+    let closed = loop {
+        rest = rest.trim_start();
+        if let Some(tail) = rest.strip_prefix("/>") {
+            rest = tail;
+            break true;
+        }
+        if let Some(tail) = rest.strip_prefix('>') {
+            rest = tail;
+            break false;
+        }
+        let eq = rest.find('=')?;
+        let name = rest[..eq].trim();
+        let tail = rest[eq + 1..].trim_start();
+        let quote = tail.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let close = tail[1..].find(quote)? + 1;
+        attrs.push((name, unescape(&tail[1..close])?));
+        rest = &tail[close + 1..];
+    };
+
+    let mut count = 0;
+    for (name, value) in attrs {
+        if name == "xmlns" {
+            scopes.push((None, value));
+            count += 1;
+        } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+            scopes.push((Some(prefix.to_string()), value));
+            count += 1;
+        }
+    }
+
+    let (prefix, local) = match qname.split_once(':') {
+        Some((prefix, local)) => (Some(prefix), local),
+        None => (None, qname),
+    };
+    if local.is_empty() {
+        return None;
+    }
+    let ns = match scopes.iter().rev().find(|(p, _)| p.as_deref() == prefix) {
+        Some((_, ns)) => ns.clone(),
+        None if prefix.is_none() => String::new(),
+        None => return None,
+    };
+
+    Some((
+        Open {
+            qname: qname.to_string(),
+            element: Element {
+                name: Name {
+                    ns,
+                    local: local.to_string(),
+                },
+                children: Vec::new(),
+            },
+            scopes: count,
+        },
+        closed,
+        rest,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml() {
+        let root = parse(
+            r#"<?xml version="1.0" encoding="utf-8" ?>
+            <!-- comment -->
+            <D:propertyupdate xmlns:D="DAV:" xmlns:Z="http://ns.example.com/z/">
+              <D:set>
+                <D:prop>
+                  <Z:Author>Jim &amp; <![CDATA[<Roy>]]></Z:Author>
+                  <title xmlns="urn:x"><b a='1'/>&#x41;</title>
+                </D:prop>
+              </D:set>
+            </D:propertyupdate>"#,
+        )
+        .unwrap();
+        assert!(root.is("propertyupdate"));
+        let prop = root.child("set").and_then(|set| set.child("prop")).unwrap();
+        let props = prop.elements().collect::<Vec<_>>();
+        assert_eq!(props[0].name.ns, "http://ns.example.com/z/");
+        assert_eq!(props[0].name.local, "Author");
+        assert_eq!(props[0].inner_xml(), "Jim &amp; &lt;Roy&gt;");
+        assert_eq!(props[1].name.ns, "urn:x");
+        assert_eq!(props[1].inner_xml(), r#"<b xmlns="urn:x"/>A"#);
+
+        assert!(parse("<a><b></a>").is_none());
+        assert!(parse("<x:a/>").is_none());
+        assert!(parse("<a/><b/>").is_none());
+        assert!(parse(r#"<!DOCTYPE a [<!ENTITY x "y">]><a>&x;</a>"#).is_none());
+        assert!(parse(&"<a>".repeat(100)).is_none());
+    }
+}
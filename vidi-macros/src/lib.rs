@@ -23,6 +23,33 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # `MultipartForm`
+//!
+//! Implements `FromMultipart` for a struct, which is extracted by `MultipartForm<T>`.
+//!
+//! The `TempFile` fields are streamed to the temporary files, the other fields are parsed by
+//! `FromStr`. A field can be wrapped in `Option` or `Vec`.
+//!
+//! ## Example
+//!
+//! ```
+//! # use vidi_core::types::TempFile;
+//! # use vidi_macros::MultipartForm;
+//!
+//! #[derive(MultipartForm)]
+//! struct Upload {
+//!     title: String,
+//!     #[multipart(rename = "tag")]
+//!     tags: Vec<String>,
+//!     #[multipart(
+//!         limit = 1048576,
+//!         content_type = "image/png",
+//!         content_type = "image/jpeg"
+//!     )]
+//!     avatar: Option<TempFile>,
+//! }
+//! ```
 
 #![doc(html_logo_url = "https://viz.rs/logo.svg")]
 #![doc(html_favicon_url = "https://viz.rs/logo.svg")]
@@ -34,7 +61,10 @@
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{FnArg, ItemFn, Result, ReturnType};
+use syn::{
+    Data, DeriveInput, Fields, FnArg, GenericArgument, ItemFn, LitInt, LitStr, PathArguments,
+    Result, ReturnType, Type,
+};
 
 /// Transforms `extract-handler` to a Handler instance.
 #[proc_macro_attribute]
@@ -136,3 +166,142 @@ fn generate_handler(input: TokenStream) -> Result<TokenStream> {
 
     Ok(stream.into())
 }
+
+/// Implements `FromMultipart` for a struct with named fields.
+///
+/// The field attributes:
+///
+/// * `#[multipart(rename = "name")]`: the name of the field in the form.
+/// * `#[multipart(limit = 1024)]`: the maximum size in bytes.
+/// * `#[multipart(content_type = "image/*")]`: an allowed media type of the file, repeatable.
+#[proc_macro_derive(MultipartForm, attributes(multipart))]
+pub fn derive_multipart_form(input: TokenStream) -> TokenStream {
+    generate_multipart_form(input).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+fn generate_multipart_form(input: TokenStream) -> Result<TokenStream> {
+    let ast = syn::parse::<DeriveInput>(input)?;
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &ast,
+                    "`MultipartForm` requires a struct with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &ast,
+                "`MultipartForm` requires a struct with named fields",
+            ));
+        }
+    };
+
+    let mut specs = Vec::new();
+    let mut values = Vec::new();
+
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut key = ident.to_string();
+        let mut limit = None;
+        let mut content_types = Vec::new();
+
+        for attr in &field.attrs {
+            if !attr.path().is_ident("multipart") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    key = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("limit") {
+                    limit = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u64>()?);
+                } else if meta.path.is_ident("content_type") {
+                    content_types.push(meta.value()?.parse::<LitStr>()?);
+                } else {
+                    return Err(meta.error("unsupported multipart attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        // `T`, `Option<T>` or `Vec<T>`
+        let (wrapper, inner) = match wrapped(&field.ty) {
+            Some((wrapper, inner)) => (Some(wrapper), inner),
+            None => (None, &field.ty),
+        };
+        let file = last_ident(inner).is_some_and(|ident| ident == "TempFile");
+        if !file && !content_types.is_empty() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`content_type` is only supported for the `TempFile` fields",
+            ));
+        }
+
+        let kind = if file { quote!(file) } else { quote!(text) };
+        let limit = limit.map(|limit| quote!(.limit(#limit)));
+        let content_types =
+            (!content_types.is_empty()).then(|| quote!(.content_types(&[#(#content_types),*])));
+        specs.push(quote! {
+            vidi_core::types::MultipartFieldSpec::#kind(#key)#limit #content_types
+        });
+
+        values.push(match (wrapper, file) {
+            (None, false) => quote!(#ident: fields.text(#key)?),
+            (None, true) => quote!(#ident: fields.file(#key)?),
+            (Some("Option"), false) => quote!(#ident: fields.text_opt(#key)?),
+            (Some("Option"), true) => quote!(#ident: fields.file_opt(#key)?),
+            (Some(_), false) => quote!(#ident: fields.texts(#key)?),
+            (Some(_), true) => quote!(#ident: fields.files(#key)),
+        });
+    }
+
+    let stream = quote! {
+        impl #impl_generics vidi_core::types::FromMultipart for #name #ty_generics #where_clause {
+            const FIELDS: &'static [vidi_core::types::MultipartFieldSpec] = &[#(#specs),*];
+
+            fn from_fields(
+                fields: &mut vidi_core::types::MultipartFields,
+            ) -> ::core::result::Result<Self, vidi_core::types::MultipartFormError> {
+                ::core::result::Result::Ok(Self {
+                    #(#values),*
+                })
+            }
+        }
+    };
+
+    Ok(stream.into())
+}
+
+/// Gets the wrapper and the inner type of `Option<T>` or `Vec<T>`.
+fn wrapped(ty: &Type) -> Option<(&'static str, &Type)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let seg = path.path.segments.last()?;
+    let wrapper = if seg.ident == "Option" {
+        "Option"
+    } else if seg.ident == "Vec" {
+        "Vec"
+    } else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some((wrapper, inner)),
+        _ => None,
+    }
+}
+
+fn last_ident(ty: &Type) -> Option<&syn::Ident> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|seg| &seg.ident),
+        _ => None,
+    }
+}
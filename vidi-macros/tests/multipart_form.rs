@@ -0,0 +1,153 @@
+//! `MultipartForm` test cases
+
+use std::fmt::Write;
+
+use http_body_util::Full;
+use vidi_core::{
+    Body, FromRequest, IntoResponse, Request, StatusCode,
+    types::{FromMultipart, MultipartForm, TempFile},
+};
+use vidi_macros::MultipartForm;
+
+#[derive(Debug, MultipartForm)]
+struct Upload {
+    title: String,
+    count: Option<u32>,
+    #[multipart(rename = "tag")]
+    tags: Vec<String>,
+    #[multipart(limit = 8, content_type = "text/*")]
+    note: Option<TempFile>,
+    attachments: Vec<TempFile>,
+}
+
+fn request(parts: &[(&str, Option<(&str, &str)>, &str)]) -> Request {
+    let mut body = String::new();
+    for (name, file, value) in parts {
+        body.push_str("--boundary\r\n");
+        match file {
+            Some((filename, content_type)) => write!(
+                body,
+                "Content-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\n"
+            ),
+            None => write!(
+                body,
+                "Content-Disposition: form-data; name=\"{name}\"\r\n\r\n"
+            ),
+        }
+        .unwrap();
+        body.push_str(value);
+        body.push_str("\r\n");
+    }
+    body.push_str("--boundary--\r\n");
+
+    let mut req = Request::new(Body::from(Full::from(body)));
+    req.headers_mut().insert(
+        "content-type",
+        "multipart/form-data; boundary=boundary".parse().unwrap(),
+    );
+    req
+}
+
+async fn extract(
+    parts: &[(&str, Option<(&str, &str)>, &str)],
+) -> Result<Upload, (StatusCode, String)> {
+    MultipartForm::<Upload>::extract(&mut request(parts))
+        .await
+        .map(MultipartForm::into_inner)
+        .map_err(|e| {
+            let message = e.to_string();
+            (e.into_response().status(), message)
+        })
+}
+
+#[tokio::test]
+async fn multipart_form() {
+    assert_eq!(Upload::FIELDS.len(), 5);
+    assert_eq!(Upload::FIELDS[2].name, "tag");
+    assert_eq!(Upload::FIELDS[3].limit, Some(8));
+    assert!(Upload::FIELDS[3].file);
+
+    let upload = extract(&[
+        ("title", None, "vidi"),
+        ("tag", None, "a"),
+        ("unknown", None, "ignored"),
+        ("tag", None, "b"),
+        ("note", Some(("note.txt", "text/plain")), "hello"),
+        ("attachments", Some(("", "application/octet-stream")), ""),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(upload.title, "vidi");
+    assert_eq!(upload.count, None);
+    assert_eq!(upload.tags, ["a", "b"]);
+    assert!(upload.attachments.is_empty());
+
+    let note = upload.note.unwrap();
+    let path = note.path().to_path_buf();
+    assert_eq!(note.file_name(), Some("note.txt"));
+    assert_eq!(note.content_type(), Some(&mime::TEXT_PLAIN));
+    assert_eq!(note.size(), 5);
+    assert_eq!(
+        note.sha256_hex(),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    drop(note);
+    assert!(!path.exists());
+
+    assert_eq!(
+        extract(&[("count", None, "1")]).await.unwrap_err(),
+        (
+            StatusCode::BAD_REQUEST,
+            "field `title` is missing".to_string()
+        )
+    );
+    assert_eq!(
+        extract(&[("title", None, "a"), ("title", None, "b")])
+            .await
+            .unwrap_err(),
+        (
+            StatusCode::BAD_REQUEST,
+            "field `title` is duplicated".to_string()
+        )
+    );
+    assert_eq!(
+        extract(&[("title", None, "vidi"), ("count", None, "x")])
+            .await
+            .unwrap_err(),
+        (
+            StatusCode::BAD_REQUEST,
+            "field `count` is invalid, invalid digit found in string".to_string()
+        )
+    );
+    assert_eq!(
+        extract(&[
+            ("title", None, "vidi"),
+            ("note", Some(("note.txt", "text/plain")), "hello world")
+        ])
+        .await
+        .unwrap_err(),
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "field `note` is too large, the limit is 8 bytes".to_string()
+        )
+    );
+    assert_eq!(
+        extract(&[
+            ("title", None, "vidi"),
+            ("note", Some(("note.png", "image/png")), "png")
+        ])
+        .await
+        .unwrap_err(),
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "field `note` has an unsupported media type".to_string()
+        )
+    );
+}
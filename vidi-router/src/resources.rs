@@ -232,14 +232,14 @@ impl IntoIterator for Resources {
                             if self.singular {
                                 String::new()
                             } else {
-                                format!(":{}_id", &self.name)
+                                format!(":{}_id", self.name)
                             }
                         }
                         Kind::Edit => {
                             if self.singular {
                                 "edit".to_string()
                             } else {
-                                format!(":{}_id/edit", &self.name)
+                                format!(":{}_id/edit", self.name)
                             }
                         }
                         Kind::Custom(path) => path,
@@ -0,0 +1,187 @@
+use vidi::{
+    Error, HandlerExt, Request, RequestExt, Result, Router, StatusCode,
+    header::{HeaderName, WWW_AUTHENTICATE},
+    middleware::{
+        auth::{self, ApiKey, Basic, Bearer, Identity},
+        cookie,
+    },
+};
+use vidi_test::TestServer;
+
+#[derive(Clone, Debug)]
+struct User(String);
+
+async fn me(mut req: Request) -> Result<String> {
+    let Identity(user) = req.extract::<Identity<User>>().await?;
+    Ok(user.0)
+}
+
+async fn home(mut req: Request) -> Result<String> {
+    Ok(match req.extract::<Option<Identity<User>>>().await? {
+        Some(Identity(user)) => format!("hello {}", user.0),
+        None => "hello guest".to_string(),
+    })
+}
+
+#[tokio::test]
+async fn auth_basic_and_bearer() -> Result<()> {
+    let router = Router::new().get("/me", me).with(auth::Config::new((
+        Basic::new("vidi", |username: String, password: String| async move {
+            (password == "rs").then_some(User(username))
+        }),
+        Bearer::new("vidi", |token: String| async move {
+            (token == "secret").then(|| User("bearer".to_string()))
+        }),
+    )));
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client.get("/me").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers()[WWW_AUTHENTICATE],
+        r#"Basic realm="vidi", charset="UTF-8", Bearer realm="vidi""#
+    );
+
+    let resp = client
+        .get("/me")
+        .basic_auth("vidi", Some("rs"))
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "vidi");
+
+    let resp = client
+        .get("/me")
+        .basic_auth("vidi", Some("go"))
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers()[WWW_AUTHENTICATE],
+        r#"Basic realm="vidi", charset="UTF-8""#
+    );
+
+    let resp = client
+        .get("/me")
+        .bearer_auth("secret")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "bearer");
+
+    let resp = client
+        .get("/me")
+        .bearer_auth("expired")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers()[WWW_AUTHENTICATE],
+        r#"Bearer realm="vidi", error="invalid_token""#
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn auth_api_key() -> Result<()> {
+    async fn verify(key: String) -> Option<User> {
+        (key == "key").then(|| User("api".to_string()))
+    }
+
+    let router = Router::new()
+        .get(
+            "/header",
+            me.with(auth::Config::new(ApiKey::header(
+                HeaderName::from_static("x-api-key"),
+                verify,
+            ))),
+        )
+        .get(
+            "/query",
+            me.with(auth::Config::new(ApiKey::query("api_key", verify))),
+        )
+        .get(
+            "/cookie",
+            me.with(auth::Config::new(ApiKey::cookie("api_key", verify))),
+        )
+        .with(cookie::Config::default());
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client
+        .get("/header")
+        .header("x-api-key", "key")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "api");
+
+    let resp = client
+        .get("/query?page=1&api_key=key")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "api");
+
+    let resp = client
+        .get("/cookie")
+        .header("cookie", "api_key=key")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "api");
+
+    let resp = client
+        .get("/query?api_key=wrong")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert!(resp.headers().get(WWW_AUTHENTICATE).is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn auth_optional() -> Result<()> {
+    let router = Router::new().get("/", home).get("/me", me).with(
+        auth::Config::new(Bearer::new("vidi", |token: String| async move {
+            (token == "secret").then(|| User("vidi".to_string()))
+        }))
+        .optional(true),
+    );
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client.get("/").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "hello guest");
+
+    let resp = client
+        .get("/")
+        .bearer_auth("secret")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "hello vidi");
+
+    // invalid credentials are still rejected
+    let resp = client
+        .get("/")
+        .bearer_auth("expired")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // the identity is required by the handler
+    let resp = client.get("/me").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers()[WWW_AUTHENTICATE], r#"Bearer realm="vidi""#);
+
+    Ok(())
+}
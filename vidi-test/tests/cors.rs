@@ -0,0 +1,278 @@
+use vidi::{
+    Error, Method, Request, Response, ResponseExt, Result, Router, StatusCode,
+    header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+        ACCESS_CONTROL_REQUEST_METHOD, HeaderValue, ORIGIN, VARY,
+    },
+    middleware::cors::{
+        self, ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK, ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK,
+    },
+};
+use vidi_test::TestServer;
+
+fn vary(resp: &reqwest::Response) -> Vec<&str> {
+    resp.headers()
+        .get_all(VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect()
+}
+
+#[tokio::test]
+async fn cors_origins() -> Result<()> {
+    let router = Router::new()
+        .get("/", |_: Request| async { Ok("cors") })
+        .options("/", |_: Request| async { Ok("") })
+        .with(
+            cors::Config::new()
+                .allow_origins(["https://vidi.rs", "https://*.viz.rs"])
+                .credentials(true)
+                .private_network(true)
+                .build()?,
+        );
+
+    let client = TestServer::new(router).await?;
+
+    // without the `Origin` header
+    let resp = client.get("/").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(vary(&resp), ["origin"]);
+    assert!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+    let resp = client
+        .get("/")
+        .header(ORIGIN, "https://vidi.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(vary(&resp), ["origin"]);
+    assert_eq!(
+        resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://vidi.rs"
+    );
+    assert_eq!(
+        resp.headers()
+            .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .unwrap(),
+        "true"
+    );
+
+    for origin in ["https://docs.viz.rs", "https://a.b.viz.rs"] {
+        let resp = client
+            .get("/")
+            .header(ORIGIN, origin)
+            .send()
+            .await
+            .map_err(Error::boxed)?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            origin
+        );
+    }
+
+    for origin in [
+        "https://viz.rs",
+        "https://.viz.rs",
+        "https://evil.rs/.viz.rs",
+        "http://docs.viz.rs",
+        "https://docs.viz.rs.evil.rs",
+    ] {
+        let resp = client
+            .get("/")
+            .header(ORIGIN, origin)
+            .send()
+            .await
+            .map_err(Error::boxed)?;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{origin}");
+    }
+
+    // preflight with private network access
+    let resp = client
+        .options("/")
+        .header(ORIGIN, "https://docs.viz.rs")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .header(ACCESS_CONTROL_REQUEST_PRIVATE_NETWORK, "true")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(resp.headers().get(ACCESS_CONTROL_MAX_AGE).unwrap(), "86400");
+    assert_eq!(
+        resp.headers()
+            .get(ACCESS_CONTROL_ALLOW_PRIVATE_NETWORK)
+            .unwrap(),
+        "true"
+    );
+    assert!(vary(&resp).contains(&"origin"));
+    assert!(vary(&resp).contains(&"access-control-request-method"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cors_vary_is_merged() -> Result<()> {
+    let router = Router::new()
+        .get("/", |_: Request| async {
+            let mut resp = Response::text("cors");
+            resp.headers_mut()
+                .insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+            Ok(resp)
+        })
+        .get("/any", |_: Request| async {
+            let mut resp = Response::text("cors");
+            resp.headers_mut()
+                .insert(VARY, HeaderValue::from_static("*"));
+            Ok(resp)
+        })
+        .with(cors::Config::new().allow_origins(["*"]).build()?);
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client
+        .get("/")
+        .header(ORIGIN, "https://vidi.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(vary(&resp), ["Accept-Encoding", "origin"]);
+    assert_eq!(
+        resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://vidi.rs"
+    );
+
+    let resp = client
+        .get("/any")
+        .header(ORIGIN, "https://vidi.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(vary(&resp), ["*"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cors_route_policies() -> Result<()> {
+    let router = Router::new()
+        .get("/private", |_: Request| async { Ok("private") })
+        .get("/public/*", |_: Request| async { Ok("public") })
+        .with(
+            cors::Config::new()
+                .allow_origins(["https://vidi.rs"])
+                .allow_methods([Method::GET, Method::POST])
+                .credentials(true)
+                .route("/public/*", |config| {
+                    config.allow_origins(["*"]).credentials(false)
+                })
+                .build()?,
+        );
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client
+        .get("/private")
+        .header(ORIGIN, "https://viz.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = client
+        .get("/public/index.html")
+        .header(ORIGIN, "https://viz.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://viz.rs"
+    );
+    assert!(
+        resp.headers()
+            .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cors_rejects_invalid_credentials() {
+    // no origin is allowed by default
+    assert!(matches!(
+        cors::Config::new().credentials(true).build(),
+        Err(cors::ConfigError::CredentialsWithoutOrigins)
+    ));
+
+    let config = cors::Config::new().allow_origins(["*"]).credentials(true);
+    assert!(matches!(
+        config.build(),
+        Err(cors::ConfigError::WildcardWithCredentials)
+    ));
+
+    let config = cors::Config::new()
+        .allow_origins(["https://vidi.rs"])
+        .credentials(true)
+        .route("/public/*", |config| config.allow_origins(["*"]));
+    assert!(config.build().is_err());
+}
+
+#[tokio::test]
+async fn cors_credentials() -> Result<()> {
+    let router = Router::new()
+        .get("/", |_: Request| async { Ok("cors") })
+        .with(
+            cors::Config::new()
+                .allow_origins(["https://vidi.rs"])
+                .credentials(true)
+                .build()?,
+        );
+
+    let client = TestServer::new(router).await?;
+
+    // a credentialed request from an unlisted origin is refused
+    let resp = client
+        .get("/")
+        .header(ORIGIN, "https://viz.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    assert!(
+        resp.headers()
+            .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cors_default_denies() -> Result<()> {
+    let router = Router::new()
+        .get("/", |_: Request| async { Ok("cors") })
+        .with(cors::Config::default().build()?);
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client
+        .get("/")
+        .header(ORIGIN, "https://viz.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // same-origin requests are not affected
+    let resp = client.get("/").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    Ok(())
+}
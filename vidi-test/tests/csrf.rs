@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use vidi::{
+    Error, Method, Request, RequestExt, Response, ResponseExt, Result, Router, StatusCode,
+    header::{COOKIE, ORIGIN, REFERER, SET_COOKIE},
+    middleware::{
+        cookie,
+        csrf::{self, CsrfToken},
+        forwarded,
+        helper::CookieOptions,
+    },
+    types::TrustedProxies,
+};
+use vidi_test::TestServer;
+
+type Config =
+    csrf::Config<fn() -> Result<Vec<u8>>, fn(&[u8], Vec<u8>) -> Vec<u8>, fn(&[u8], String) -> bool>;
+
+fn config(store: csrf::Store) -> Config {
+    csrf::Config::new(
+        store,
+        [Method::GET, Method::HEAD, Method::OPTIONS, Method::TRACE].into(),
+        CookieOptions::new("_csrf"),
+        csrf::secret,
+        csrf::generate,
+        csrf::verify,
+    )
+}
+
+#[tokio::test]
+async fn csrf_signed_double_submit() -> Result<()> {
+    let router = Router::new()
+        .get("/", |mut req: Request| async move {
+            Ok(req.extract::<CsrfToken>().await?.0)
+        })
+        .post("/", |mut req: Request| async move {
+            let form = req.form::<HashMap<String, String>>().await?;
+            Ok(Response::json(form))
+        })
+        .post("/multipart", |mut req: Request| async move {
+            let text = req.text().await?;
+            Ok(text.contains("vidi").to_string())
+        })
+        .with(config(csrf::Store::SignedCookie).field("_csrf"))
+        .with(cookie::Config::default());
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client.get("/").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let cookie = resp
+        .headers()
+        .get(SET_COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(ToString::to_string)
+        .unwrap();
+    let token = resp.text().await.map_err(Error::boxed)?;
+
+    // the signed cookie is not the raw token
+    assert_ne!(cookie, format!("_csrf={token}"));
+
+    // without token
+    let resp = client
+        .post("/")
+        .header(COOKIE, &cookie)
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // via header
+    let resp = client
+        .post("/")
+        .header(COOKIE, &cookie)
+        .header("x-csrf-token", &token)
+        .form(&[("name", "vidi")])
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // via form field, the body is restored for the handler
+    let resp = client
+        .post("/")
+        .header(COOKIE, &cookie)
+        .form(&[("name", "vidi"), ("_csrf", &token)])
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let form = resp
+        .json::<HashMap<String, String>>()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(form["name"], "vidi");
+    assert_eq!(form["_csrf"], token);
+
+    // via multipart field
+    let form = vidi_test::multipart::Form::new()
+        .text("name", "vidi")
+        .text("_csrf", token.clone());
+    let resp = client
+        .post("/multipart")
+        .header(COOKIE, &cookie)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "true");
+
+    // tampered cookie
+    let (name, value) = cookie.split_once('=').unwrap();
+    let first = if value.starts_with('A') { 'B' } else { 'A' };
+    let resp = client
+        .post("/")
+        .header(COOKIE, format!("{name}={first}{}", &value[1..]))
+        .header("x-csrf-token", &token)
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn csrf_origin() -> Result<()> {
+    // a cloned configuration can still be built on, the clone is left untouched
+    let shared = config(csrf::Store::Cookie);
+    let config = shared
+        .clone()
+        .mode(csrf::Mode::Origin)
+        .trusted_origins(["https://vidi.rs"]);
+    assert!(format!("{shared:?}").contains("mode: Token"));
+
+    let router = Router::new()
+        .post("/", |_: Request| async { Ok("created") })
+        .with(config)
+        .with(cookie::Config::default());
+
+    let client = TestServer::new(router).await?;
+    let host = format!("http://{}", client.addr());
+
+    for (name, value, status) in [
+        (ORIGIN, host.as_str(), StatusCode::OK),
+        (
+            ORIGIN,
+            &format!("https://{}", client.addr()),
+            StatusCode::FORBIDDEN,
+        ),
+        (ORIGIN, "https://vidi.rs", StatusCode::OK),
+        (ORIGIN, "https://evil.rs", StatusCode::FORBIDDEN),
+        (ORIGIN, "null", StatusCode::FORBIDDEN),
+        (REFERER, &format!("{host}/form?q=1"), StatusCode::OK),
+        (REFERER, "https://evil.rs/form", StatusCode::FORBIDDEN),
+    ] {
+        let resp = client
+            .post("/")
+            .header(name.clone(), value)
+            .send()
+            .await
+            .map_err(Error::boxed)?;
+        assert_eq!(resp.status(), status, "{name}: {value}");
+        // no token is issued
+        assert!(resp.headers().get(SET_COOKIE).is_none());
+    }
+
+    let resp = client.post("/").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn csrf_origin_forwarded_scheme() -> Result<()> {
+    let router = Router::new()
+        .post("/", |_: Request| async { Ok("created") })
+        .with(config(csrf::Store::Cookie).mode(csrf::Mode::Origin))
+        .with(cookie::Config::default())
+        .with(forwarded::Config::new(
+            TrustedProxies::new().cidrs(["127.0.0.1".parse().unwrap()]),
+        ));
+
+    let client = TestServer::new(router).await?;
+
+    // the site is served over HTTPS by the proxy
+    for (origin, status) in [
+        ("https://vidi.rs", StatusCode::OK),
+        ("http://vidi.rs", StatusCode::FORBIDDEN),
+    ] {
+        let resp = client
+            .post("/")
+            .header("x-forwarded-proto", "https")
+            .header("x-forwarded-host", "vidi.rs")
+            .header(ORIGIN, origin)
+            .send()
+            .await
+            .map_err(Error::boxed)?;
+        assert_eq!(resp.status(), status, "{origin}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn csrf_fetch_metadata() -> Result<()> {
+    let router = Router::new()
+        .post("/", |_: Request| async { Ok("created") })
+        .with(config(csrf::Store::Cookie).mode(csrf::Mode::FetchMetadata { same_site: false }))
+        .with(cookie::Config::default());
+
+    let client = TestServer::new(router).await?;
+
+    for (site, status) in [
+        ("same-origin", StatusCode::OK),
+        ("none", StatusCode::OK),
+        ("same-site", StatusCode::FORBIDDEN),
+        ("cross-site", StatusCode::FORBIDDEN),
+    ] {
+        let resp = client
+            .post("/")
+            .header("sec-fetch-site", site)
+            .send()
+            .await
+            .map_err(Error::boxed)?;
+        assert_eq!(resp.status(), status, "{site}");
+    }
+
+    // falls back to the origin check
+    let resp = client
+        .post("/")
+        .header(ORIGIN, format!("http://{}", client.addr()))
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = client
+        .post("/")
+        .header(ORIGIN, "https://evil.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // safe methods are always allowed
+    let resp = client
+        .get("/")
+        .header("sec-fetch-site", "cross-site")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_ne!(resp.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use vidi::{
+    Error, Request, RequestExt, Result, Router, middleware::forwarded, types::TrustedProxies,
+};
+use vidi_test::TestServer;
+
+#[tokio::test]
+async fn forwarded() -> Result<()> {
+    let router = |proxies: TrustedProxies| {
+        Router::new()
+            .get("/", |req: Request| async move {
+                Ok(format!(
+                    "{} {} {}",
+                    req.realip().map(|ip| ip.0.to_string()).unwrap_or_default(),
+                    req.schema().map(ToString::to_string).unwrap_or_default(),
+                    req.uri().host().unwrap_or_default(),
+                ))
+            })
+            .with(forwarded::Config::new(proxies))
+    };
+
+    let client = TestServer::new(router(
+        TrustedProxies::new().cidrs(["127.0.0.1".parse().unwrap()]),
+    ))
+    .await?;
+
+    let resp = client
+        .get("/")
+        .header("x-forwarded-for", "6.6.6.6, 1.1.1.1")
+        .header("x-forwarded-proto", "https")
+        .header("x-forwarded-host", "vidi.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(
+        resp.text().await.map_err(Error::boxed)?,
+        "1.1.1.1 https vidi.rs"
+    );
+
+    let resp = client
+        .get("/")
+        .header("forwarded", "for=1.1.1.1;proto=https;host=vidi.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(
+        resp.text().await.map_err(Error::boxed)?,
+        "1.1.1.1 https vidi.rs"
+    );
+
+    // the peer is not trusted
+    let client = TestServer::new(router(TrustedProxies::new())).await?;
+
+    let resp = client
+        .get("/")
+        .header("x-forwarded-for", "1.1.1.1")
+        .header("x-forwarded-proto", "https")
+        .header("x-forwarded-host", "vidi.rs")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.text().await.map_err(Error::boxed)?, "127.0.0.1  ");
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::sync::{Arc, Mutex};
+
+use vidi::{
+    Error, IntoResponse, Request, RequestExt, Response, ResponseExt, Result, Router, StatusCode,
+    header::{
+        CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY, CONTENT_TYPE, HeaderValue,
+        REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+    },
+    middleware::secure_headers::{
+        self, CROSS_ORIGIN_OPENER_POLICY, Csp, CspNonce, CspReport, FrameOptions, Hsts,
+        ReportHandler, Source,
+    },
+};
+use vidi_test::TestServer;
+
+#[tokio::test]
+async fn secure_headers_defaults() -> Result<()> {
+    let router = Router::new()
+        .get("/", |_: Request| async { Ok("secure") })
+        .get("/frame", |_: Request| async {
+            let mut resp = Response::text("frame");
+            resp.headers_mut()
+                .insert(X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"));
+            Ok(resp)
+        })
+        .get("/error", |_: Request| async {
+            Err::<Response, _>(StatusCode::BAD_REQUEST.into_error())
+        })
+        .with(secure_headers::Config::new());
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client.get("/").send().await.map_err(Error::boxed)?;
+    let headers = resp.headers();
+    assert_eq!(
+        headers[STRICT_TRANSPORT_SECURITY],
+        "max-age=31536000; includeSubDomains"
+    );
+    assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+    assert_eq!(headers[X_FRAME_OPTIONS], "DENY");
+    assert_eq!(headers[REFERRER_POLICY], "strict-origin-when-cross-origin");
+    assert_eq!(headers[CROSS_ORIGIN_OPENER_POLICY], "same-origin");
+    assert!(headers.get(CONTENT_SECURITY_POLICY).is_none());
+
+    // the headers set by the handler are kept
+    let resp = client.get("/frame").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.headers()[X_FRAME_OPTIONS], "SAMEORIGIN");
+
+    // the error responses are secured too
+    let resp = client.get("/error").send().await.map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(resp.headers()[X_CONTENT_TYPE_OPTIONS], "nosniff");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn secure_headers_csp_nonce() -> Result<()> {
+    let router = Router::new()
+        .get("/", |mut req: Request| async move {
+            Ok(req.extract::<CspNonce>().await?.0)
+        })
+        .with(
+            secure_headers::Config::empty()
+                .hsts(Hsts::new(std::time::Duration::from_secs(60)).preload(true))
+                .frame_options(Some(FrameOptions::SameOrigin))
+                .csp(
+                    Csp::new()
+                        .default_src([Source::SelfOrigin])
+                        .script_src([Source::Nonce, Source::StrictDynamic])
+                        .img_src([Source::SelfOrigin, Source::Scheme("data")])
+                        .upgrade_insecure_requests(true),
+                )
+                .csp_report_only(
+                    Csp::new()
+                        .default_src([Source::None])
+                        .report_uri("/csp-reports"),
+                ),
+        );
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client.get("/").send().await.map_err(Error::boxed)?;
+    let headers = resp.headers().clone();
+    let nonce = resp.text().await.map_err(Error::boxed)?;
+    assert!(!nonce.is_empty());
+
+    assert_eq!(headers[STRICT_TRANSPORT_SECURITY], "max-age=60; preload");
+    assert_eq!(headers[X_FRAME_OPTIONS], "SAMEORIGIN");
+    assert!(headers.get(X_CONTENT_TYPE_OPTIONS).is_none());
+    assert_eq!(
+        headers[CONTENT_SECURITY_POLICY],
+        format!(
+            "default-src 'self'; script-src 'nonce-{nonce}' 'strict-dynamic'; img-src 'self' data:; upgrade-insecure-requests"
+        )
+    );
+    assert_eq!(
+        headers[CONTENT_SECURITY_POLICY_REPORT_ONLY],
+        "default-src 'none'; report-uri /csp-reports"
+    );
+
+    // a new nonce for each request
+    let resp = client.get("/").send().await.map_err(Error::boxed)?;
+    assert_ne!(resp.text().await.map_err(Error::boxed)?, nonce);
+
+    Ok(())
+}
+
+#[test]
+fn secure_headers_csp_sources() {
+    let csp = Csp::new()
+        .img_src([
+            Source::Host("https://*.vidi.rs".to_string()),
+            Source::Hash("'sha256-abc+/='".to_string()),
+        ])
+        .report_to("csp");
+    assert_eq!(
+        csp.render(None),
+        "img-src https://*.vidi.rs 'sha256-abc+/='; report-to csp"
+    );
+}
+
+#[test]
+#[should_panic(expected = "invalid content security policy source")]
+fn secure_headers_csp_invalid_host() {
+    let _ = Csp::new().script_src([Source::Host("vidi.rs; script-src *".to_string())]);
+}
+
+#[test]
+#[should_panic(expected = "invalid content security policy source")]
+fn secure_headers_csp_invalid_hash() {
+    let _ = Csp::new().script_src([Source::Hash("sha256-abc 'unsafe-inline'".to_string())]);
+}
+
+#[test]
+#[should_panic(expected = "invalid content security policy report uri")]
+fn secure_headers_csp_invalid_report_uri() {
+    let _ = Csp::new().report_uri("/csp\r\nx-injected: 1");
+}
+
+#[tokio::test]
+async fn secure_headers_csp_reports() -> Result<()> {
+    let reports = Arc::new(Mutex::new(Vec::<CspReport>::new()));
+
+    let router = Router::new().post(
+        "/csp-reports",
+        ReportHandler::new({
+            let reports = reports.clone();
+            move |report| reports.lock().unwrap().push(report)
+        }),
+    );
+
+    let client = TestServer::new(router).await?;
+
+    let resp = client
+        .post("/csp-reports")
+        .header(CONTENT_TYPE, "application/csp-report")
+        .body(
+            r#"{"csp-report":{"document-uri":"https://vidi.rs/","blocked-uri":"inline","violated-directive":"script-src","original-policy":"script-src 'self'","line-number":7}}"#,
+        )
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = client
+        .post("/csp-reports")
+        .header(CONTENT_TYPE, "application/reports+json")
+        .body(
+            r#"[{"type":"csp-violation","age":1,"url":"https://vidi.rs/","body":{"documentURL":"https://vidi.rs/","blockedURL":"eval","effectiveDirective":"script-src","originalPolicy":"script-src 'self'","disposition":"report","statusCode":200}},{"type":"deprecation","body":{}}]"#,
+        )
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = client
+        .post("/csp-reports")
+        .header(CONTENT_TYPE, "text/plain")
+        .body("{}")
+        .send()
+        .await
+        .map_err(Error::boxed)?;
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].document_url, "https://vidi.rs/");
+    assert_eq!(reports[0].blocked_url.as_deref(), Some("inline"));
+    assert_eq!(reports[0].effective_directive, "script-src");
+    assert_eq!(reports[0].line_number, Some(7));
+    assert_eq!(reports[1].blocked_url.as_deref(), Some("eval"));
+    assert_eq!(reports[1].disposition.as_deref(), Some("report"));
+    assert_eq!(reports[1].status_code, Some(200));
+
+    Ok(())
+}
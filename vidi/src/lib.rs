@@ -4,7 +4,7 @@
 //!
 //! # Features
 //!
-//! * **Safety** `#![forbid(unsafe_code)]`
+//! * **Safety** `#![deny(unsafe_code)]`, except taking over the sockets of the socket activation
 //!
 //! * Lightweight
 //!
@@ -538,11 +538,20 @@ mod listener;
 pub use listener::Listener;
 
 mod server;
-pub use server::{Server, serve};
+pub use server::{Server, ServerConfig, ServerStats, serve};
 
 #[cfg(any(feature = "native-tls", feature = "rustls"))]
 pub use server::tls;
 
+#[cfg(feature = "http3")]
+pub use server::http3;
+
+#[cfg(feature = "proxy-protocol")]
+pub use server::proxy_protocol;
+
+#[cfg(all(target_os = "linux", feature = "socket-activation"))]
+pub use server::activation;
+
 pub use vidi_core::*;
 pub use vidi_router::*;
 
@@ -554,4 +563,4 @@ pub use vidi_handlers as handlers;
 #[cfg(feature = "macros")]
 #[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
 #[doc(inline)]
-pub use vidi_macros::handler;
+pub use vidi_macros::{MultipartForm, handler};
@@ -17,4 +17,10 @@ pub trait Listener {
     /// An error will return if got the socket address of the local half of this connection is
     /// failed.
     fn local_addr(&self) -> std::io::Result<Self::Addr>;
+
+    /// Returns the data of an accepted connection, which are added into the extensions of
+    /// each request on it, e.g. the TLS server name.
+    fn extensions(&self, _io: &Self::Io) -> http::Extensions {
+        http::Extensions::new()
+    }
 }
@@ -1,12 +1,17 @@
 use std::{convert::Infallible, future::Future, pin::Pin, sync::Arc};
 
-use crate::{Body, Handler, Incoming, IntoResponse, Method, Request, Response, StatusCode, Tree};
+use crate::{
+    Body, Handler, Incoming, IntoResponse, Method, Request, Response, StatusCode, Tree,
+    header::{ALT_SVC, HeaderValue},
+};
 
 /// Handles the HTTP [`Request`] and returns the HTTP [`Response`].
 #[derive(Debug)]
 pub struct Responder<A> {
     tree: Arc<Tree>,
     remote_addr: Option<A>,
+    extensions: http::Extensions,
+    alt_svc: Option<HeaderValue>,
 }
 
 impl<A> Responder<A>
@@ -16,21 +21,36 @@ where
     /// Creates a Responder for handling the [`Request`].
     #[must_use]
     pub fn new(tree: Arc<Tree>, remote_addr: Option<A>) -> Self {
-        Self { tree, remote_addr }
+        Self {
+            tree,
+            remote_addr,
+            extensions: http::Extensions::new(),
+            alt_svc: None,
+        }
     }
-}
 
-impl<A> hyper::service::Service<Request<Incoming>> for Responder<A>
-where
-    A: Clone + Send + Sync + 'static,
-{
-    type Response = Response;
-    type Error = Infallible;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    /// Sets the data of the connection, which are added into the extensions of each request.
+    #[must_use]
+    pub fn with_extensions(mut self, extensions: http::Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
 
-    fn call(&self, mut req: Request<Incoming>) -> Self::Future {
+    /// Sets the `Alt-Svc` header, which is added into each response.
+    #[must_use]
+    pub fn with_alt_svc(mut self, alt_svc: Option<HeaderValue>) -> Self {
+        self.alt_svc = alt_svc;
+        self
+    }
+
+    /// Handles the [`Request`] and returns the [`Response`].
+    pub(crate) fn handle(
+        &self,
+        mut req: Request,
+    ) -> Pin<Box<dyn Future<Output = Response> + Send>> {
         let method = req.method().clone();
         let path = req.uri().path().to_owned();
+        let alt_svc = self.alt_svc.clone();
 
         let Some((handler, route)) = self.tree.find(&method, &path).or_else(|| {
             if method == Method::HEAD {
@@ -39,11 +59,14 @@ where
                 None
             }
         }) else {
-            return Box::pin(async move { Ok(StatusCode::NOT_FOUND.into_response()) });
+            return Box::pin(async move {
+                with_alt_svc(StatusCode::NOT_FOUND.into_response(), alt_svc)
+            });
         };
 
         let extensions = req.extensions_mut();
 
+        extensions.extend(self.extensions.clone());
         extensions.insert(self.remote_addr.clone());
         extensions.insert(Arc::from(crate::types::RouteInfo {
             id: *route.id,
@@ -54,10 +77,32 @@ where
         let handler = handler.clone();
 
         Box::pin(async move {
-            handler
-                .call(req.map(Body::Incoming))
+            let resp = handler
+                .call(req)
                 .await
-                .or_else(|e| Ok(e.into_response()))
+                .unwrap_or_else(IntoResponse::into_response);
+            with_alt_svc(resp, alt_svc)
         })
     }
 }
+
+fn with_alt_svc(mut resp: Response, alt_svc: Option<HeaderValue>) -> Response {
+    if let Some(alt_svc) = alt_svc {
+        resp.headers_mut().entry(ALT_SVC).or_insert(alt_svc);
+    }
+    resp
+}
+
+impl<A> hyper::service::Service<Request<Incoming>> for Responder<A>
+where
+    A: Clone + Send + Sync + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn call(&self, req: Request<Incoming>) -> Self::Future {
+        let fut = self.handle(req.map(Body::Incoming));
+        Box::pin(async move { Ok(fut.await) })
+    }
+}
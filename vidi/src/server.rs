@@ -7,23 +7,42 @@ use std::{
     time::Duration,
 };
 
+use hyper_util::rt::{TokioExecutor, TokioIo};
 #[cfg(any(feature = "http1", feature = "http2"))]
 use hyper_util::server::conn::auto::Builder;
-use hyper_util::{
-    rt::{TokioExecutor, TokioIo},
-    server::graceful::GracefulShutdown,
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{Semaphore, watch},
 };
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::task::TaskTracker;
 
-use crate::{Listener, Responder, Router};
+use crate::{Listener, Responder, Router, header::HeaderValue};
 
 /// TLS
 #[cfg(any(feature = "native-tls", feature = "rustls"))]
 pub mod tls;
 
+#[cfg(feature = "http3")]
+pub mod http3;
+
+#[cfg(feature = "proxy-protocol")]
+pub mod proxy_protocol;
+
+#[cfg(all(target_os = "linux", feature = "socket-activation"))]
+pub mod activation;
+
 #[cfg(any(feature = "http1", feature = "http2"))]
 mod tcp;
 
+mod limits;
+pub use limits::ServerStats;
+use limits::{Activity, IdleIo, IdleService, Limits, PerIp};
+
+#[cfg(any(feature = "http1", feature = "http2"))]
+mod config;
+#[cfg(any(feature = "http1", feature = "http2"))]
+pub use config::ServerConfig;
+
 #[cfg(all(unix, feature = "unix-socket"))]
 mod unix;
 
@@ -36,10 +55,14 @@ pub fn serve<L>(listener: L, router: Router) -> Server<L> {
 #[derive(Debug)]
 pub struct Server<L, S = Pending<()>> {
     listener: L,
+    listeners: Listeners,
     signal: S,
     tree: crate::Tree,
     builder: Builder<TokioExecutor>,
     graceful_timeout: Duration,
+    alt_svc: Option<HeaderValue>,
+    limits: Limits,
+    stats: ServerStats,
 }
 
 impl<L> Server<L> {
@@ -52,13 +75,22 @@ impl<L> Server<L> {
     pub fn with_builder(listener: L, router: Router, builder: Builder<TokioExecutor>) -> Self {
         Server {
             listener,
+            listeners: Listeners::default(),
             builder,
             signal: pending(),
             tree: router.into(),
             graceful_timeout: Duration::from_secs(10),
+            alt_svc: None,
+            limits: Limits::default(),
+            stats: ServerStats::default(),
         }
     }
 
+    /// Starts a [`Server`] with a listener, a [`Router`] and a [`ServerConfig`].
+    pub fn with_config(listener: L, router: Router, config: &ServerConfig) -> Self {
+        Self::with_builder(listener, router, config.build())
+    }
+
     /// Specifies a signal for graceful shutdown.
     pub fn signal<S>(self, signal: S) -> Server<L, S> {
         Server {
@@ -66,10 +98,72 @@ impl<L> Server<L> {
             tree: self.tree,
             builder: self.builder,
             listener: self.listener,
+            listeners: self.listeners,
             graceful_timeout: self.graceful_timeout,
+            alt_svc: self.alt_svc,
+            limits: self.limits,
+            stats: self.stats,
         }
     }
 
+    /// Attaches an additional listener, e.g. a Unix socket or a TLS listener beside the TCP
+    /// listener.
+    ///
+    /// All listeners serve the same [`Router`], share the connection limits and are stopped by
+    /// the same signal.
+    #[must_use]
+    pub fn listener<T>(mut self, listener: T) -> Self
+    where
+        T: Listener + Send + 'static,
+        T::Io: AsyncRead + AsyncWrite + Send + Unpin,
+        T::Addr: Send + Sync + Debug,
+    {
+        self.listeners.0.push(Box::new(|shared, shutdown| {
+            Box::pin(accept(listener, shared, shutdown))
+        }));
+        self
+    }
+
+    /// Sets the protocol options of the connections.
+    #[must_use]
+    pub fn config(mut self, config: &ServerConfig) -> Self {
+        self.builder = config.build();
+        self
+    }
+
+    /// Advertises the alternative services via the `Alt-Svc` header of each response.
+    ///
+    /// E.g. `h3=":443"; ma=86400` advertises HTTP/3 on the port 443 for a day.
+    #[must_use]
+    pub fn alt_svc(mut self, alt_svc: HeaderValue) -> Self {
+        self.alt_svc = Some(alt_svc);
+        self
+    }
+
+    /// Serves HTTP/3 on a [`QuicListener`](http3::QuicListener) alongside with the same router.
+    ///
+    /// The responses of the other listeners advertise it via the `Alt-Svc` header, unless
+    /// it is set by [`Server::alt_svc`].
+    #[cfg(feature = "http3")]
+    #[must_use]
+    pub fn http3(mut self, listener: http3::QuicListener) -> Self {
+        if self.alt_svc.is_none() {
+            match listener.alt_svc() {
+                Ok(alt_svc) => self.alt_svc = Some(alt_svc),
+                Err(err) => tracing::error!("quic listener address error: {err}"),
+            }
+        }
+        self.listeners.0.push(Box::new(|shared, shutdown| {
+            Box::pin(async move {
+                let endpoint = listener.endpoint();
+                http3::accept(endpoint, shared.tree.clone(), shutdown, &shared.connections).await;
+                // stops accepting and sends `GOAWAY` to the connections
+                endpoint.set_server_config(None);
+            })
+        }));
+        self
+    }
+
     /// Sets the graceful shutdown timeout duration.
     ///
     /// Default is 10 seconds.
@@ -78,6 +172,59 @@ impl<L> Server<L> {
         self.graceful_timeout = timeout;
         self
     }
+
+    /// Sets the maximum number of the concurrent connections.
+    ///
+    /// Once the limit is reached, the server stops accepting and the new connections wait in
+    /// the backlog of the listener.
+    ///
+    /// Default is unlimited.
+    #[must_use]
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.limits.max_connections = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of the concurrent connections from an IP address, the
+    /// connections over the limit are closed immediately.
+    ///
+    /// The connections without an IP address, e.g. on a Unix socket, are not limited.
+    ///
+    /// Default is unlimited.
+    #[must_use]
+    pub fn max_connections_per_ip(mut self, max: usize) -> Self {
+        self.limits.max_connections_per_ip = Some(max);
+        self
+    }
+
+    /// Closes the connections which are neither read nor written for the duration between the
+    /// requests, e.g. the idle keep-alive connections or the stalled clients.
+    ///
+    /// It is paused while a request is in flight, until its response body is sent, so the slow
+    /// handlers and the long polling are not closed.
+    ///
+    /// Default is disabled.
+    #[must_use]
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.limits.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the backoff after an accept error, e.g. too many open files, which is doubled on
+    /// each consecutive error from the minimum up to the maximum.
+    ///
+    /// Default is from 5 milliseconds to 1 second.
+    #[must_use]
+    pub fn accept_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.limits.backoff = (min, max.max(min));
+        self
+    }
+
+    /// Gets the counters of the connections, which are updated by the running server.
+    #[must_use]
+    pub fn stats(&self) -> ServerStats {
+        self.stats.clone()
+    }
 }
 
 impl<L, S> IntoFuture for Server<L, S>
@@ -97,59 +244,56 @@ where
             signal,
             builder,
             listener,
+            listeners,
             graceful_timeout,
+            alt_svc,
+            limits,
+            stats,
         } = self;
 
         Box::pin(async move {
-            let graceful = GracefulShutdown::new();
-            let tree = Arc::new(tree);
-            let mut signal = pin!(signal);
-
-            loop {
-                tokio::select! {
-                    conn = listener.accept() => {
-                        let (stream, peer_addr) = match conn {
-                            Ok(conn) => conn,
-                            Err(err) => {
-                                if is_connection_error(&err) {
-                                    tracing::trace!("connection error (ignored): {err}");
-                                } else {
-                                    tracing::error!("listener accept error: {err}");
-                                    tokio::time::sleep(Duration::from_secs(1)).await;
-                                }
-                                continue;
-                            }
-                        };
-
-                        tracing::trace!("incoming connection accepted: {:?}", peer_addr);
-
-                        let peer_addr = Arc::new(peer_addr);
-                        let stream = TokioIo::new(Box::pin(stream));
-
-                        let responder = Responder::new(tree.clone(), Some(peer_addr.clone()));
+            let shared = Arc::new(Shared {
+                tree: Arc::new(tree),
+                builder,
+                connections: TaskTracker::new(),
+                alt_svc,
+                semaphore: limits
+                    .max_connections
+                    .map(|max| Arc::new(Semaphore::new(max))),
+                per_ip: Arc::new(PerIp::default()),
+                limits,
+                stats,
+            });
+            let (shutdown, _) = watch::channel(false);
 
-                        let conn = builder.serve_connection_with_upgrades(stream, responder);
+            let mut tasks = vec![tokio::spawn(accept(
+                listener,
+                shared.clone(),
+                shutdown.subscribe(),
+            ))];
+            tasks.extend(
+                listeners
+                    .0
+                    .into_iter()
+                    .map(|accept| tokio::spawn(accept(shared.clone(), shutdown.subscribe()))),
+            );
 
-                        let conn = graceful.watch(conn.into_owned());
+            signal.await;
+            tracing::trace!("Signal received, starting shutdown");
 
-                        tokio::spawn(async move {
-                            if let Err(err) = conn.await {
-                                tracing::error!("connection error: {}", err);
-                            }
-                            tracing::trace!("connection dropped: {:?}", peer_addr);
-                        });
-                    },
-
-                    _ = signal.as_mut() => {
-                        drop(listener);
-                        tracing::trace!("Signal received, starting shutdown");
-                        break;
-                    }
+            // stops accepting on all listeners
+            shutdown.send_replace(true);
+            for task in tasks {
+                if let Err(err) = task.await {
+                    tracing::error!("listener task error: {err}");
                 }
             }
 
+            // the connections have been signaled to shut down, waits for them to finish
+            shared.connections.close();
+
             tokio::select! {
-                () = graceful.shutdown() => {
+                () = shared.connections.wait() => {
                     tracing::trace!("Gracefully shutdown!");
                 },
                 () = tokio::time::sleep(graceful_timeout) => {
@@ -162,6 +306,166 @@ where
     }
 }
 
+/// The state which is shared by the listeners of a [`Server`].
+struct Shared {
+    tree: Arc<crate::Tree>,
+    builder: Builder<TokioExecutor>,
+    connections: TaskTracker,
+    alt_svc: Option<HeaderValue>,
+    limits: Limits,
+    semaphore: Option<Arc<Semaphore>>,
+    per_ip: Arc<PerIp>,
+    stats: ServerStats,
+}
+
+type Accept = Box<
+    dyn FnOnce(Arc<Shared>, watch::Receiver<bool>) -> Pin<Box<dyn Future<Output = ()> + Send>>
+        + Send,
+>;
+
+/// The additional listeners of a [`Server`].
+#[derive(Default)]
+struct Listeners(Vec<Accept>);
+
+impl Debug for Listeners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Listeners")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+/// Accepts the connections of a listener until the shutdown.
+async fn accept<L>(listener: L, shared: Arc<Shared>, mut shutdown: watch::Receiver<bool>)
+where
+    L: Listener,
+    L::Io: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    L::Addr: Send + Sync + Debug + 'static,
+{
+    let Shared {
+        tree,
+        builder,
+        connections,
+        alt_svc,
+        limits,
+        semaphore,
+        per_ip,
+        stats,
+    } = &*shared;
+    let (min_backoff, max_backoff) = limits.backoff;
+    let mut backoff = min_backoff;
+
+    loop {
+        // waits for a free slot before accepting
+        let permit = tokio::select! {
+            permit = limits::acquire(semaphore.clone()) => permit,
+            _ = shutdown.changed() => break,
+        };
+
+        tokio::select! {
+            conn = listener.accept() => {
+                let (stream, peer_addr) = match conn {
+                    Ok(conn) => conn,
+                    Err(err) => {
+                        stats.accept_error();
+                        if !is_accept_error(&err) {
+                            tracing::trace!("connection error (ignored): {err}");
+                            continue;
+                        }
+
+                        tracing::error!("listener accept error: {err}");
+                        tokio::select! {
+                            () = tokio::time::sleep(backoff) => {},
+                            _ = shutdown.changed() => break,
+                        }
+                        backoff = (backoff * 2).min(max_backoff);
+                        continue;
+                    }
+                };
+                backoff = min_backoff;
+
+                let per_ip = match limits.max_connections_per_ip {
+                    Some(max) => {
+                        if let Some(guard) = per_ip.open(&peer_addr, max) {
+                            Some(guard)
+                        } else {
+                            stats.reject();
+                            tracing::trace!("connection rejected: {:?}", peer_addr);
+                            continue;
+                        }
+                    }
+                    None => None,
+                };
+                let active = stats.open();
+
+                tracing::trace!("incoming connection accepted: {:?}", peer_addr);
+
+                let peer_addr = Arc::new(peer_addr);
+                let extensions = listener.extensions(&stream);
+                let activity = limits.idle_timeout.map(|_| Activity::new());
+                let stream = TokioIo::new(Box::pin(IdleIo::new(stream, activity.clone())));
+
+                let responder = Responder::new(tree.clone(), Some(peer_addr.clone()))
+                    .with_extensions(extensions)
+                    .with_alt_svc(alt_svc.clone());
+                let responder = IdleService::new(responder, activity.clone());
+
+                let conn = builder
+                    .serve_connection_with_upgrades(stream, responder)
+                    .into_owned();
+                let idle_timeout = limits.idle_timeout;
+                let stats = stats.clone();
+                let mut shutdown = shutdown.clone();
+
+                connections.spawn(async move {
+                    let mut conn = pin!(conn);
+                    let mut idle = pin!(async {
+                        match (activity, idle_timeout) {
+                            (Some(activity), Some(timeout)) => activity.idle(timeout).await,
+                            _ => pending().await,
+                        }
+                    });
+                    let mut draining = false;
+
+                    loop {
+                        tokio::select! {
+                            res = conn.as_mut() => {
+                                if let Err(err) = res {
+                                    tracing::error!("connection error: {}", err);
+                                }
+                                break;
+                            },
+                            () = idle.as_mut() => {
+                                stats.idle_timeout();
+                                tracing::trace!("connection idle timeout: {:?}", peer_addr);
+                                break;
+                            },
+                            _ = shutdown.wait_for(|stop| *stop), if !draining => {
+                                draining = true;
+                                conn.as_mut().graceful_shutdown();
+                            }
+                        }
+                    }
+                    tracing::trace!("connection dropped: {:?}", peer_addr);
+                    drop((active, per_ip, permit));
+                });
+            },
+
+            _ = shutdown.changed() => break,
+        }
+    }
+}
+
+/// Whether the error comes from `accept(2)` itself, e.g. too many open files, and the listener
+/// should back off.
+///
+/// The handshake errors of the wrapped listeners, e.g. TLS, carry no OS error code, and the
+/// errors of a single peer are ignored.
+#[inline]
+fn is_accept_error(e: &io::Error) -> bool {
+    e.raw_os_error().is_some() && !is_connection_error(e)
+}
+
 #[inline]
 fn is_connection_error(e: &io::Error) -> bool {
     matches!(
@@ -169,5 +473,10 @@ fn is_connection_error(e: &io::Error) -> bool {
         io::ErrorKind::ConnectionRefused
             | io::ErrorKind::ConnectionAborted
             | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
     )
 }
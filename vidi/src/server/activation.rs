@@ -0,0 +1,482 @@
+//! Socket activation and listener handoff.
+//!
+//! The listening sockets can be inherited from systemd via `LISTEN_FDS`, or from the previous
+//! process of a zero-downtime restart: on `SIGHUP` the [`Upgrader`] starts a new process of the
+//! same binary and hands the sockets over, then the old server stops accepting and drains the
+//! in-flight connections within the `graceful_timeout`.
+//!
+//! ```no_run
+//! use tokio::net::TcpListener;
+//! use vidi::{
+//!     Request, Result, Router,
+//!     activation::{Inherited, Upgrader},
+//!     serve,
+//! };
+//!
+//! # async fn run() -> Result<()> {
+//! let mut inherited = Inherited::from_env().await?;
+//! let listener = match inherited.take("http") {
+//!     Some(fd) => fd.into_tcp()?,
+//!     None => TcpListener::bind("0.0.0.0:3000").await?,
+//! };
+//! let upgrader = Upgrader::new().listener("http", &listener)?;
+//!
+//! let app = Router::new().get("/", |_: Request| async { Ok("Hello, World!") });
+//! let server = serve(listener, app).signal(upgrader.hangup());
+//!
+//! // the old process stops accepting
+//! inherited.ready().await?;
+//! server.await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::hash_map::RandomState,
+    env,
+    fs::DirBuilder,
+    hash::{BuildHasher, Hasher},
+    io,
+    io::{IoSlice, IoSliceMut},
+    mem::MaybeUninit,
+    os::{
+        fd::{AsFd, BorrowedFd, FromRawFd, OwnedFd},
+        unix::fs::DirBuilderExt,
+    },
+    path::PathBuf,
+    process::{Child, Command},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use rustix::{
+    cmsg_space,
+    io::{FdFlags, fcntl_setfd},
+    net::{
+        AddressFamily, RecvAncillaryBuffer, RecvAncillaryMessage, RecvFlags, SendAncillaryBuffer,
+        SendAncillaryMessage, SendFlags, recvmsg, sendmsg, sockopt,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, Interest},
+    net::{TcpListener, UnixListener, UnixStream},
+    signal::unix::{SignalKind, signal},
+};
+
+/// The environment variable of the handoff socket, which is set for the new process.
+pub const HANDOFF_ENV: &str = "VIDI_HANDOFF";
+
+/// The maximum number of the handed over sockets.
+pub const MAX_FDS: usize = 64;
+
+/// The first file descriptor of systemd socket activation.
+const LISTEN_FDS_START: i32 = 3;
+
+/// The environment variables of systemd socket activation.
+const LISTEN_ENVS: [&str; 3] = ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"];
+
+/// The sockets of systemd socket activation can only be taken once.
+static LISTEN_FDS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// An inherited listening socket.
+#[derive(Debug)]
+pub struct ListenFd {
+    name: String,
+    fd: OwnedFd,
+}
+
+impl ListenFd {
+    /// Creates a socket with a name.
+    pub fn new(name: impl Into<String>, fd: OwnedFd) -> Self {
+        Self {
+            name: name.into(),
+            fd,
+        }
+    }
+
+    /// Gets the name, e.g. `FileDescriptorName` of the systemd socket unit.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Converts into a TCP listener.
+    ///
+    /// # Errors
+    ///
+    /// Throws an error if the socket is not an IPv4 or IPv6 socket.
+    pub fn into_tcp(self) -> io::Result<TcpListener> {
+        let domain = sockopt::socket_domain(&self.fd)?;
+        if domain != AddressFamily::INET && domain != AddressFamily::INET6 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("`{}` is not a TCP socket", self.name),
+            ));
+        }
+        let listener = std::net::TcpListener::from(self.fd);
+        listener.set_nonblocking(true)?;
+        TcpListener::from_std(listener)
+    }
+
+    /// Converts into a Unix listener.
+    ///
+    /// # Errors
+    ///
+    /// Throws an error if the socket is not a Unix socket.
+    pub fn into_unix(self) -> io::Result<UnixListener> {
+        if sockopt::socket_domain(&self.fd)? != AddressFamily::UNIX {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("`{}` is not a Unix socket", self.name),
+            ));
+        }
+        let listener = std::os::unix::net::UnixListener::from(self.fd);
+        listener.set_nonblocking(true)?;
+        UnixListener::from_std(listener)
+    }
+}
+
+impl AsFd for ListenFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl From<ListenFd> for OwnedFd {
+    fn from(fd: ListenFd) -> Self {
+        fd.fd
+    }
+}
+
+/// Takes the sockets of systemd socket activation, which are passed via `LISTEN_PID`,
+/// `LISTEN_FDS` and `LISTEN_FDNAMES`.
+///
+/// Like `sd_listen_fds(1)`, the variables are removed from the environment and the sockets are
+/// not inherited by the children, so they are only taken once. It should be called at the
+/// startup, before the other threads read the environment.
+///
+/// # Errors
+///
+/// Throws an error if the descriptors can not be configured.
+#[allow(unsafe_code)]
+pub fn listen_fds() -> io::Result<Vec<ListenFd>> {
+    let pid = env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok());
+    if pid != Some(std::process::id()) || LISTEN_FDS_TAKEN.swap(true, Ordering::AcqRel) {
+        return Ok(Vec::new());
+    }
+
+    let count = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<i32>().ok())
+        .unwrap_or(0);
+    let names = env::var("LISTEN_FDNAMES").unwrap_or_default();
+    for name in LISTEN_ENVS {
+        // SAFETY: the variables of socket activation are only read here, at the startup.
+        unsafe { env::remove_var(name) };
+    }
+
+    let fds = (LISTEN_FDS_START..LISTEN_FDS_START + count.max(0))
+        // SAFETY: the descriptors are passed to this process by `LISTEN_PID`, they are owned by
+        // nothing else and are taken only once.
+        .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+        .collect::<Vec<_>>();
+
+    let mut names = names.split(':');
+    fds.into_iter()
+        .map(|fd| {
+            fcntl_setfd(&fd, FdFlags::CLOEXEC)?;
+            let name = names.next().filter(|name| !name.is_empty());
+            Ok(ListenFd::new(name.unwrap_or("unknown"), fd))
+        })
+        .collect()
+}
+
+/// The sockets which are inherited from the previous process or systemd.
+#[derive(Debug, Default)]
+pub struct Inherited {
+    fds: Vec<ListenFd>,
+    parent: Option<UnixStream>,
+}
+
+impl Inherited {
+    /// Receives the sockets from the previous process if [`HANDOFF_ENV`] is set, otherwise takes
+    /// the sockets of systemd socket activation.
+    ///
+    /// # Errors
+    ///
+    /// Throws an error if the sockets can not be received.
+    pub async fn from_env() -> io::Result<Self> {
+        match env::var_os(HANDOFF_ENV) {
+            Some(path) => receive(UnixStream::connect(path).await?).await,
+            None => Ok(Self {
+                fds: listen_fds()?,
+                parent: None,
+            }),
+        }
+    }
+
+    /// Takes the socket by the name.
+    pub fn take(&mut self, name: &str) -> Option<ListenFd> {
+        let index = self.fds.iter().position(|fd| fd.name == name)?;
+        Some(self.fds.remove(index))
+    }
+
+    /// Gets the number of the remaining sockets.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.fds.len()
+    }
+
+    /// Checks if no sockets are remaining.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fds.is_empty()
+    }
+
+    /// Notifies the previous process that this process is ready to accept, which then stops
+    /// accepting and drains its connections.
+    ///
+    /// # Errors
+    ///
+    /// Throws an error if the previous process is gone.
+    pub async fn ready(&mut self) -> io::Result<()> {
+        if let Some(mut parent) = self.parent.take() {
+            parent.write_all(b"1").await?;
+        }
+        Ok(())
+    }
+}
+
+/// Hands the listening sockets over to a new process.
+#[derive(Debug)]
+pub struct Upgrader {
+    fds: Vec<ListenFd>,
+    timeout: Duration,
+}
+
+impl Default for Upgrader {
+    fn default() -> Self {
+        Self {
+            fds: Vec::new(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Upgrader {
+    /// Creates a new upgrader.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a listening socket with a name, the descriptor is duplicated.
+    ///
+    /// # Errors
+    ///
+    /// Throws an error if the descriptor can not be duplicated.
+    pub fn listener(mut self, name: impl Into<String>, listener: &impl AsFd) -> io::Result<Self> {
+        if self.fds.len() == MAX_FDS {
+            return Err(io::Error::other("too many sockets"));
+        }
+        let fd = listener.as_fd().try_clone_to_owned()?;
+        self.fds.push(ListenFd::new(name, fd));
+        Ok(self)
+    }
+
+    /// Sets the timeout for the new process to receive the sockets and be ready.
+    ///
+    /// Default is 30 seconds.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Starts the command and hands the sockets over, returns once the new process is
+    /// [`ready`][Inherited::ready].
+    ///
+    /// # Errors
+    ///
+    /// Throws an error if the new process fails to start or is not ready in time, the process
+    /// is killed.
+    pub async fn upgrade(&self, mut command: Command) -> io::Result<Child> {
+        let dir = handoff_dir()?;
+        let path = dir.join("handoff.sock");
+        let spawned = UnixListener::bind(&path)
+            .and_then(|listener| Ok((listener, command.env(HANDOFF_ENV, &path).spawn()?)));
+        let (listener, mut child) = match spawned {
+            Ok(spawned) => spawned,
+            Err(err) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(err);
+            }
+        };
+
+        let handoff = async {
+            let (mut stream, _) = listener.accept().await?;
+            send(&mut stream, &self.fds).await?;
+            let mut ready = [0; 1];
+            stream.read_exact(&mut ready).await?;
+            io::Result::Ok(())
+        };
+
+        let res = tokio::time::timeout(self.timeout, handoff)
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()));
+        let _ = std::fs::remove_dir_all(&dir);
+        if let Err(err) = res {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+
+        Ok(child)
+    }
+
+    /// Waits for `SIGHUP` and upgrades to a new process of the current executable with the
+    /// same arguments, resolves once the new process is ready.
+    ///
+    /// It is designed for the [`signal`][crate::Server::signal] of a server, a failed upgrade
+    /// is logged and the server keeps running.
+    pub async fn hangup(self) {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(err) => {
+                tracing::error!("failed to listen SIGHUP: {err}");
+                return std::future::pending().await;
+            }
+        };
+
+        while hangup.recv().await.is_some() {
+            let command = env::current_exe().map(|exe| {
+                let mut command = Command::new(exe);
+                command.args(env::args_os().skip(1));
+                command
+            });
+            match command {
+                Ok(command) => match self.upgrade(command).await {
+                    Ok(child) => {
+                        tracing::info!("upgraded to the process {}", child.id());
+                        return;
+                    }
+                    Err(err) => tracing::error!("failed to upgrade: {err}"),
+                },
+                Err(err) => tracing::error!("failed to upgrade: {err}"),
+            }
+        }
+
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Creates a private directory for the handoff socket, which is only accessible by the user,
+/// in `XDG_RUNTIME_DIR` if it is set, otherwise in the temporary directory.
+fn handoff_dir() -> io::Result<PathBuf> {
+    let base = env::var_os("XDG_RUNTIME_DIR").map_or_else(env::temp_dir, PathBuf::from);
+    let mut builder = DirBuilder::new();
+    builder.mode(0o700);
+
+    // an existing directory is never reused
+    for _ in 0..8 {
+        let random = RandomState::new().build_hasher().finish();
+        let dir = base.join(format!("vidi-{}-{random:016x}", std::process::id()));
+        match builder.create(&dir) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            res => return res.map(|()| dir),
+        }
+    }
+    Err(io::ErrorKind::AlreadyExists.into())
+}
+
+/// Sends the names and the sockets, the names are prefixed with the length.
+async fn send(stream: &mut UnixStream, fds: &[ListenFd]) -> io::Result<()> {
+    let names = fds
+        .iter()
+        .map(ListenFd::name)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let len = u32::try_from(names.len())
+        .map_err(io::Error::other)?
+        .to_be_bytes();
+    let borrowed = fds.iter().map(AsFd::as_fd).collect::<Vec<_>>();
+
+    let mut space = [MaybeUninit::uninit(); cmsg_space!(ScmRights(MAX_FDS))];
+    let mut control = SendAncillaryBuffer::new(&mut space);
+    if !borrowed.is_empty() {
+        control.push(SendAncillaryMessage::ScmRights(&borrowed));
+    }
+
+    // the sockets are sent with the first bytes
+    let sent = stream
+        .async_io(Interest::WRITABLE, || {
+            sendmsg(
+                &*stream,
+                &[IoSlice::new(&len), IoSlice::new(names.as_bytes())],
+                &mut control,
+                SendFlags::empty(),
+            )
+            .map_err(io::Error::from)
+        })
+        .await?;
+
+    let rest = [&len[..], names.as_bytes()].concat();
+    if let Some(rest) = rest.get(sent..) {
+        stream.write_all(rest).await?;
+    }
+    Ok(())
+}
+
+/// Receives the names and the sockets from the previous process.
+async fn receive(mut stream: UnixStream) -> io::Result<Inherited> {
+    let mut buf = vec![0; 4096];
+    let mut space = [MaybeUninit::uninit(); cmsg_space!(ScmRights(MAX_FDS))];
+    let mut control = RecvAncillaryBuffer::new(&mut space);
+
+    let received = stream
+        .async_io(Interest::READABLE, || {
+            recvmsg(
+                &stream,
+                &mut [IoSliceMut::new(&mut buf)],
+                &mut control,
+                RecvFlags::CMSG_CLOEXEC,
+            )
+            .map(|msg| msg.bytes)
+            .map_err(io::Error::from)
+        })
+        .await?;
+
+    let mut fds = Vec::new();
+    for msg in control.drain() {
+        if let RecvAncillaryMessage::ScmRights(rights) = msg {
+            fds.extend(rights);
+        }
+    }
+
+    buf.truncate(received);
+    if buf.len() < 4 {
+        let mut rest = vec![0; 4 - buf.len()];
+        stream.read_exact(&mut rest).await?;
+        buf.extend(rest);
+    }
+    let len = usize::try_from(u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]))
+        .map_err(io::Error::other)?;
+    let mut names = buf.split_off(4);
+    if names.len() < len {
+        let mut rest = vec![0; len - names.len()];
+        stream.read_exact(&mut rest).await?;
+        names.extend(rest);
+    }
+    let names = String::from_utf8(names).map_err(io::Error::other)?;
+
+    Ok(Inherited {
+        fds: names
+            .split('\n')
+            .zip(fds)
+            .map(|(name, fd)| ListenFd::new(name, fd))
+            .collect(),
+        parent: Some(stream),
+    })
+}
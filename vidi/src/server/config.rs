@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use hyper_util::{
+    rt::{TokioExecutor, TokioTimer},
+    server::conn::auto::Builder,
+};
+
+/// The protocol options of the [`Server`][crate::Server].
+///
+/// The unset options keep the defaults of `hyper`. With the `http2` feature, HTTP/2 is served
+/// over TLS when `h2` is negotiated by ALPN, and over cleartext with the prior knowledge.
+///
+/// ```
+/// use std::time::Duration;
+/// use vidi::ServerConfig;
+///
+/// let config = ServerConfig::new()
+///     .header_read_timeout(Duration::from_secs(5))
+///     .max_headers(64);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ServerConfig {
+    #[cfg(feature = "http1")]
+    http1: Http1,
+    #[cfg(feature = "http2")]
+    http2: Http2,
+}
+
+#[cfg(feature = "http1")]
+#[derive(Clone, Debug, Default)]
+struct Http1 {
+    keep_alive: Option<bool>,
+    header_read_timeout: Option<Duration>,
+    max_headers: Option<usize>,
+    max_buf_size: Option<usize>,
+}
+
+#[cfg(feature = "http2")]
+#[derive(Clone, Debug, Default)]
+struct Http2 {
+    max_concurrent_streams: Option<u32>,
+    initial_stream_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+    adaptive_window: Option<bool>,
+    max_frame_size: Option<u32>,
+    max_header_list_size: Option<u32>,
+    keep_alive_interval: Option<Duration>,
+    keep_alive_timeout: Option<Duration>,
+    connect_protocol: bool,
+}
+
+impl ServerConfig {
+    /// Creates a new config with the defaults of `hyper`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables the HTTP/1 keep-alive.
+    ///
+    /// Default is `true`.
+    #[cfg(feature = "http1")]
+    #[must_use]
+    pub fn keep_alive(mut self, enabled: bool) -> Self {
+        self.http1.keep_alive = Some(enabled);
+        self
+    }
+
+    /// Sets the timeout for reading the HTTP/1 request headers, the connection is closed once
+    /// the timeout is reached.
+    ///
+    /// Default is 30 seconds.
+    #[cfg(feature = "http1")]
+    #[must_use]
+    pub fn header_read_timeout(mut self, timeout: Duration) -> Self {
+        self.http1.header_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of the HTTP/1 request headers.
+    ///
+    /// Default is 100.
+    #[cfg(feature = "http1")]
+    #[must_use]
+    pub fn max_headers(mut self, max: usize) -> Self {
+        self.http1.max_headers = Some(max);
+        self
+    }
+
+    /// Sets the maximum size of the HTTP/1 read buffer.
+    ///
+    /// Default is about 400kb.
+    #[cfg(feature = "http1")]
+    #[must_use]
+    pub fn max_buf_size(mut self, max: usize) -> Self {
+        self.http1.max_buf_size = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of the concurrent HTTP/2 streams per connection.
+    ///
+    /// Default is 200.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn max_concurrent_streams(mut self, max: u32) -> Self {
+        self.http2.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Sets the initial HTTP/2 stream-level flow control window size.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn initial_stream_window_size(mut self, size: u32) -> Self {
+        self.http2.initial_stream_window_size = Some(size);
+        self
+    }
+
+    /// Sets the initial HTTP/2 connection-level flow control window size.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn initial_connection_window_size(mut self, size: u32) -> Self {
+        self.http2.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Enables or disables the adaptive HTTP/2 flow control, which overrides the window sizes.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2.adaptive_window = Some(enabled);
+        self
+    }
+
+    /// Sets the maximum HTTP/2 frame size.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn max_frame_size(mut self, size: u32) -> Self {
+        self.http2.max_frame_size = Some(size);
+        self
+    }
+
+    /// Sets the maximum size of the received HTTP/2 header list.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn max_header_list_size(mut self, size: u32) -> Self {
+        self.http2.max_header_list_size = Some(size);
+        self
+    }
+
+    /// Sends the HTTP/2 keep-alive pings in the interval.
+    ///
+    /// Default is disabled.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http2.keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Sets the timeout for receiving the acknowledgement of the HTTP/2 keep-alive ping, the
+    /// connection is closed once the timeout is reached.
+    ///
+    /// Default is 20 seconds, it does nothing without the
+    /// [`keep_alive_interval`][Self::keep_alive_interval].
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.http2.keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Enables the extended CONNECT protocol of HTTP/2, e.g. websockets over HTTP/2.
+    #[cfg(feature = "http2")]
+    #[must_use]
+    pub fn enable_connect_protocol(mut self) -> Self {
+        self.http2.connect_protocol = true;
+        self
+    }
+
+    /// Builds the connection [`Builder`].
+    #[must_use]
+    pub fn build(&self) -> Builder<TokioExecutor> {
+        let mut builder = Builder::new(TokioExecutor::new());
+
+        #[cfg(feature = "http1")]
+        {
+            let Http1 {
+                keep_alive,
+                header_read_timeout,
+                max_headers,
+                max_buf_size,
+            } = self.http1;
+            let mut http1 = builder.http1();
+            http1.timer(TokioTimer::new());
+            if let Some(enabled) = keep_alive {
+                http1.keep_alive(enabled);
+            }
+            if let Some(timeout) = header_read_timeout {
+                http1.header_read_timeout(timeout);
+            }
+            if let Some(max) = max_headers {
+                http1.max_headers(max);
+            }
+            if let Some(max) = max_buf_size {
+                http1.max_buf_size(max);
+            }
+        }
+
+        #[cfg(feature = "http2")]
+        {
+            let Http2 {
+                max_concurrent_streams,
+                initial_stream_window_size,
+                initial_connection_window_size,
+                adaptive_window,
+                max_frame_size,
+                max_header_list_size,
+                keep_alive_interval,
+                keep_alive_timeout,
+                connect_protocol,
+            } = self.http2;
+            let mut http2 = builder.http2();
+            http2
+                .timer(TokioTimer::new())
+                .initial_stream_window_size(initial_stream_window_size)
+                .initial_connection_window_size(initial_connection_window_size)
+                .max_frame_size(max_frame_size)
+                .keep_alive_interval(keep_alive_interval);
+            if let Some(max) = max_concurrent_streams {
+                http2.max_concurrent_streams(max);
+            }
+            if let Some(enabled) = adaptive_window {
+                http2.adaptive_window(enabled);
+            }
+            if let Some(size) = max_header_list_size {
+                http2.max_header_list_size(size);
+            }
+            if let Some(timeout) = keep_alive_timeout {
+                http2.keep_alive_timeout(timeout);
+            }
+            if connect_protocol {
+                http2.enable_connect_protocol();
+            }
+        }
+
+        builder
+    }
+}
@@ -0,0 +1,354 @@
+//! HTTP/3 over QUIC.
+//!
+//! The [`QuicListener`] accepts the QUIC connections and the HTTP/3 requests are dispatched
+//! into the same [`Router`] as the TCP server. Served via [`Server::http3`](crate::Server::http3)
+//! alongside the TCP listener, the HTTP/3 endpoint is advertised via the `Alt-Svc` header.
+//!
+//! ```no_run
+//! use std::{net::SocketAddr, sync::Arc};
+//! use tokio::net::TcpListener;
+//! use vidi::{
+//!     Request, Result, Router,
+//!     http3::QuicListener,
+//!     serve,
+//!     tls::{
+//!         TlsListener,
+//!         rustls::{Config, TlsAcceptor},
+//!     },
+//! };
+//!
+//! # async fn run() -> Result<()> {
+//! let app = Router::new().get("/", |_: Request| async { Ok("Hello, HTTP/3") });
+//! let config = Config::new()
+//!     .cert(std::fs::read("cert.pem")?)
+//!     .key(std::fs::read("key.pem")?)
+//!     .build()?;
+//!
+//! let addr = SocketAddr::from(([0, 0, 0, 0], 443));
+//! let quic = QuicListener::bind(addr, config.clone())?;
+//! let listener = TlsListener::new(
+//!     TcpListener::bind(addr).await?,
+//!     TlsAcceptor::from(Arc::new(config)),
+//! );
+//!
+//! serve(listener, app).http3(quic).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The standalone [`Server`] serves only HTTP/3, a separate TCP server has to advertise it with
+//! `.alt_svc(quic.alt_svc()?)` before the listener is moved.
+
+use std::{
+    future::{Future, IntoFuture, Pending, pending},
+    io,
+    net::SocketAddr,
+    pin::{Pin, pin},
+    sync::Arc,
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes};
+use h3::server::{Connection as H3Connection, RequestResolver};
+use http_body_util::BodyExt;
+use quinn::{
+    Endpoint, Incoming,
+    crypto::rustls::{HandshakeData, QuicServerConfig},
+};
+use tokio::sync::watch;
+use tokio_rustls::rustls::{ServerConfig, pki_types::CertificateDer};
+use tokio_util::task::TaskTracker;
+
+use crate::{
+    Body, BoxError, Responder, Router,
+    header::HeaderValue,
+    tls::{PeerCertificate, ServerName, TlsInfo},
+};
+
+/// The ALPN protocol of HTTP/3.
+pub const ALPN: &[u8] = b"h3";
+
+/// A QUIC listener for HTTP/3.
+#[derive(Debug)]
+pub struct QuicListener {
+    endpoint: Endpoint,
+}
+
+impl QuicListener {
+    /// Binds a UDP socket with the TLS config, the ALPN protocols are replaced with `h3`.
+    ///
+    /// # Errors
+    ///
+    /// Throws an error if the TLS config does not support TLS 1.3 or the socket can not be bound.
+    pub fn bind(addr: SocketAddr, mut config: ServerConfig) -> io::Result<Self> {
+        config.alpn_protocols = vec![ALPN.to_vec()];
+        let config = QuicServerConfig::try_from(config).map_err(io::Error::other)?;
+        let endpoint = Endpoint::server(quinn::ServerConfig::with_crypto(Arc::new(config)), addr)?;
+        Ok(Self { endpoint })
+    }
+
+    /// Creates a listener from a configured QUIC [`Endpoint`].
+    #[must_use]
+    pub const fn from_endpoint(endpoint: Endpoint) -> Self {
+        Self { endpoint }
+    }
+
+    /// Returns the local address that this listener is bound to.
+    ///
+    /// # Errors
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.endpoint.local_addr()
+    }
+
+    /// Gets the QUIC [`Endpoint`].
+    #[must_use]
+    pub const fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    /// Creates the `Alt-Svc` header value for advertising this listener, e.g.
+    /// `h3=":443"; ma=86400`, which is set by [`Server::http3`](crate::Server::http3).
+    ///
+    /// # Errors
+    pub fn alt_svc(&self) -> io::Result<HeaderValue> {
+        let port = self.local_addr()?.port();
+        HeaderValue::try_from(format!("h3=\":{port}\"; ma=86400")).map_err(io::Error::other)
+    }
+}
+
+/// Starts a HTTP/3 server and serves the connections.
+#[must_use]
+pub fn serve(listener: QuicListener, router: Router) -> Server {
+    Server::new(listener, router)
+}
+
+/// A listening HTTP/3 server that accepts the QUIC connections.
+#[derive(Debug)]
+pub struct Server<S = Pending<()>> {
+    listener: QuicListener,
+    signal: S,
+    tree: crate::Tree,
+    graceful_timeout: Duration,
+}
+
+impl Server {
+    /// Starts a [`Server`] with a [`QuicListener`] and a [`Router`].
+    #[must_use]
+    pub fn new(listener: QuicListener, router: Router) -> Self {
+        Self {
+            listener,
+            signal: pending(),
+            tree: router.into(),
+            graceful_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Specifies a signal for graceful shutdown.
+    pub fn signal<S>(self, signal: S) -> Server<S> {
+        Server {
+            signal,
+            tree: self.tree,
+            listener: self.listener,
+            graceful_timeout: self.graceful_timeout,
+        }
+    }
+}
+
+impl<S> Server<S> {
+    /// Sets the graceful shutdown timeout duration.
+    ///
+    /// Default is 10 seconds.
+    #[must_use]
+    pub fn graceful_timeout(mut self, timeout: Duration) -> Self {
+        self.graceful_timeout = timeout;
+        self
+    }
+}
+
+impl<S> IntoFuture for Server<S>
+where
+    S: Future + Send + 'static,
+    S::Output: Send,
+{
+    type Output = io::Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let Self {
+            listener,
+            signal,
+            tree,
+            graceful_timeout,
+        } = self;
+
+        Box::pin(async move {
+            let endpoint = listener.endpoint;
+            let (shutdown, _) = watch::channel(false);
+            let connections = TaskTracker::new();
+
+            tokio::select! {
+                () = accept(&endpoint, Arc::new(tree), shutdown.subscribe(), &connections) => {},
+                _ = signal => {
+                    tracing::trace!("Signal received, starting shutdown");
+                }
+            }
+
+            // stops accepting and sends `GOAWAY` to the connections
+            endpoint.set_server_config(None);
+            shutdown.send_replace(true);
+
+            tokio::select! {
+                () = endpoint.wait_idle() => {
+                    tracing::trace!("Gracefully shutdown!");
+                },
+                () = tokio::time::sleep(graceful_timeout) => {
+                    tracing::error!("Waited {} seconds for graceful shutdown, aborting...", graceful_timeout.as_secs());
+                    endpoint.close(0u32.into(), b"shutdown");
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Accepts the QUIC connections until the shutdown or the endpoint is closed.
+pub(super) async fn accept(
+    endpoint: &Endpoint,
+    tree: Arc<crate::Tree>,
+    mut shutdown: watch::Receiver<bool>,
+    connections: &TaskTracker,
+) {
+    loop {
+        tokio::select! {
+            incoming = endpoint.accept() => {
+                let Some(incoming) = incoming else {
+                    break;
+                };
+
+                let tree = tree.clone();
+                let shutdown = shutdown.clone();
+                connections.spawn(async move {
+                    if let Err(err) = connection(incoming, tree, shutdown).await {
+                        tracing::error!("connection error: {}", err);
+                    }
+                });
+            },
+
+            _ = shutdown.changed() => break,
+        }
+    }
+}
+
+/// Drives a QUIC connection and serves the HTTP/3 requests.
+async fn connection(
+    incoming: Incoming,
+    tree: Arc<crate::Tree>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), BoxError> {
+    let conn = incoming.await?;
+    let peer_addr = conn.remote_address();
+
+    tracing::trace!("incoming connection accepted: {:?}", peer_addr);
+
+    let responder = Arc::new(
+        Responder::new(tree, Some(Arc::new(peer_addr))).with_extensions(extensions(&conn)),
+    );
+    let mut conn = H3Connection::new(h3_quinn::Connection::new(conn)).await?;
+    let mut closing = false;
+
+    loop {
+        tokio::select! {
+            resolver = conn.accept() => {
+                let Some(resolver) = resolver? else {
+                    break;
+                };
+
+                let responder = responder.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = request(resolver, responder).await {
+                        tracing::error!("request error: {}", err);
+                    }
+                });
+            },
+
+            _ = shutdown.changed(), if !closing => {
+                closing = true;
+                conn.shutdown(0).await?;
+            }
+        }
+    }
+
+    tracing::trace!("connection dropped: {:?}", peer_addr);
+
+    Ok(())
+}
+
+/// Resolves a HTTP/3 request, calls the handler and sends the response.
+async fn request(
+    resolver: RequestResolver<h3_quinn::Connection, Bytes>,
+    responder: Arc<Responder<Arc<SocketAddr>>>,
+) -> Result<(), BoxError> {
+    let (req, stream) = resolver.resolve_request().await?;
+    let (mut send, recv) = stream.split();
+
+    let body = Body::from_stream(futures_util::stream::unfold(Some(recv), |recv| async {
+        let mut recv = recv?;
+        match recv.recv_data().await {
+            Ok(Some(mut data)) => Some((Ok(data.copy_to_bytes(data.remaining())), Some(recv))),
+            Ok(None) => None,
+            Err(err) => Some((Err(err), None)),
+        }
+    }));
+
+    let (parts, body) = responder.handle(req.map(|()| body)).await.into_parts();
+    send.send_response(http::Response::from_parts(parts, ()))
+        .await?;
+
+    let mut body = pin!(body);
+    while let Some(frame) = body.frame().await {
+        match frame?.into_data() {
+            Ok(data) => send.send_data(data).await?,
+            Err(frame) => {
+                if let Ok(trailers) = frame.into_trailers() {
+                    send.send_trailers(trailers).await?;
+                }
+            }
+        }
+    }
+
+    send.finish().await?;
+
+    Ok(())
+}
+
+/// Collects the data of the TLS handshake.
+fn extensions(conn: &quinn::Connection) -> http::Extensions {
+    let handshake = conn
+        .handshake_data()
+        .and_then(|data| data.downcast::<HandshakeData>().ok());
+    let info = TlsInfo {
+        server_name: handshake.as_ref().and_then(|data| data.server_name.clone()),
+        alpn: handshake.and_then(|data| data.protocol),
+        version: Some("TLSv1_3".to_string()),
+        cipher_suite: None,
+        peer_certificates: conn
+            .peer_identity()
+            .and_then(|certs| certs.downcast::<Vec<CertificateDer<'static>>>().ok())
+            .map(|certs| {
+                certs
+                    .iter()
+                    .map(|cert| PeerCertificate::new(cert.to_vec()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    let mut extensions = http::Extensions::new();
+    if let Some(name) = &info.server_name {
+        extensions.insert(ServerName(name.clone()));
+    }
+    extensions.insert(info);
+    extensions.insert(http::uri::Scheme::HTTPS);
+    extensions
+}
@@ -0,0 +1,379 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::{
+    body::{Body as HttpBody, Bytes, Frame, SizeHint},
+    service::Service,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+use crate::{Body, Incoming, Request, Response};
+
+/// The connection limits of the [`Server`][crate::Server].
+#[derive(Debug)]
+pub(crate) struct Limits {
+    pub(crate) max_connections: Option<usize>,
+    pub(crate) max_connections_per_ip: Option<usize>,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) backoff: (Duration, Duration),
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            max_connections_per_ip: None,
+            idle_timeout: None,
+            backoff: (Duration::from_millis(5), Duration::from_secs(1)),
+        }
+    }
+}
+
+/// The counters of the [`Server`][crate::Server], which are shared with the running server.
+///
+/// ```
+/// # use tokio::net::TcpListener;
+/// # use vidi::{Result, Router, Server};
+/// # async fn run() -> Result<()> {
+/// let listener = TcpListener::bind("127.0.0.1:3000").await?;
+/// let server = Server::new(listener, Router::new()).max_connections(1024);
+/// let stats = server.stats();
+///
+/// tokio::spawn(async move {
+///     loop {
+///         tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+///         tracing::info!("active connections: {}", stats.active());
+///     }
+/// });
+///
+/// server.await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ServerStats(Arc<Counters>);
+
+#[derive(Debug, Default)]
+struct Counters {
+    active: AtomicUsize,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    accept_errors: AtomicU64,
+    idle_timeouts: AtomicU64,
+}
+
+impl ServerStats {
+    /// Gets the number of the open connections.
+    #[must_use]
+    pub fn active(&self) -> usize {
+        self.0.active.load(Ordering::Relaxed)
+    }
+
+    /// Gets the total number of the accepted connections.
+    #[must_use]
+    pub fn accepted(&self) -> u64 {
+        self.0.accepted.load(Ordering::Relaxed)
+    }
+
+    /// Gets the total number of the connections which are closed by the per-IP limit.
+    #[must_use]
+    pub fn rejected(&self) -> u64 {
+        self.0.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Gets the total number of the failed accepts.
+    #[must_use]
+    pub fn accept_errors(&self) -> u64 {
+        self.0.accept_errors.load(Ordering::Relaxed)
+    }
+
+    /// Gets the total number of the connections which are closed by the idle timeout.
+    #[must_use]
+    pub fn idle_timeouts(&self) -> u64 {
+        self.0.idle_timeouts.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn accept_error(&self) {
+        self.0.accept_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn reject(&self) {
+        self.0.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn idle_timeout(&self) {
+        self.0.idle_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts an accepted connection until the guard is dropped.
+    pub(crate) fn open(&self) -> ActiveGuard {
+        self.0.accepted.fetch_add(1, Ordering::Relaxed);
+        self.0.active.fetch_add(1, Ordering::Relaxed);
+        ActiveGuard(self.0.clone())
+    }
+}
+
+pub(crate) struct ActiveGuard(Arc<Counters>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Waits for a free slot of the maximum connections.
+pub(crate) async fn acquire(semaphore: Option<Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+    // the semaphore is never closed
+    semaphore?.acquire_owned().await.ok()
+}
+
+/// Counts the open connections of each IP.
+#[derive(Debug, Default)]
+pub(crate) struct PerIp(Mutex<HashMap<IpAddr, usize>>);
+
+impl PerIp {
+    /// Counts a connection from the address, `None` if the limit is reached.
+    ///
+    /// The addresses without IP, e.g. the Unix sockets, are not limited.
+    pub(crate) fn open<A: Any>(self: &Arc<Self>, addr: &A, max: usize) -> Option<PerIpGuard> {
+        let Some(ip) = (addr as &dyn Any)
+            .downcast_ref::<SocketAddr>()
+            .map(|addr| addr.ip().to_canonical())
+        else {
+            return Some(PerIpGuard(None));
+        };
+
+        let mut map = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = map.entry(ip).or_default();
+        if *count >= max {
+            return None;
+        }
+        *count += 1;
+        Some(PerIpGuard(Some((self.clone(), ip))))
+    }
+}
+
+pub(crate) struct PerIpGuard(Option<(Arc<PerIp>, IpAddr)>);
+
+impl Drop for PerIpGuard {
+    fn drop(&mut self) {
+        if let Some((per_ip, ip)) = self.0.take() {
+            let mut map = per_ip.0.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(count) = map.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    map.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+/// Records the last time of reading or writing on a connection, and the in-flight requests.
+#[derive(Debug)]
+pub(crate) struct Activity {
+    start: Instant,
+    last: AtomicU64,
+    requests: AtomicUsize,
+}
+
+impl Activity {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            start: Instant::now(),
+            last: AtomicU64::new(0),
+            requests: AtomicUsize::new(0),
+        })
+    }
+
+    fn touch(&self) {
+        let elapsed = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.last.store(elapsed, Ordering::Relaxed);
+    }
+
+    /// Counts an in-flight request until the guard is dropped, the connection is not idle
+    /// meanwhile.
+    fn request(self: &Arc<Self>) -> RequestGuard {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        RequestGuard(self.clone())
+    }
+
+    /// Resolves once the connection is idle for the timeout between the requests.
+    pub(crate) async fn idle(&self, timeout: Duration) {
+        loop {
+            let deadline = if self.requests.load(Ordering::Relaxed) == 0 {
+                let last = self.start + Duration::from_millis(self.last.load(Ordering::Relaxed));
+                last + timeout
+            } else {
+                Instant::now() + timeout
+            };
+            if Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+}
+
+/// A guard of an in-flight request, the activity is touched once it is done.
+#[derive(Debug)]
+struct RequestGuard(Arc<Activity>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.0.touch();
+        self.0.requests.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A service which records the in-flight requests, until their response bodies are sent.
+#[derive(Debug)]
+pub(crate) struct IdleService<S> {
+    inner: S,
+    activity: Option<Arc<Activity>>,
+}
+
+impl<S> IdleService<S> {
+    pub(crate) const fn new(inner: S, activity: Option<Arc<Activity>>) -> Self {
+        Self { inner, activity }
+    }
+}
+
+impl<S> Service<Request<Incoming>> for IdleService<S>
+where
+    S: Service<Request<Incoming>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn call(&self, req: Request<Incoming>) -> Self::Future {
+        let guard = self.activity.as_ref().map(Activity::request);
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            Ok(match guard {
+                Some(guard) if !res.body().is_end_stream() => res.map(|body| {
+                    Body::wrap(InFlightBody {
+                        body,
+                        _guard: guard,
+                    })
+                }),
+                _ => res,
+            })
+        })
+    }
+}
+
+/// A response body which keeps the request in-flight until it is dropped.
+struct InFlightBody {
+    body: Body,
+    _guard: RequestGuard,
+}
+
+impl HttpBody for InFlightBody {
+    type Data = Bytes;
+    type Error = <Body as HttpBody>::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+/// A stream which records the activity.
+pub(crate) struct IdleIo<IO> {
+    io: IO,
+    activity: Option<Arc<Activity>>,
+}
+
+impl<IO> IdleIo<IO> {
+    pub(crate) const fn new(io: IO, activity: Option<Arc<Activity>>) -> Self {
+        Self { io, activity }
+    }
+
+    fn touch<T>(&self, poll: &Poll<T>) {
+        if let (Some(activity), Poll::Ready(_)) = (&self.activity, poll) {
+            activity.touch();
+        }
+    }
+}
+
+impl<IO> AsyncRead for IdleIo<IO>
+where
+    IO: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.io).poll_read(cx, buf);
+        self.touch(&poll);
+        poll
+    }
+}
+
+impl<IO> AsyncWrite for IdleIo<IO>
+where
+    IO: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.io).poll_write(cx, buf);
+        self.touch(&poll);
+        poll
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.io).poll_write_vectored(cx, bufs);
+        self.touch(&poll);
+        poll
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
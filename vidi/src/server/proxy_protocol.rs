@@ -0,0 +1,473 @@
+//! A listener wrapper for the [PROXY protocol] v1 and v2.
+//!
+//! The load balancers send the address of the client in a header before the data of the
+//! connection, the [`ProxyListener`] reads the header and returns the client's address as the
+//! remote address. It wraps a TCP listener and is wrapped by the TLS listeners.
+//!
+//! No source is trusted by default, the addresses of the load balancers must be set by
+//! [`ProxyListener::trusted`].
+//!
+//! ```no_run
+//! use tokio::net::TcpListener;
+//! use vidi::{Request, RequestExt, Result, Router, proxy_protocol::ProxyListener, serve};
+//!
+//! # async fn run() -> Result<()> {
+//! let app = Router::new().get("/", |req: Request| async move {
+//!     Ok(req
+//!         .remote_addr()
+//!         .map(ToString::to_string)
+//!         .unwrap_or_default())
+//! });
+//!
+//! let listener = ProxyListener::new(TcpListener::bind("0.0.0.0:8080").await?)
+//!     .trusted(["10.0.0.0/8".parse().unwrap()]);
+//! serve(listener, app).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [PROXY protocol]: https://www.haproxy.org/download/3.0/doc/proxy-protocol.txt
+
+use std::{
+    fmt::Debug,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf},
+    sync::Mutex,
+    task::JoinSet,
+};
+
+use crate::{Error, FromRequest, IntoResponse, Listener, Request, StatusCode, types::Cidr};
+
+/// The signature of the v2 header.
+const SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The maximum length of the v1 header.
+const V1_MAX_LEN: usize = 107;
+
+/// The parsed PROXY protocol header.
+///
+/// It is added into the extensions of the requests by the [`ProxyListener`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// The version of the protocol, `1` or `2`.
+    pub version: u8,
+    /// The address of the client, `None` for the `LOCAL` command or the unknown protocols.
+    pub source: Option<SocketAddr>,
+    /// The address which the client connected to.
+    pub destination: Option<SocketAddr>,
+    /// The Type-Length-Value vectors of the v2 header.
+    pub tlvs: Vec<(u8, Vec<u8>)>,
+}
+
+impl ProxyHeader {
+    /// `PP2_TYPE_ALPN`
+    pub const ALPN: u8 = 0x01;
+    /// `PP2_TYPE_AUTHORITY`
+    pub const AUTHORITY: u8 = 0x02;
+    /// `PP2_TYPE_CRC32C`
+    pub const CRC32C: u8 = 0x03;
+    /// `PP2_TYPE_NOOP`
+    pub const NOOP: u8 = 0x04;
+    /// `PP2_TYPE_UNIQUE_ID`
+    pub const UNIQUE_ID: u8 = 0x05;
+    /// `PP2_TYPE_SSL`
+    pub const SSL: u8 = 0x20;
+    /// `PP2_TYPE_NETNS`
+    pub const NETNS: u8 = 0x30;
+
+    /// Gets the value of the first TLV with the type.
+    #[must_use]
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find_map(|(k, v)| (*k == kind).then_some(v.as_slice()))
+    }
+
+    /// Gets the ALPN protocol which is negotiated by the client and the proxy.
+    #[must_use]
+    pub fn alpn(&self) -> Option<&[u8]> {
+        self.tlv(Self::ALPN)
+    }
+
+    /// Gets the host name which is sent by the client via SNI.
+    #[must_use]
+    pub fn authority(&self) -> Option<&str> {
+        self.tlv(Self::AUTHORITY)
+            .and_then(|v| std::str::from_utf8(v).ok())
+    }
+
+    /// Gets the unique ID of the connection which is generated by the proxy.
+    #[must_use]
+    pub fn unique_id(&self) -> Option<&[u8]> {
+        self.tlv(Self::UNIQUE_ID)
+    }
+}
+
+impl FromRequest for ProxyHeader {
+    type Error = Error;
+
+    async fn extract(req: &mut Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get()
+            .cloned()
+            .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "Missing proxy header").into_error())
+    }
+}
+
+/// A listener which reads the PROXY protocol header of the connections.
+///
+/// The connections from the trusted sources must start with a v1 or v2 header, otherwise they
+/// are dropped. The connections from the other sources are passed through without reading the
+/// header, so the clients can not spoof their addresses.
+///
+/// The headers are read in the background, a slow or invalid connection does not block the
+/// others.
+pub struct ProxyListener<L: Listener> {
+    inner: L,
+    trusted: Vec<Cidr>,
+    timeout: Duration,
+    pending: Mutex<JoinSet<Option<(ProxyStream<L::Io>, SocketAddr)>>>,
+}
+
+impl<L: Listener> ProxyListener<L> {
+    /// Creates a new listener which trusts none of the sources.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            trusted: Vec::new(),
+            timeout: Duration::from_secs(5),
+            pending: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    /// Only reads the header from the trusted sources, e.g. the addresses of the load balancers.
+    #[must_use]
+    pub fn trusted<I>(mut self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = Cidr>,
+    {
+        self.trusted.extend(sources);
+        self
+    }
+
+    /// Sets the timeout for reading the header.
+    ///
+    /// Default is 5 seconds.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Gets the listener.
+    pub const fn get_ref(&self) -> &L {
+        &self.inner
+    }
+
+    fn is_trusted(&self, addr: &SocketAddr) -> bool {
+        self.trusted.iter().any(|cidr| cidr.contains(&addr.ip()))
+    }
+}
+
+impl<L: Listener + Debug> Debug for ProxyListener<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyListener")
+            .field("inner", &self.inner)
+            .field("trusted", &self.trusted)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<L> Listener for ProxyListener<L>
+where
+    L: Listener<Addr = SocketAddr> + Sync,
+    L::Io: AsyncRead + Unpin + Send + 'static,
+{
+    type Io = ProxyStream<L::Io>;
+    type Addr = SocketAddr;
+
+    async fn accept(&self) -> IoResult<(Self::Io, Self::Addr)> {
+        let mut pending = self.pending.lock().await;
+
+        loop {
+            tokio::select! {
+                Some(res) = pending.join_next() => {
+                    if let Ok(Some(conn)) = res {
+                        return Ok(conn);
+                    }
+                }
+                conn = self.inner.accept() => {
+                    let (stream, addr) = conn?;
+
+                    if !self.is_trusted(&addr) {
+                        return Ok((
+                            ProxyStream {
+                                inner: stream,
+                                header: None,
+                            },
+                            addr,
+                        ));
+                    }
+
+                    pending.spawn(handshake(stream, addr, self.timeout));
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> IoResult<Self::Addr> {
+        self.inner.local_addr()
+    }
+
+    fn extensions(&self, io: &Self::Io) -> http::Extensions {
+        let mut extensions = self.inner.extensions(&io.inner);
+        if let Some(header) = &io.header {
+            extensions.insert(header.clone());
+        }
+        extensions
+    }
+}
+
+/// A stream which has been read the PROXY protocol header.
+#[derive(Debug)]
+pub struct ProxyStream<IO> {
+    inner: IO,
+    header: Option<ProxyHeader>,
+}
+
+impl<IO> ProxyStream<IO> {
+    /// Gets the stream.
+    pub const fn get_ref(&self) -> &IO {
+        &self.inner
+    }
+
+    /// Gets the header, `None` if the source is not trusted.
+    pub const fn header(&self) -> Option<&ProxyHeader> {
+        self.header.as_ref()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for ProxyStream<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for ProxyStream<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+/// Reads the header of a connection from a trusted source, `None` if it is invalid or timed out.
+async fn handshake<IO>(
+    mut stream: IO,
+    addr: SocketAddr,
+    timeout: Duration,
+) -> Option<(ProxyStream<IO>, SocketAddr)>
+where
+    IO: AsyncRead + Unpin,
+{
+    let header = match tokio::time::timeout(timeout, read_header(&mut stream)).await {
+        Ok(Ok(header)) => header,
+        Ok(Err(err)) => {
+            tracing::trace!("invalid proxy protocol header: {addr}, {err}");
+            return None;
+        }
+        Err(_) => {
+            tracing::trace!("proxy protocol header timed out: {addr}");
+            return None;
+        }
+    };
+
+    let source = header.source.unwrap_or(addr);
+    Some((
+        ProxyStream {
+            inner: stream,
+            header: Some(header),
+        },
+        source,
+    ))
+}
+
+fn invalid(msg: &'static str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg)
+}
+
+/// Reads the header exactly, the following data are kept in the stream.
+async fn read_header<IO>(stream: &mut IO) -> IoResult<ProxyHeader>
+where
+    IO: AsyncRead + Unpin,
+{
+    let mut buf = [0; 16];
+    stream.read_exact(&mut buf[..12]).await?;
+
+    if &buf[..12] == SIGNATURE {
+        stream.read_exact(&mut buf[12..]).await?;
+        let mut payload = vec![0; usize::from(u16::from_be_bytes([buf[14], buf[15]]))];
+        stream.read_exact(&mut payload).await?;
+        return parse_v2(buf[12], buf[13], &payload);
+    }
+
+    if !buf.starts_with(b"PROXY ") {
+        return Err(invalid("invalid proxy protocol header"));
+    }
+
+    let mut line = buf[..12].to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() == V1_MAX_LEN {
+            return Err(invalid("proxy protocol v1 header is too long"));
+        }
+        line.push(stream.read_u8().await?);
+    }
+    parse_v1(&line[..line.len() - 2])
+}
+
+/// Parses the v1 line without the `\r\n`, e.g. `PROXY TCP4 1.1.1.1 2.2.2.2 1000 80`.
+fn parse_v1(line: &[u8]) -> IoResult<ProxyHeader> {
+    let line =
+        std::str::from_utf8(line).map_err(|_| invalid("invalid proxy protocol v1 header"))?;
+    let mut parts = line.split(' ').skip(1);
+
+    let mut header = ProxyHeader {
+        version: 1,
+        ..ProxyHeader::default()
+    };
+
+    let ipv6 = match parts.next() {
+        Some("TCP4") => false,
+        Some("TCP6") => true,
+        Some("UNKNOWN") => return Ok(header),
+        _ => return Err(invalid("invalid proxy protocol v1 family")),
+    };
+
+    let mut next = || {
+        parts
+            .next()
+            .ok_or_else(|| invalid("invalid proxy protocol v1 header"))
+    };
+    let (src, dst, src_port, dst_port) = (next()?, next()?, next()?, next()?);
+    if parts.next().is_some() {
+        return Err(invalid("invalid proxy protocol v1 header"));
+    }
+
+    // the addresses must match the family
+    let addr = |ip: &str, port: &str| -> IoResult<SocketAddr> {
+        Ok(SocketAddr::new(
+            ip.parse::<IpAddr>()
+                .ok()
+                .filter(|ip| ip.is_ipv6() == ipv6)
+                .ok_or_else(|| invalid("invalid proxy protocol v1 address"))?,
+            port.parse()
+                .map_err(|_| invalid("invalid proxy protocol v1 port"))?,
+        ))
+    };
+    header.source = Some(addr(src, src_port)?);
+    header.destination = Some(addr(dst, dst_port)?);
+
+    Ok(header)
+}
+
+/// Parses the v2 payload after the 16 bytes.
+fn parse_v2(ver_cmd: u8, family: u8, payload: &[u8]) -> IoResult<ProxyHeader> {
+    if ver_cmd >> 4 != 2 {
+        return Err(invalid("invalid proxy protocol v2 version"));
+    }
+
+    let mut header = ProxyHeader {
+        version: 2,
+        ..ProxyHeader::default()
+    };
+
+    let local = match ver_cmd & 0x0f {
+        0x00 => true,
+        0x01 => false,
+        _ => return Err(invalid("invalid proxy protocol v2 command")),
+    };
+
+    let len = match family >> 4 {
+        // AF_INET
+        0x1 => 12,
+        // AF_INET6
+        0x2 => 36,
+        // AF_UNIX
+        0x3 => 216,
+        // AF_UNSPEC
+        _ => 0,
+    };
+    if payload.len() < len {
+        return Err(invalid("invalid proxy protocol v2 address"));
+    }
+    let (addrs, mut tlvs) = payload.split_at(len);
+
+    if !local {
+        let port = |i: usize| u16::from_be_bytes([addrs[i], addrs[i + 1]]);
+        match family >> 4 {
+            0x1 => {
+                let ip =
+                    |i: usize| Ipv4Addr::new(addrs[i], addrs[i + 1], addrs[i + 2], addrs[i + 3]);
+                header.source = Some(SocketAddr::new(ip(0).into(), port(8)));
+                header.destination = Some(SocketAddr::new(ip(4).into(), port(10)));
+            }
+            0x2 => {
+                let ip = |i: usize| {
+                    let mut octets = [0; 16];
+                    octets.copy_from_slice(&addrs[i..i + 16]);
+                    Ipv6Addr::from(octets)
+                };
+                header.source = Some(SocketAddr::new(ip(0).into(), port(32)));
+                header.destination = Some(SocketAddr::new(ip(16).into(), port(34)));
+            }
+            _ => {}
+        }
+    }
+
+    while !tlvs.is_empty() {
+        let [kind, hi, lo, rest @ ..] = tlvs else {
+            return Err(invalid("invalid proxy protocol v2 tlv"));
+        };
+        let (value, rest) = rest
+            .split_at_checked(usize::from(u16::from_be_bytes([*hi, *lo])))
+            .ok_or_else(|| invalid("invalid proxy protocol v2 tlv"))?;
+        header.tlvs.push((*kind, value.to_vec()));
+        tlvs = rest;
+    }
+
+    Ok(header)
+}
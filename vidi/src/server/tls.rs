@@ -8,6 +8,136 @@ pub mod native_tls;
 #[cfg(feature = "rustls")]
 pub mod rustls;
 
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+use crate::{Error, FromRequest, IntoResponse, Request, StatusCode};
+
+/// The server name of the TLS connection which is sent by the client via SNI.
+///
+/// It is added into the extensions of the requests by the `rustls` listeners.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerName(pub String);
+
+/// A DER-encoded X.509 certificate of the peer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerCertificate(Vec<u8>);
+
+/// A subject alternative name of the certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubjectAltName {
+    /// `DNS:`
+    Dns(String),
+    /// `email:`
+    Email(String),
+    /// `URI:`
+    Uri(String),
+    /// `IP:`
+    Ip(IpAddr),
+}
+
+impl PeerCertificate {
+    /// Creates a new certificate with the DER-encoded bytes.
+    #[must_use]
+    pub const fn new(der: Vec<u8>) -> Self {
+        Self(der)
+    }
+
+    /// Gets the DER-encoded bytes.
+    #[must_use]
+    pub fn der(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Gets the subject in the order of the certificate, e.g. `CN=vidi, O=Vidi`.
+    ///
+    /// Returns `None` if the certificate is malformed.
+    #[must_use]
+    pub fn subject(&self) -> Option<String> {
+        self.parse()?
+            .subject()
+            .to_string_with_registry(x509_parser::objects::oid_registry())
+            .ok()
+    }
+
+    /// Gets the subject alternative names.
+    #[must_use]
+    pub fn subject_alt_names(&self) -> Vec<SubjectAltName> {
+        let Some(cert) = self.parse() else {
+            return Vec::new();
+        };
+        let Ok(Some(names)) = cert.subject_alternative_name() else {
+            return Vec::new();
+        };
+
+        names
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(dns) => Some(SubjectAltName::Dns((*dns).to_string())),
+                GeneralName::RFC822Name(email) => Some(SubjectAltName::Email((*email).to_string())),
+                GeneralName::URI(uri) => Some(SubjectAltName::Uri((*uri).to_string())),
+                GeneralName::IPAddress(ip) => match ip.len() {
+                    4 => <[u8; 4]>::try_from(*ip)
+                        .ok()
+                        .map(|ip| SubjectAltName::Ip(Ipv4Addr::from(ip).into())),
+                    16 => <[u8; 16]>::try_from(*ip)
+                        .ok()
+                        .map(|ip| SubjectAltName::Ip(Ipv6Addr::from(ip).into())),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn parse(&self) -> Option<X509Certificate<'_>> {
+        X509Certificate::from_der(&self.0)
+            .ok()
+            .map(|(_, cert)| cert)
+    }
+}
+
+/// The information of the TLS connection.
+///
+/// It is added into the extensions of the requests by the TLS listeners, the fields which are
+/// not supported by the backend are empty, e.g. `native-tls` only provides the leaf
+/// certificate of the peer.
+#[derive(Clone, Debug, Default)]
+pub struct TlsInfo {
+    /// The server name which is sent by the client via SNI.
+    pub server_name: Option<String>,
+    /// The negotiated ALPN protocol, e.g. `h2`.
+    pub alpn: Option<Vec<u8>>,
+    /// The negotiated TLS version, e.g. `TLSv1_3`.
+    pub version: Option<String>,
+    /// The negotiated cipher suite, e.g. `TLS13_AES_256_GCM_SHA384`.
+    pub cipher_suite: Option<String>,
+    /// The certificate chain of the client, the leaf certificate is the first.
+    pub peer_certificates: Vec<PeerCertificate>,
+}
+
+impl TlsInfo {
+    /// Gets the leaf certificate of the client.
+    #[must_use]
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.peer_certificates.first()
+    }
+}
+
+impl FromRequest for TlsInfo {
+    type Error = Error;
+
+    async fn extract(req: &mut Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get()
+            .cloned()
+            .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "Missing tls info").into_error())
+    }
+}
+
 /// Unified TLS listener type.
 #[derive(Debug)]
 pub struct TlsListener<T, A> {
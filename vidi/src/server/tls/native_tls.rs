@@ -1,9 +1,12 @@
-use std::{fmt, io::Result as IoResult, net::SocketAddr};
+use std::{fmt, io::Result as IoResult};
 
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_native_tls::{TlsStream, native_tls::TlsAcceptor as TlsAcceptorWrapper};
 
-use crate::{Error, Result};
+use crate::{
+    Error, Result,
+    tls::{PeerCertificate, TlsInfo},
+};
 
 pub use tokio_native_tls::{TlsAcceptor, native_tls::Identity};
 
@@ -37,9 +40,14 @@ impl Config {
     }
 }
 
-impl crate::Listener for crate::tls::TlsListener<TcpListener, TlsAcceptor> {
-    type Io = TlsStream<TcpStream>;
-    type Addr = SocketAddr;
+impl<L> crate::Listener for crate::tls::TlsListener<L, TlsAcceptor>
+where
+    L: crate::Listener + Sync,
+    L::Io: AsyncRead + AsyncWrite + Unpin + Send,
+    L::Addr: Send,
+{
+    type Io = TlsStream<L::Io>;
+    type Addr = L::Addr;
 
     async fn accept(&self) -> IoResult<(Self::Io, Self::Addr)> {
         let (stream, addr) = self.inner.accept().await?;
@@ -54,4 +62,24 @@ impl crate::Listener for crate::tls::TlsListener<TcpListener, TlsAcceptor> {
     fn local_addr(&self) -> IoResult<Self::Addr> {
         self.inner.local_addr()
     }
+
+    fn extensions(&self, io: &Self::Io) -> http::Extensions {
+        let info = TlsInfo {
+            peer_certificates: io
+                .get_ref()
+                .peer_certificate()
+                .ok()
+                .flatten()
+                .and_then(|cert| cert.to_der().ok())
+                .map(PeerCertificate::new)
+                .into_iter()
+                .collect(),
+            ..TlsInfo::default()
+        };
+
+        let mut extensions = self.inner.extensions(io.get_ref().get_ref().get_ref());
+        extensions.insert(info);
+        extensions.insert(http::uri::Scheme::HTTPS);
+        extensions
+    }
 }
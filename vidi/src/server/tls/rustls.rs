@@ -1,17 +1,33 @@
 use std::{
+    collections::HashMap,
     io::{Error as IoError, ErrorKind, Result as IoResult},
-    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime},
 };
 
-use tokio::net::{TcpListener, TcpStream};
+use futures_util::{Stream, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    task::JoinHandle,
+};
 use tokio_rustls::{
-    rustls::{RootCertStore, ServerConfig, pki_types::PrivateKeyDer, server::WebPkiClientVerifier},
+    rustls::{
+        RootCertStore,
+        crypto::{CryptoProvider, aws_lc_rs},
+        pki_types::{CertificateDer, PrivateKeyDer},
+        server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier},
+        sign::CertifiedKey,
+    },
     server::TlsStream,
 };
 
-use crate::{Error, Result};
+use crate::{
+    Error, Result,
+    tls::{PeerCertificate, ServerName, TlsInfo},
+};
 
-pub use tokio_rustls::TlsAcceptor;
+pub use tokio_rustls::{TlsAcceptor, rustls::ServerConfig};
 
 /// Tls client authentication configuration.
 #[derive(Debug)]
@@ -31,6 +47,9 @@ pub struct Config {
     key: Vec<u8>,
     ocsp_resp: Vec<u8>,
     client_auth: ClientAuth,
+    sni: Vec<(String, Vec<u8>, Vec<u8>)>,
+    alpn_protocols: Vec<Vec<u8>>,
+    provider: Option<Arc<CryptoProvider>>,
 }
 
 impl Default for Config {
@@ -48,6 +67,15 @@ impl Config {
             key: Vec::new(),
             client_auth: ClientAuth::Off,
             ocsp_resp: Vec::new(),
+            sni: Vec::new(),
+            alpn_protocols: [
+                #[cfg(feature = "http2")]
+                b"h2".to_vec(),
+                #[cfg(feature = "http1")]
+                b"http/1.1".to_vec(),
+            ]
+            .into(),
+            provider: None,
         }
     }
 
@@ -86,6 +114,47 @@ impl Config {
         self
     }
 
+    /// Adds a certificate and a key for the SNI hostname, e.g. `vidi.rs` or `*.vidi.rs`.
+    ///
+    /// The wildcard matches a single label, the certificate set by [`cert`][Self::cert] and
+    /// [`key`][Self::key] is the fallback for the unknown or missing hostnames.
+    #[must_use]
+    pub fn sni(
+        mut self,
+        hostname: impl Into<String>,
+        cert: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.sni.push((
+            hostname.into().to_ascii_lowercase(),
+            cert.into(),
+            key.into(),
+        ));
+        self
+    }
+
+    /// Sets the ALPN protocols in the order of preference.
+    ///
+    /// Default is `h2` and `http/1.1` depending on the enabled `http2` and `http1` features.
+    #[must_use]
+    pub fn alpn_protocols<I, P>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Vec<u8>>,
+    {
+        self.alpn_protocols = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the crypto provider.
+    ///
+    /// Default is the process-level provider if it is installed, otherwise `aws-lc-rs`.
+    #[must_use]
+    pub fn crypto_provider(mut self, provider: impl Into<Arc<CryptoProvider>>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
     /// builds the Tls `ServerConfig`
     ///
     /// # Errors
@@ -101,64 +170,348 @@ impl Config {
             Ok(store)
         }
 
-        let certs = rustls_pemfile::certs(&mut self.cert.as_slice())
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(Error::boxed)?;
+        // the process-level provider is ambiguous if several providers are compiled in
+        let provider = self
+            .provider
+            .or_else(|| CryptoProvider::get_default().cloned())
+            .unwrap_or_else(|| Arc::new(aws_lc_rs::default_provider()));
+
+        let client_auth = match self.client_auth {
+            ClientAuth::Off => WebPkiClientVerifier::no_client_auth(),
+            ClientAuth::Optional(trust_anchor) => WebPkiClientVerifier::builder_with_provider(
+                read_trust_anchor(&trust_anchor)?.into(),
+                provider.clone(),
+            )
+            .allow_unauthenticated()
+            .build()
+            .map_err(Error::boxed)?,
+            ClientAuth::Required(trust_anchor) => WebPkiClientVerifier::builder_with_provider(
+                read_trust_anchor(&trust_anchor)?.into(),
+                provider.clone(),
+            )
+            .build()
+            .map_err(Error::boxed)?,
+        };
+
+        let builder = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(Error::boxed)?
+            .with_client_cert_verifier(client_auth);
 
-        let keys = {
-            let mut pkcs8 = rustls_pemfile::pkcs8_private_keys(&mut self.key.as_slice())
-                .collect::<Result<Vec<_>, _>>()
+        if self.sni.is_empty() {
+            let mut config = builder
+                .with_single_cert_with_ocsp(
+                    read_certs(&self.cert)?,
+                    read_key(&self.key)?,
+                    self.ocsp_resp,
+                )
                 .map_err(Error::boxed)?;
-            if pkcs8.is_empty() {
-                let mut rsa = rustls_pemfile::rsa_private_keys(&mut self.key.as_slice())
-                    .collect::<Result<Vec<_>, _>>()
-                    .map_err(Error::boxed)?;
-
-                if rsa.is_empty() {
-                    return Err(Error::boxed(IoError::new(
-                        ErrorKind::InvalidData,
-                        "failed to parse tls private keys",
-                    )));
-                }
-                PrivateKeyDer::Pkcs1(rsa.remove(0))
-            } else {
-                PrivateKeyDer::Pkcs8(pkcs8.remove(0))
+            config.alpn_protocols = self.alpn_protocols;
+            return Ok(config);
+        }
+
+        let provider = builder.crypto_provider();
+        let mut resolver = SniResolver::default();
+
+        if !self.cert.is_empty() {
+            let mut default = certified_key(&self.cert, &self.key, provider)?;
+            if !self.ocsp_resp.is_empty() {
+                default.ocsp = Some(self.ocsp_resp);
             }
-        };
+            resolver.default = Some(Arc::new(default));
+        }
 
-        let client_auth = match self.client_auth {
-            ClientAuth::Off => WebPkiClientVerifier::no_client_auth(),
-            ClientAuth::Optional(trust_anchor) => {
-                WebPkiClientVerifier::builder(read_trust_anchor(&trust_anchor)?.into())
-                    .allow_unauthenticated()
-                    .build()
-                    .map_err(Error::boxed)?
+        for (hostname, cert, key) in self.sni {
+            let certified_key = Arc::new(certified_key(&cert, &key, provider)?);
+            match hostname.strip_prefix("*.") {
+                Some(suffix) => resolver.wildcards.insert(suffix.to_string(), certified_key),
+                None => resolver.names.insert(hostname, certified_key),
+            };
+        }
+
+        let mut config = builder.with_cert_resolver(Arc::new(resolver));
+        config.alpn_protocols = self.alpn_protocols;
+        Ok(config)
+    }
+}
+
+fn read_certs(mut cert: &[u8]) -> Result<Vec<CertificateDer<'static>>> {
+    rustls_pemfile::certs(&mut cert)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::boxed)
+}
+
+fn read_key(key: &[u8]) -> Result<PrivateKeyDer<'static>> {
+    let mut pkcs8 = rustls_pemfile::pkcs8_private_keys(&mut &key[..])
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::boxed)?;
+    if !pkcs8.is_empty() {
+        return Ok(PrivateKeyDer::Pkcs8(pkcs8.remove(0)));
+    }
+
+    let mut rsa = rustls_pemfile::rsa_private_keys(&mut &key[..])
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::boxed)?;
+    if rsa.is_empty() {
+        return Err(Error::boxed(IoError::new(
+            ErrorKind::InvalidData,
+            "failed to parse tls private keys",
+        )));
+    }
+    Ok(PrivateKeyDer::Pkcs1(rsa.remove(0)))
+}
+
+fn certified_key(cert: &[u8], key: &[u8], provider: &CryptoProvider) -> Result<CertifiedKey> {
+    CertifiedKey::from_der(read_certs(cert)?, read_key(key)?, provider).map_err(Error::boxed)
+}
+
+/// Selects the certificate by the SNI hostname.
+#[derive(Debug, Default)]
+struct SniResolver {
+    names: HashMap<String, Arc<CertifiedKey>>,
+    wildcards: HashMap<String, Arc<CertifiedKey>>,
+    default: Option<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        client_hello
+            .server_name()
+            .map(str::to_ascii_lowercase)
+            .and_then(|name| {
+                self.names.get(&name).or_else(|| {
+                    name.split_once('.')
+                        .and_then(|(_, suffix)| self.wildcards.get(suffix))
+                })
+            })
+            .or(self.default.as_ref())
+            .cloned()
+    }
+}
+
+impl<L> crate::Listener for crate::tls::TlsListener<L, TlsAcceptor>
+where
+    L: crate::Listener + Sync,
+    L::Io: AsyncRead + AsyncWrite + Unpin + Send,
+    L::Addr: Send,
+{
+    type Io = TlsStream<L::Io>;
+    type Addr = L::Addr;
+
+    async fn accept(&self) -> IoResult<(Self::Io, Self::Addr)> {
+        let (stream, addr) = self.inner.accept().await?;
+        let stream = self.acceptor.accept(stream).await?;
+        Ok((stream, addr))
+    }
+
+    fn local_addr(&self) -> IoResult<Self::Addr> {
+        self.inner.local_addr()
+    }
+
+    fn extensions(&self, io: &Self::Io) -> http::Extensions {
+        extensions(&self.inner, io)
+    }
+}
+
+/// Collects the data of the inner and the TLS connection.
+fn extensions<L: crate::Listener>(inner: &L, io: &TlsStream<L::Io>) -> http::Extensions {
+    let (io, conn) = io.get_ref();
+    let info = TlsInfo {
+        server_name: conn.server_name().map(ToString::to_string),
+        alpn: conn.alpn_protocol().map(<[u8]>::to_vec),
+        version: conn
+            .protocol_version()
+            .and_then(|version| version.as_str())
+            .map(ToString::to_string),
+        cipher_suite: conn
+            .negotiated_cipher_suite()
+            .and_then(|suite| suite.suite().as_str())
+            .map(ToString::to_string),
+        peer_certificates: conn
+            .peer_certificates()
+            .unwrap_or_default()
+            .iter()
+            .map(|cert| PeerCertificate::new(cert.to_vec()))
+            .collect(),
+    };
+
+    let mut extensions = inner.extensions(io);
+    if let Some(name) = &info.server_name {
+        extensions.insert(ServerName(name.clone()));
+    }
+    extensions.insert(info);
+    extensions.insert(http::uri::Scheme::HTTPS);
+    extensions
+}
+
+/// A TLS acceptor whose [`ServerConfig`] can be swapped without restarting the server.
+///
+/// The new handshakes use the current config, the established connections are not affected.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use vidi::tls::{
+///     TlsListener,
+///     rustls::{Config, ReloadableAcceptor},
+/// };
+///
+/// # async fn run() -> vidi::Result<()> {
+/// let build = || {
+///     Config::new()
+///         .cert(std::fs::read("cert.pem")?)
+///         .key(std::fs::read("key.pem")?)
+///         .build()
+/// };
+///
+/// let acceptor = ReloadableAcceptor::new(build()?);
+/// acceptor.watch(["cert.pem", "key.pem"], Duration::from_secs(10), build);
+///
+/// let listener = TlsListener::new(
+///     tokio::net::TcpListener::bind("0.0.0.0:443").await?,
+///     acceptor,
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReloadableAcceptor(Arc<RwLock<Arc<ServerConfig>>>);
+
+impl ReloadableAcceptor {
+    /// Creates a new acceptor with the initial [`ServerConfig`].
+    #[must_use]
+    pub fn new(config: impl Into<Arc<ServerConfig>>) -> Self {
+        Self(Arc::new(RwLock::new(config.into())))
+    }
+
+    /// Gets the current [`ServerConfig`].
+    #[must_use]
+    pub fn config(&self) -> Arc<ServerConfig> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Replaces the [`ServerConfig`] and returns the previous one.
+    pub fn swap(&self, config: impl Into<Arc<ServerConfig>>) -> Arc<ServerConfig> {
+        std::mem::replace(
+            &mut *self.0.write().unwrap_or_else(PoisonError::into_inner),
+            config.into(),
+        )
+    }
+
+    /// Builds a new [`ServerConfig`] and swaps it in.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if building fails, the previous config is kept.
+    pub fn reload<F>(&self, build: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<ServerConfig>,
+    {
+        match build() {
+            Ok(config) => {
+                self.swap(config);
+                tracing::info!("tls config reloaded");
+                Ok(())
             }
-            ClientAuth::Required(trust_anchor) => {
-                WebPkiClientVerifier::builder(read_trust_anchor(&trust_anchor)?.into())
-                    .build()
-                    .map_err(Error::boxed)?
+            Err(err) => {
+                tracing::error!("failed to reload tls config, keeping the previous one: {err}");
+                Err(err)
             }
-        };
+        }
+    }
 
-        ServerConfig::builder()
-            .with_client_cert_verifier(client_auth)
-            .with_single_cert_with_ocsp(certs, keys, self.ocsp_resp)
-            .map_err(Error::boxed)
+    /// Reloads the [`ServerConfig`] each time the trigger yields, e.g. a channel or a
+    /// signal stream.
+    ///
+    /// The task ends when the trigger ends.
+    pub fn reload_on<S, F>(&self, trigger: S, build: F) -> JoinHandle<()>
+    where
+        S: Stream + Send + 'static,
+        F: Fn() -> Result<ServerConfig> + Send + 'static,
+    {
+        let acceptor = self.clone();
+        tokio::spawn(async move {
+            let mut trigger = std::pin::pin!(trigger);
+            while trigger.next().await.is_some() {
+                let _ = acceptor.reload(&build);
+            }
+        })
+    }
+
+    /// Watches the modification time of the files and reloads the [`ServerConfig`] when any
+    /// of them changes.
+    ///
+    /// The task runs until the returned handle is aborted.
+    pub fn watch<I, F>(&self, paths: I, interval: Duration, build: F) -> JoinHandle<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<PathBuf>,
+        F: Fn() -> Result<ServerConfig> + Send + 'static,
+    {
+        async fn modified(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+            let mut times = Vec::with_capacity(paths.len());
+            for path in paths {
+                times.push(
+                    tokio::fs::metadata(path)
+                        .await
+                        .and_then(|m| m.modified())
+                        .ok(),
+                );
+            }
+            times
+        }
+
+        let paths = paths.into_iter().map(Into::into).collect::<Vec<_>>();
+        let acceptor = self.clone();
+
+        tokio::spawn(async move {
+            let mut last = modified(&paths).await;
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                let current = modified(&paths).await;
+                if current != last {
+                    last = current;
+                    let _ = acceptor.reload(&build);
+                }
+            }
+        })
     }
 }
 
-impl crate::Listener for crate::tls::TlsListener<TcpListener, TlsAcceptor> {
-    type Io = TlsStream<TcpStream>;
-    type Addr = SocketAddr;
+impl From<ServerConfig> for ReloadableAcceptor {
+    fn from(config: ServerConfig) -> Self {
+        Self::new(config)
+    }
+}
+
+impl<L> crate::Listener for crate::tls::TlsListener<L, ReloadableAcceptor>
+where
+    L: crate::Listener + Sync,
+    L::Io: AsyncRead + AsyncWrite + Unpin + Send,
+    L::Addr: Send,
+{
+    type Io = TlsStream<L::Io>;
+    type Addr = L::Addr;
 
     async fn accept(&self) -> IoResult<(Self::Io, Self::Addr)> {
         let (stream, addr) = self.inner.accept().await?;
-        let stream = self.acceptor.accept(stream).await?;
+        let stream = TlsAcceptor::from(self.acceptor.config())
+            .accept(stream)
+            .await?;
         Ok((stream, addr))
     }
 
     fn local_addr(&self) -> IoResult<Self::Addr> {
         self.inner.local_addr()
     }
+
+    fn extensions(&self, io: &Self::Io) -> http::Extensions {
+        extensions(&self.inner, io)
+    }
 }
@@ -0,0 +1,175 @@
+//! Socket activation test cases
+
+#![cfg(all(target_os = "linux", feature = "socket-activation"))]
+
+use std::{
+    net::SocketAddr,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+};
+use vidi::{
+    Error, Request, Result, Router,
+    activation::{self, HANDOFF_ENV, Inherited, Upgrader},
+    serve,
+};
+
+async fn get(addr: SocketAddr, path: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n").as_bytes(),
+        )
+        .await?;
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).await?;
+    Ok(buf
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default())
+}
+
+/// The new process which is started by `activation_handoff`.
+#[tokio::test]
+async fn activation_child() -> Result<()> {
+    if std::env::var_os(HANDOFF_ENV).is_none() {
+        assert!(activation::listen_fds()?.is_empty());
+        return Ok(());
+    }
+
+    let mut inherited = Inherited::from_env().await?;
+    assert_eq!(inherited.len(), 1);
+    let listener = inherited
+        .take("http")
+        .ok_or_else(|| Error::boxed(std::io::Error::other("missing `http`")))?
+        .into_tcp()?;
+
+    let server = serve(
+        listener,
+        Router::new().get("/", |_: Request| async { Ok("new") }),
+    );
+    inherited.ready().await?;
+
+    // killed by the old process
+    let _ = tokio::time::timeout(Duration::from_secs(10), server.into_future()).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn activation_handoff() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let upgrader = Upgrader::new()
+        .listener("http", &listener)?
+        .timeout(Duration::from_secs(10));
+
+    let (upgrade_tx, upgrade_rx) = oneshot::channel::<()>();
+    let (child_tx, child_rx) = oneshot::channel();
+
+    let router = Router::new()
+        .get("/", |_: Request| async { Ok("old") })
+        .get("/slow", |_: Request| async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            Ok("slow")
+        });
+    let server = tokio::spawn(
+        serve(listener, router)
+            .signal(async move {
+                let _ = upgrade_rx.await;
+                let mut command = Command::new(std::env::current_exe()?);
+                command.args(["activation_child", "--exact", "--nocapture"]);
+                let child = upgrader.upgrade(command).await;
+                let _ = child_tx.send(child);
+                std::io::Result::Ok(())
+            })
+            .into_future(),
+    );
+
+    assert_eq!(get(addr, "/").await?, "old");
+
+    // in-flight on the old process
+    let slow = tokio::spawn(get(addr, "/slow"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let _ = upgrade_tx.send(());
+    let mut child = child_rx.await.map_err(Error::boxed)??;
+
+    // the old process drains
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .map_err(Error::boxed)?
+        .map_err(Error::boxed)??;
+    assert_eq!(slow.await.map_err(Error::boxed)??, "slow");
+
+    // the same socket is served by the new process
+    assert_eq!(get(addr, "/").await?, "new");
+
+    child.kill()?;
+    child.wait()?;
+
+    Ok(())
+}
+
+/// The process which is started by `activation_listen_fds` like systemd.
+#[tokio::test]
+async fn activation_listen_child() -> Result<()> {
+    if std::env::var_os("LISTEN_FDS").is_none() {
+        return Ok(());
+    }
+
+    let mut fds = activation::listen_fds()?;
+    assert_eq!(fds.len(), 1);
+    assert_eq!(fds[0].name(), "http");
+    // taken only once, and not inherited by the children
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        assert!(std::env::var_os(name).is_none());
+    }
+    assert!(activation::listen_fds()?.is_empty());
+    assert!(
+        rustix::io::fcntl_getfd(&fds[0])
+            .map_err(Error::boxed)?
+            .contains(rustix::io::FdFlags::CLOEXEC)
+    );
+
+    let listener = fds.remove(0).into_tcp()?;
+    let server = serve(
+        listener,
+        Router::new().get("/", |_: Request| async { Ok("activated") }),
+    );
+
+    // killed by the parent
+    let _ = tokio::time::timeout(Duration::from_secs(10), server.into_future()).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn activation_listen_fds() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    // the socket is passed as the stdin, then as the descriptor `3` of the process `$$`
+    let fd = rustix::io::dup(&listener).map_err(Error::boxed)?;
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(r#"exec 3<&0 0</dev/null; LISTEN_PID=$$ LISTEN_FDS=1 LISTEN_FDNAMES=http exec "$0" activation_listen_child --exact --nocapture"#)
+        .arg(std::env::current_exe()?)
+        .stdin(Stdio::from(fd))
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    let res = tokio::time::timeout(Duration::from_secs(10), get(addr, "/")).await;
+
+    child.kill()?;
+    child.wait()?;
+
+    assert_eq!(res.map_err(Error::boxed)??, "activated");
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! HTTP/3 test cases
+
+#![cfg(feature = "http3")]
+
+use std::{net::SocketAddr, sync::Arc};
+
+use bytes::{Buf, Bytes};
+use quinn::{Endpoint, crypto::rustls::QuicClientConfig};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, crypto::aws_lc_rs};
+use vidi::{
+    Error, Request, RequestExt, Result, Router,
+    header::ALT_SVC,
+    http3::QuicListener,
+    serve,
+    tls::{TlsInfo, rustls::Config},
+};
+
+fn file(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/tests/tls/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+fn router() -> Router {
+    Router::new()
+        .get("/", |mut req: Request| async move {
+            let info = req.extract::<TlsInfo>().await?;
+            Ok(format!(
+                "{:?} {}",
+                req.version(),
+                String::from_utf8_lossy(&info.alpn.unwrap_or_default())
+            ))
+        })
+        .post(
+            "/echo",
+            |mut req: Request| async move { Ok(req.text().await?) },
+        )
+}
+
+async fn request(
+    addr: SocketAddr,
+    req: http::Request<()>,
+    body: &'static str,
+) -> Result<(http::Response<()>, String)> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut file("ca.pem").as_slice()) {
+        roots.add(cert?).map_err(Error::boxed)?;
+    }
+    let mut config = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h3".to_vec()];
+
+    let mut endpoint = Endpoint::client(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+    endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+        QuicClientConfig::try_from(config).map_err(Error::boxed)?,
+    )));
+    let conn = endpoint
+        .connect(addr, "localhost")
+        .map_err(Error::boxed)?
+        .await
+        .map_err(Error::boxed)?;
+
+    let (mut driver, mut sender) = h3::client::new(h3_quinn::Connection::new(conn))
+        .await
+        .map_err(Error::boxed)?;
+    tokio::spawn(async move { std::future::poll_fn(|cx| driver.poll_close(cx)).await });
+
+    let mut stream = sender.send_request(req).await.map_err(Error::boxed)?;
+    if !body.is_empty() {
+        stream
+            .send_data(Bytes::from_static(body.as_bytes()))
+            .await
+            .map_err(Error::boxed)?;
+    }
+    stream.finish().await.map_err(Error::boxed)?;
+
+    let resp = stream.recv_response().await.map_err(Error::boxed)?;
+    let mut buf = Vec::new();
+    while let Some(mut chunk) = stream.recv_data().await.map_err(Error::boxed)? {
+        buf.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+
+    Ok((resp, String::from_utf8(buf).map_err(Error::boxed)?))
+}
+
+#[tokio::test]
+async fn http3() -> Result<()> {
+    let config = Config::new()
+        .cert(file("default.pem"))
+        .key(file("default.key"))
+        .build()?;
+    let listener = QuicListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)), config)?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(vidi::http3::serve(listener, router()).into_future());
+
+    let (resp, body) = request(
+        addr,
+        http::Request::get("https://localhost/").body(()).unwrap(),
+        "",
+    )
+    .await?;
+    assert_eq!(resp.status(), http::StatusCode::OK);
+    assert_eq!(body, "HTTP/3.0 h3");
+
+    let (resp, body) = request(
+        addr,
+        http::Request::post("https://localhost/echo")
+            .body(())
+            .unwrap(),
+        "Hello, HTTP/3",
+    )
+    .await?;
+    assert_eq!(resp.status(), http::StatusCode::OK);
+    assert_eq!(body, "Hello, HTTP/3");
+
+    let (resp, _) = request(
+        addr,
+        http::Request::get("https://localhost/missing")
+            .body(())
+            .unwrap(),
+        "",
+    )
+    .await?;
+    assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn http3_alt_svc() -> Result<()> {
+    let config = Config::new()
+        .cert(file("default.pem"))
+        .key(file("default.key"))
+        .build()?;
+    let quic = QuicListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)), config)?;
+    let quic_addr = quic.local_addr()?;
+    let port = quic_addr.port();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(
+        serve(
+            listener,
+            Router::new().get("/", |req: Request| async move {
+                Ok(format!("{:?}", req.version()))
+            }),
+        )
+        .http3(quic)
+        .into_future(),
+    );
+
+    // served with the same router
+    let (resp, body) = request(
+        quic_addr,
+        http::Request::get("https://localhost/").body(()).unwrap(),
+        "",
+    )
+    .await?;
+    assert_eq!(resp.status(), http::StatusCode::OK);
+    assert!(resp.headers().get(ALT_SVC).is_none());
+    assert_eq!(body, "HTTP/3.0");
+
+    for path in ["/", "/missing"] {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await?;
+        let mut buf = String::new();
+        stream.read_to_string(&mut buf).await?;
+        assert!(
+            buf.to_lowercase()
+                .contains(&format!("{ALT_SVC}: h3=\":{port}\"; ma=86400")),
+            "{buf}"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,251 @@
+//! PROXY protocol test cases
+
+#![cfg(feature = "proxy-protocol")]
+
+use std::net::SocketAddr;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use vidi::{
+    Listener, Request, RequestExt, Result, Router,
+    proxy_protocol::{ProxyHeader, ProxyListener},
+    serve,
+};
+
+fn router() -> Router {
+    Router::new().get("/", |mut req: Request| async move {
+        let header = req.extract::<Option<ProxyHeader>>().await?;
+        Ok(format!(
+            "{} {}",
+            req.remote_addr().unwrap(),
+            header
+                .as_ref()
+                .and_then(ProxyHeader::authority)
+                .unwrap_or("-")
+        ))
+    })
+}
+
+async fn trusted() -> Result<ProxyListener<TcpListener>> {
+    Ok(ProxyListener::new(TcpListener::bind("127.0.0.1:0").await?)
+        .trusted(["127.0.0.0/8".parse().unwrap()]))
+}
+
+fn listen(listener: ProxyListener<TcpListener>) -> Result<SocketAddr> {
+    let addr = listener.local_addr()?;
+    tokio::spawn(serve(listener, router()).into_future());
+    Ok(addr)
+}
+
+async fn request(addr: SocketAddr, header: &[u8]) -> std::io::Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(header).await?;
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).await?;
+    Ok(buf)
+}
+
+fn body(resp: &str) -> &str {
+    resp.split("\r\n\r\n").nth(1).unwrap_or_default()
+}
+
+fn v2(family: u8, addrs: &[u8], tlvs: &[(u8, &[u8])]) -> Vec<u8> {
+    let mut payload = addrs.to_vec();
+    for (kind, value) in tlvs {
+        payload.push(*kind);
+        payload.extend_from_slice(&u16::try_from(value.len()).unwrap().to_be_bytes());
+        payload.extend_from_slice(value);
+    }
+    let mut header = b"\r\n\r\n\0\r\nQUIT\n\x21".to_vec();
+    header.push(family);
+    header.extend_from_slice(&u16::try_from(payload.len()).unwrap().to_be_bytes());
+    header.extend_from_slice(&payload);
+    header
+}
+
+#[tokio::test]
+async fn proxy_protocol_v1() -> Result<()> {
+    let addr = listen(trusted().await?)?;
+
+    let resp = request(addr, b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n").await?;
+    assert_eq!(body(&resp), "192.168.0.1:56324 -");
+
+    let resp = request(addr, b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 443\r\n").await?;
+    assert_eq!(body(&resp), "[2001:db8::1]:4000 -");
+
+    // the address of the connection
+    let resp = request(addr, b"PROXY UNKNOWN\r\n").await?;
+    assert!(body(&resp).starts_with("127.0.0.1:"));
+
+    // rejected
+    for header in [
+        &b"GET / HTTP/1.1\r\n"[..],
+        b"PROXY TCP4 192.168.0.1 10.0.0.1 56324\r\n",
+        b"PROXY TCP4 vidi.rs 10.0.0.1 56324 443\r\n",
+        // the addresses do not match the family
+        b"PROXY TCP4 ::1 ::1 56324 443\r\n",
+        b"PROXY TCP6 192.168.0.1 10.0.0.1 56324 443\r\n",
+        b"PROXY TCP6 2001:db8::1 10.0.0.1 56324 443\r\n",
+    ] {
+        let resp = request(addr, header).await.unwrap_or_default();
+        assert!(resp.is_empty(), "{resp}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn proxy_protocol_v2() -> Result<()> {
+    let addr = listen(trusted().await?)?;
+
+    let header = v2(
+        0x11,
+        &[192, 168, 0, 1, 10, 0, 0, 1, 0xdc, 0x04, 0x01, 0xbb],
+        &[
+            (ProxyHeader::AUTHORITY, b"vidi.rs"),
+            (ProxyHeader::NOOP, b""),
+        ],
+    );
+    let resp = request(addr, &header).await?;
+    assert_eq!(body(&resp), "192.168.0.1:56324 vidi.rs");
+
+    let mut addrs = [0; 36];
+    addrs[..16].copy_from_slice(
+        &"2001:db8::1"
+            .parse::<std::net::Ipv6Addr>()
+            .unwrap()
+            .octets(),
+    );
+    addrs[32..].copy_from_slice(&[0x0f, 0xa0, 0x01, 0xbb]);
+    let resp = request(addr, &v2(0x21, &addrs, &[])).await?;
+    assert_eq!(body(&resp), "[2001:db8::1]:4000 -");
+
+    // LOCAL command, e.g. the health checks
+    let mut header = v2(0x00, &[], &[]);
+    header[12] = 0x20;
+    let resp = request(addr, &header).await?;
+    assert!(body(&resp).starts_with("127.0.0.1:"));
+
+    // a truncated TLV
+    let mut header = v2(
+        0x11,
+        &[192, 168, 0, 1, 10, 0, 0, 1, 0, 1, 0, 2],
+        &[(0x02, b"vidi")],
+    );
+    header[15] -= 1;
+    let resp = request(addr, &header[..header.len() - 1])
+        .await
+        .unwrap_or_default();
+    assert!(resp.is_empty(), "{resp}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn proxy_protocol_untrusted() -> Result<()> {
+    let addr = listen(
+        ProxyListener::new(TcpListener::bind("127.0.0.1:0").await?)
+            .trusted(["10.0.0.0/8".parse().unwrap()]),
+    )?;
+
+    // the header is not read from the untrusted sources
+    let resp = request(addr, b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n").await?;
+    assert!(resp.starts_with("HTTP/1.1 400"), "{resp}");
+
+    let resp = request(addr, b"").await?;
+    assert!(body(&resp).starts_with("127.0.0.1:"));
+
+    // no source is trusted by default
+    let addr = listen(ProxyListener::new(TcpListener::bind("127.0.0.1:0").await?))?;
+    let resp = request(addr, b"").await?;
+    assert!(body(&resp).starts_with("127.0.0.1:"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn proxy_protocol_pending() -> Result<()> {
+    let addr = listen(trusted().await?)?;
+
+    // the incomplete headers do not block the other connections
+    let mut slow = TcpStream::connect(addr).await?;
+    slow.write_all(b"PROXY TCP4 ").await?;
+    let mut invalid = TcpStream::connect(addr).await?;
+    invalid.write_all(b"GET / HTTP/1.1\r\n").await?;
+
+    let resp = request(addr, b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n").await?;
+    assert_eq!(body(&resp), "192.168.0.1:56324 -");
+
+    slow.write_all(b"192.168.0.2 10.0.0.1 56324 443\r\n")
+        .await?;
+    slow.write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut buf = String::new();
+    slow.read_to_string(&mut buf).await?;
+    assert_eq!(body(&buf), "192.168.0.2:56324 -");
+
+    let mut buf = String::new();
+    invalid.read_to_string(&mut buf).await.unwrap_or_default();
+    assert!(buf.is_empty(), "{buf}");
+
+    Ok(())
+}
+
+#[cfg(feature = "rustls")]
+#[tokio::test]
+async fn proxy_protocol_tls() -> Result<()> {
+    use std::sync::Arc;
+
+    use tokio_rustls::{
+        TlsConnector,
+        rustls::{ClientConfig, RootCertStore, crypto::aws_lc_rs, pki_types::ServerName},
+    };
+    use vidi::tls::{
+        TlsListener,
+        rustls::{Config, TlsAcceptor},
+    };
+
+    let file = |name: &str| {
+        std::fs::read(format!("{}/tests/tls/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap()
+    };
+
+    let config = Config::new()
+        .cert(file("default.pem"))
+        .key(file("default.key"))
+        .build()?;
+    let listener = trusted().await?;
+    let addr = listener.local_addr()?;
+    let listener = TlsListener::new(listener, TlsAcceptor::from(Arc::new(config)));
+    tokio::spawn(serve(listener, router()).into_future());
+
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut file("ca.pem").as_slice()) {
+        roots.add(cert?).unwrap();
+    }
+    let config = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n")
+        .await?;
+    let mut stream = TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await?;
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).await?;
+    assert_eq!(body(&buf), "192.168.0.1:56324 -");
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Static file serving test cases
+
+#![cfg(all(unix, feature = "serve"))]
+
+use std::{net::SocketAddr, process::Command, sync::Arc};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Notify,
+};
+use vidi::{Error, Handler, Request, Result, Router, handlers::serve, serve};
+
+const SIZE: usize = 4 * 1024;
+
+async fn get(addr: SocketAddr, path: &str) -> Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n").as_bytes(),
+        )
+        .await?;
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
+fn body(res: &[u8]) -> usize {
+    res.windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map_or(0, |n| res.len() - n - 4)
+}
+
+/// A request is blocked on opening a FIFO without a writer, another request on the single
+/// thread runtime must still complete.
+#[tokio::test(flavor = "current_thread")]
+async fn serve_blocked_file_does_not_stall_runtime() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("vidi-serve-blocked-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("small.bin"), vec![b'v'; SIZE])?;
+
+    // the file is replaced with a FIFO after the handler is created
+    let fifo = dir.join("blocked.bin");
+    std::fs::write(&fifo, "")?;
+    let blocked = serve::File::new(&fifo)?;
+    std::fs::remove_file(&fifo)?;
+    assert!(Command::new("mkfifo").arg(&fifo).status()?.success());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let opening = Arc::new(Notify::new());
+    let router = Router::new()
+        .get("/blocked.bin", {
+            let opening = opening.clone();
+            move |req: Request| {
+                let opening = opening.clone();
+                let blocked = blocked.clone();
+                async move {
+                    opening.notify_one();
+                    blocked.call(req).await
+                }
+            }
+        })
+        .get("/*", serve::Dir::new(&dir)?);
+    tokio::spawn(serve(listener, router).into_future());
+
+    let pending = tokio::spawn(get(addr, "/blocked.bin"));
+    opening.notified().await;
+
+    let res = get(addr, "/small.bin").await?;
+    assert!(res.starts_with(b"HTTP/1.1 200"));
+    assert_eq!(body(&res), SIZE);
+
+    // a writer unblocks the open, the FIFO is empty
+    drop(std::fs::OpenOptions::new().write(true).open(&fifo)?);
+    let res = pending.await.map_err(Error::boxed)??;
+    assert!(res.starts_with(b"HTTP/1.1 200"));
+    assert_eq!(body(&res), 0);
+
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+//! Server test cases
+
+use std::{net::SocketAddr, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use vidi::{Request, Result, Router, Server, ServerConfig};
+
+async fn listen(config: &ServerConfig) -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let router = Router::new().get("/", |_: Request| async { Ok("vidi") });
+    tokio::spawn(Server::with_config(listener, router, config).into_future());
+    Ok(addr)
+}
+
+#[tokio::test]
+async fn server_config_max_headers() -> Result<()> {
+    let addr = listen(&ServerConfig::new().max_headers(4)).await?;
+
+    for (headers, status) in [(2, "200 OK"), (8, "431 Request Header Fields Too Large")] {
+        let mut stream = TcpStream::connect(addr).await?;
+        let req = (0..headers).fold(
+            String::from("GET / HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n"),
+            |req, i| req + &format!("x-{i}: {i}\r\n"),
+        ) + "\r\n";
+        stream.write_all(req.as_bytes()).await?;
+
+        let mut buf = String::new();
+        stream.read_to_string(&mut buf).await?;
+        assert!(buf.starts_with(&format!("HTTP/1.1 {status}")), "{buf}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_config_header_read_timeout() -> Result<()> {
+    let addr = listen(&ServerConfig::new().header_read_timeout(Duration::from_millis(100))).await?;
+
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\n")
+        .await?;
+
+    // the connection is closed without completing the headers
+    let mut buf = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut buf))
+        .await
+        .map_err(vidi::Error::boxed)??;
+    assert!(!buf.starts_with(b"HTTP/1.1 200"));
+
+    Ok(())
+}
+
+async fn bind() -> Result<(TcpListener, SocketAddr)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    Ok((listener, addr))
+}
+
+fn router() -> Router {
+    Router::new().get("/", |_: Request| async { Ok("vidi") })
+}
+
+/// Sends a keep-alive request and reads the response.
+async fn get(stream: &mut TcpStream) -> Result<String> {
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\n\r\n")
+        .await?;
+    let mut buf = Vec::new();
+    while !buf.ends_with(b"vidi") {
+        let mut chunk = [0; 1024];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[tokio::test]
+async fn server_max_connections() -> Result<()> {
+    let (listener, addr) = bind().await?;
+    let server = Server::new(listener, router()).max_connections(1);
+    let stats = server.stats();
+    tokio::spawn(server.into_future());
+
+    let mut first = TcpStream::connect(addr).await?;
+    assert!(get(&mut first).await?.starts_with("HTTP/1.1 200"));
+
+    // waits in the backlog
+    let mut second = TcpStream::connect(addr).await?;
+    let second = async move { get(&mut second).await };
+    tokio::pin!(second);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), &mut second)
+            .await
+            .is_err()
+    );
+    assert_eq!(stats.active(), 1);
+    assert_eq!(stats.accepted(), 1);
+
+    drop(first);
+    let resp = tokio::time::timeout(Duration::from_secs(2), second)
+        .await
+        .map_err(vidi::Error::boxed)??;
+    assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+    assert_eq!(stats.accepted(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_max_connections_per_ip() -> Result<()> {
+    let (listener, addr) = bind().await?;
+    let server = Server::new(listener, router()).max_connections_per_ip(1);
+    let stats = server.stats();
+    tokio::spawn(server.into_future());
+
+    let mut first = TcpStream::connect(addr).await?;
+    assert!(get(&mut first).await?.starts_with("HTTP/1.1 200"));
+
+    // closed immediately
+    let mut second = TcpStream::connect(addr).await?;
+    let resp = tokio::time::timeout(Duration::from_secs(2), get(&mut second))
+        .await
+        .map_err(vidi::Error::boxed)?;
+    assert!(resp.is_err() || resp.is_ok_and(|resp| resp.is_empty()));
+    assert_eq!(stats.rejected(), 1);
+
+    // the slot is released
+    drop(first);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut third = TcpStream::connect(addr).await?;
+    assert!(get(&mut third).await?.starts_with("HTTP/1.1 200"));
+    assert_eq!(stats.accepted(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_idle_timeout() -> Result<()> {
+    let (listener, addr) = bind().await?;
+    let server = Server::new(listener, router()).idle_timeout(Duration::from_millis(200));
+    let stats = server.stats();
+    tokio::spawn(server.into_future());
+
+    let mut stream = TcpStream::connect(addr).await?;
+    assert!(get(&mut stream).await?.starts_with("HTTP/1.1 200"));
+
+    // the keep-alive connection is closed once idle
+    let mut buf = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut buf))
+        .await
+        .map_err(vidi::Error::boxed)??;
+    assert!(buf.is_empty());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(stats.idle_timeouts(), 1);
+    assert_eq!(stats.active(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_idle_timeout_in_flight() -> Result<()> {
+    let (listener, addr) = bind().await?;
+    let router = Router::new().get("/", |_: Request| async {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        Ok("vidi")
+    });
+    let server = Server::new(listener, router).idle_timeout(Duration::from_millis(100));
+    let stats = server.stats();
+    tokio::spawn(server.into_future());
+
+    // the slow handler is not closed
+    let mut stream = TcpStream::connect(addr).await?;
+    assert!(get(&mut stream).await?.starts_with("HTTP/1.1 200"));
+    assert_eq!(stats.idle_timeouts(), 0);
+
+    // the idle timeout is applied between the requests
+    let mut buf = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut buf))
+        .await
+        .map_err(vidi::Error::boxed)??;
+    assert!(buf.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_multiple_listeners() -> Result<()> {
+    let (first, first_addr) = bind().await?;
+    let (second, second_addr) = bind().await?;
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+    let server = Server::new(first, router()).listener(second);
+    #[cfg(all(unix, feature = "unix-socket"))]
+    let (server, path) = {
+        let path = std::env::temp_dir().join(format!("vidi-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        (
+            server.listener(tokio::net::UnixListener::bind(&path)?),
+            path,
+        )
+    };
+    let stats = server.stats();
+    let server = tokio::spawn(server.signal(rx).into_future());
+
+    for addr in [first_addr, second_addr] {
+        let mut stream = TcpStream::connect(addr).await?;
+        assert!(get(&mut stream).await?.starts_with("HTTP/1.1 200"));
+    }
+
+    #[cfg(all(unix, feature = "unix-socket"))]
+    {
+        let mut stream = tokio::net::UnixStream::connect(&path).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut buf = String::new();
+        stream.read_to_string(&mut buf).await?;
+        assert!(buf.starts_with("HTTP/1.1 200"), "{buf}");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    assert!(stats.accepted() >= 2);
+
+    // a signal stops all listeners
+    let _ = tx.send(());
+    tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .map_err(vidi::Error::boxed)?
+        .map_err(vidi::Error::boxed)??;
+
+    for addr in [first_addr, second_addr] {
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_graceful_shutdown() -> Result<()> {
+    let (listener, addr) = bind().await?;
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+    let router = Router::new().get("/", |_: Request| async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        Ok("vidi")
+    });
+    let server = tokio::spawn(Server::new(listener, router).signal(rx).into_future());
+
+    // the in-flight request is completed before the server returns
+    let mut stream = TcpStream::connect(addr).await?;
+    let pending = tokio::spawn(async move { get(&mut stream).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let _ = tx.send(());
+
+    let resp = pending.await.map_err(vidi::Error::boxed)??;
+    assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+    tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .map_err(vidi::Error::boxed)?
+        .map_err(vidi::Error::boxed)??;
+
+    Ok(())
+}
@@ -0,0 +1,335 @@
+//! TLS test cases
+
+#![cfg(feature = "rustls")]
+
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{ClientConfig, RootCertStore, crypto::aws_lc_rs, pki_types::ServerName},
+};
+use vidi::{
+    Request, RequestExt, Result, Router, Server, serve,
+    tls::{
+        self, TlsListener,
+        rustls::{Config, ReloadableAcceptor, ServerConfig},
+    },
+};
+
+const CERT: &[u8] = include_bytes!("../../examples/tls/cert.pem");
+const KEY: &[u8] = include_bytes!("../../examples/tls/key.pem");
+
+fn build(cert: &Path, key: &Path) -> Result<ServerConfig> {
+    Config::new()
+        .cert(std::fs::read(cert)?)
+        .key(std::fs::read(key)?)
+        .build()
+}
+
+#[tokio::test]
+async fn tls_reload() -> Result<()> {
+    let acceptor = ReloadableAcceptor::new(Config::new().cert(CERT).key(KEY).build()?);
+    let first = acceptor.config();
+
+    // bad files keep the previous config
+    assert!(
+        acceptor
+            .reload(|| Config::new().cert(CERT).key("").build())
+            .is_err()
+    );
+    assert!(Arc::ptr_eq(&first, &acceptor.config()));
+
+    assert!(
+        acceptor
+            .reload(|| Config::new().cert(CERT).key(KEY).build())
+            .is_ok()
+    );
+    assert!(!Arc::ptr_eq(&first, &acceptor.config()));
+
+    // triggered
+    let second = acceptor.config();
+    acceptor
+        .reload_on(futures_util::stream::iter([(), ()]), || {
+            Config::new().cert(CERT).key(KEY).build()
+        })
+        .await
+        .unwrap();
+    assert!(!Arc::ptr_eq(&second, &acceptor.config()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tls_watch_files() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("vidi-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let cert = dir.join("cert.pem");
+    let key = dir.join("key.pem");
+    std::fs::write(&cert, CERT)?;
+    std::fs::write(&key, KEY)?;
+
+    let acceptor = ReloadableAcceptor::new(build(&cert, &key)?);
+    let first = acceptor.config();
+
+    let handle = acceptor.watch([cert.clone(), key.clone()], Duration::from_millis(20), {
+        let (cert, key) = (cert.clone(), key.clone());
+        move || build(&cert, &key)
+    });
+
+    // a broken key is ignored
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    std::fs::write(&key, "broken")?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(Arc::ptr_eq(&first, &acceptor.config()));
+
+    // a fixed key is picked up
+    std::fs::write(&key, KEY)?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!Arc::ptr_eq(&first, &acceptor.config()));
+
+    handle.abort();
+    std::fs::remove_dir_all(dir)?;
+
+    Ok(())
+}
+
+fn file(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/tests/tls/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+async fn request(
+    addr: SocketAddr,
+    name: ServerName<'static>,
+) -> std::io::Result<(Vec<u8>, String)> {
+    request_with(addr, name, None).await
+}
+
+async fn request_with(
+    addr: SocketAddr,
+    name: ServerName<'static>,
+    client: Option<(&str, &str)>,
+) -> std::io::Result<(Vec<u8>, String)> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut file("ca.pem").as_slice()) {
+        roots.add(cert?).unwrap();
+    }
+    let builder = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots);
+    let config = match client {
+        Some((cert, key)) => builder
+            .with_client_auth_cert(
+                rustls_pemfile::certs(&mut file(cert).as_slice()).collect::<Result<_, _>>()?,
+                rustls_pemfile::private_key(&mut file(key).as_slice())?.unwrap(),
+            )
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+
+    let stream = TcpStream::connect(addr).await?;
+    let mut stream = TlsConnector::from(Arc::new(config))
+        .connect(name, stream)
+        .await?;
+    let cert = stream.get_ref().1.peer_certificates().unwrap()[0].to_vec();
+
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).await?;
+    let body = buf.split("\r\n\r\n").nth(1).unwrap_or_default().to_string();
+
+    Ok((cert, body))
+}
+
+fn der(name: &str) -> Vec<u8> {
+    rustls_pemfile::certs(&mut file(name).as_slice())
+        .next()
+        .unwrap()
+        .unwrap()
+        .to_vec()
+}
+
+#[tokio::test]
+async fn tls_sni() -> Result<()> {
+    let config = Config::new()
+        .cert(file("default.pem"))
+        .key(file("default.key"))
+        .sni("vidi.rs", file("vidi.pem"), file("vidi.key"))
+        .sni("*.viz.rs", file("wildcard.pem"), file("wildcard.key"))
+        .build()?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let listener = TlsListener::new(listener, tls::rustls::TlsAcceptor::from(Arc::new(config)));
+
+    let router = Router::new().get("/", |req: Request| async move {
+        Ok(req
+            .extensions()
+            .get::<tls::ServerName>()
+            .map_or_else(|| "-".to_string(), |name| name.0.clone()))
+    });
+    tokio::spawn(serve(listener, router).into_future());
+
+    let (cert, body) = request(addr, ServerName::try_from("vidi.rs").unwrap()).await?;
+    assert_eq!(cert, der("vidi.pem"));
+    assert_eq!(body, "vidi.rs");
+
+    let (cert, body) = request(addr, ServerName::try_from("docs.viz.rs").unwrap()).await?;
+    assert_eq!(cert, der("wildcard.pem"));
+    assert_eq!(body, "docs.viz.rs");
+
+    // the wildcard matches a single label
+    assert!(
+        request(addr, ServerName::try_from("a.docs.viz.rs").unwrap())
+            .await
+            .is_err()
+    );
+
+    // without SNI
+    let (cert, body) = request(addr, ServerName::try_from("127.0.0.1").unwrap()).await?;
+    assert_eq!(cert, der("default.pem"));
+    assert_eq!(body, "-");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tls_info() -> Result<()> {
+    let config = Config::new()
+        .cert(file("default.pem"))
+        .key(file("default.key"))
+        .client_auth_optional(file("ca.pem"))
+        .build()?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let listener = TlsListener::new(listener, tls::rustls::TlsAcceptor::from(Arc::new(config)));
+
+    let router = Router::new().get("/", |mut req: Request| async move {
+        let info = req.extract::<tls::TlsInfo>().await?;
+        let peer = info.peer_certificate();
+        Ok(format!(
+            "{:?}|{:?}|{:?}|{}|{}",
+            info.version,
+            peer.and_then(tls::PeerCertificate::subject),
+            peer.map(tls::PeerCertificate::subject_alt_names),
+            info.cipher_suite.is_some(),
+            req.schema().map(ToString::to_string).unwrap_or_default(),
+        ))
+    });
+    tokio::spawn(serve(listener, router).into_future());
+
+    let name = ServerName::try_from("localhost").unwrap();
+
+    let (_, body) = request_with(addr, name.clone(), Some(("client.pem", "client.key"))).await?;
+    assert_eq!(
+        body,
+        format!(
+            "{:?}|{:?}|{:?}|true|https",
+            Some("TLSv1_3"),
+            Some("CN=client"),
+            Some(vec![
+                tls::SubjectAltName::Dns("client.vidi.rs".to_string()),
+                tls::SubjectAltName::Email("client@vidi.rs".to_string()),
+            ]),
+        )
+    );
+
+    // without a client certificate
+    let (_, body) = request(addr, name).await?;
+    assert_eq!(body, r#"Some("TLSv1_3")|None|None|true|https"#);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tls_handshake_error_no_backoff() -> Result<()> {
+    let config = Config::new()
+        .cert(file("default.pem"))
+        .key(file("default.key"))
+        .build()?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let listener = TlsListener::new(listener, tls::rustls::TlsAcceptor::from(Arc::new(config)));
+
+    let server = Server::new(
+        listener,
+        Router::new().get("/", |_: Request| async { Ok("vidi") }),
+    )
+    .accept_backoff(Duration::from_secs(10), Duration::from_secs(10));
+    let stats = server.stats();
+    tokio::spawn(server.into_future());
+
+    // a plain text client fails the handshake
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: vidi\r\n\r\n")
+        .await?;
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf).await;
+
+    // the next client is accepted without a backoff
+    let (_, body) = tokio::time::timeout(
+        Duration::from_secs(2),
+        request(addr, ServerName::try_from("localhost").unwrap()),
+    )
+    .await
+    .map_err(vidi::Error::boxed)??;
+    assert_eq!(body, "vidi");
+    assert_eq!(stats.accept_errors(), 1);
+
+    Ok(())
+}
+
+#[cfg(feature = "http2")]
+#[tokio::test]
+async fn tls_alpn() -> Result<()> {
+    let config = Config::new()
+        .cert(file("default.pem"))
+        .key(file("default.key"))
+        .build()?;
+    assert_eq!(
+        config.alpn_protocols,
+        [b"h2".to_vec(), b"http/1.1".to_vec()]
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let listener = TlsListener::new(listener, tls::rustls::TlsAcceptor::from(Arc::new(config)));
+
+    tokio::spawn(serve(listener, Router::new()).into_future());
+
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut file("ca.pem").as_slice()) {
+        roots.add(cert?).unwrap();
+    }
+
+    for (protocols, expected) in [
+        (vec![b"h2".to_vec(), b"http/1.1".to_vec()], Some(&b"h2"[..])),
+        (vec![b"http/1.1".to_vec()], Some(&b"http/1.1"[..])),
+        (vec![], None),
+    ] {
+        let mut config =
+            ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots.clone())
+                .with_no_client_auth();
+        config.alpn_protocols = protocols;
+
+        let stream = TcpStream::connect(addr).await?;
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await?;
+        assert_eq!(stream.get_ref().1.alpn_protocol(), expected);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! tus test cases
+
+#![cfg(feature = "tus")]
+
+use std::net::SocketAddr;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use vidi::{Result, Router, handlers::tus::Tus, serve};
+
+async fn request(addr: SocketAddr, head: &str, body: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(
+            format!(
+                "{head}\r\nHost: vidi\r\nConnection: close\r\nTus-Resumable: 1.0.0\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).await?;
+    Ok(buf.to_lowercase())
+}
+
+#[tokio::test]
+async fn tus_resumable_upload() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("vidi-tus-{}", std::process::id()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let tus = Tus::new(&dir)?.max_size(1024);
+    let router = Router::new()
+        .any("/files/", tus.clone())
+        .any("/files/:id", tus);
+    tokio::spawn(serve(listener, router).into_future());
+
+    let res = request(addr, "POST /files/ HTTP/1.1\r\nUpload-Length: 11", "").await?;
+    assert!(res.starts_with("http/1.1 201"), "{res}");
+    assert!(res.contains("tus-resumable: 1.0.0"));
+    let location = res
+        .lines()
+        .find_map(|line| line.strip_prefix("location: "))
+        .unwrap()
+        .to_string();
+
+    let res = request(
+        addr,
+        &format!(
+            "PATCH {location} HTTP/1.1\r\nUpload-Offset: 0\r\nContent-Type: application/offset+octet-stream"
+        ),
+        "hello ",
+    )
+    .await?;
+    assert!(res.starts_with("http/1.1 204"), "{res}");
+    assert!(res.contains("upload-offset: 6"));
+
+    let res = request(addr, &format!("HEAD {location} HTTP/1.1"), "").await?;
+    assert!(res.starts_with("http/1.1 200"), "{res}");
+    assert!(res.contains("upload-offset: 6"));
+    assert!(res.contains("upload-length: 11"));
+
+    let res = request(
+        addr,
+        &format!(
+            "PATCH {location} HTTP/1.1\r\nUpload-Offset: 6\r\nContent-Type: application/offset+octet-stream"
+        ),
+        "world",
+    )
+    .await?;
+    assert!(res.contains("upload-offset: 11"), "{res}");
+    let id = location.strip_prefix("/files/").unwrap();
+    assert_eq!(std::fs::read_to_string(dir.join(id))?, "hello world");
+
+    let res = request(addr, &format!("DELETE {location} HTTP/1.1"), "").await?;
+    assert!(res.starts_with("http/1.1 204"), "{res}");
+    assert!(!dir.join(id).exists());
+
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! `WebDAV` test cases
+
+#![cfg(feature = "webdav")]
+
+use std::net::SocketAddr;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use vidi::{
+    Result, Route, Router,
+    handlers::webdav::{self, WebDav},
+    serve,
+};
+
+async fn request(addr: SocketAddr, head: &str, body: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(
+            format!(
+                "{head}\r\nHost: vidi\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).await?;
+    Ok(buf)
+}
+
+#[tokio::test]
+async fn webdav_local_directory() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("vidi-webdav-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let dav = WebDav::new(&dir)?;
+    let route = webdav::methods().fold(Route::new(), |route, method| route.on(method, dav.clone()));
+    let router = Router::new()
+        .route("/dav/", route.clone())
+        .route("/dav/*", route);
+    tokio::spawn(serve(listener, router).into_future());
+
+    let res = request(addr, "MKCOL /dav/docs HTTP/1.1", "").await?;
+    assert!(res.starts_with("HTTP/1.1 201"), "{res}");
+    let res = request(addr, "PUT /dav/docs/readme.md HTTP/1.1", "# vidi").await?;
+    assert!(res.starts_with("HTTP/1.1 201"), "{res}");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("docs/readme.md"))?,
+        "# vidi"
+    );
+
+    let res = request(addr, "PROPFIND /dav/ HTTP/1.1\r\nDepth: 1", "").await?;
+    assert!(res.starts_with("HTTP/1.1 207"), "{res}");
+    assert!(res.contains("<D:href>/dav/docs/</D:href>"));
+    assert!(!res.contains("readme.md"));
+
+    let res = request(
+        addr,
+        "MOVE /dav/docs/readme.md HTTP/1.1\r\nDestination: http://vidi/dav/readme.md",
+        "",
+    )
+    .await?;
+    assert!(res.starts_with("HTTP/1.1 201"), "{res}");
+    let res = request(addr, "GET /dav/readme.md HTTP/1.1", "").await?;
+    assert!(res.ends_with("\r\n\r\n# vidi"), "{res}");
+
+    let res = request(addr, "DELETE /dav/docs HTTP/1.1", "").await?;
+    assert!(res.starts_with("HTTP/1.1 204"), "{res}");
+    assert!(!dir.join("docs").exists());
+
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}